@@ -0,0 +1,96 @@
+// 附件：元数据存放在 attachment 表，内容按 SHA-256 去重后交给 BlobStore 保存
+
+use crate::database::Attachment;
+use crate::repository::{Repositories, Result};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+
+pub const OWNER_MATTER: &str = "matter";
+pub const OWNER_TODO: &str = "todo";
+
+// 单个附件大小上限 20MB
+pub const MAX_ATTACHMENT_SIZE: usize = 20 * 1024 * 1024;
+
+pub fn content_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+pub fn save(
+    storage: &Repositories,
+    owner_type: &str,
+    owner_id: &str,
+    file_name: &str,
+    content_type: &str,
+    data: &[u8],
+) -> Result<Attachment> {
+    let hash = content_hash(data);
+    storage.blobs.put(&hash, data)?;
+
+    let attachment = Attachment {
+        id: uuid::Uuid::new_v4().to_string(),
+        owner_type: owner_type.to_string(),
+        owner_id: owner_id.to_string(),
+        file_name: file_name.to_string(),
+        content_type: content_type.to_string(),
+        size: data.len() as i64,
+        hash,
+        created_at: Utc::now(),
+    };
+    storage.attachments.create(&attachment)?;
+    Ok(attachment)
+}
+
+pub fn read(storage: &Repositories, attachment: &Attachment) -> Result<Option<Vec<u8>>> {
+    storage.blobs.get(&attachment.hash)
+}
+
+// 删除附件记录，内容不再被任何记录引用时一并删除
+pub fn delete(storage: &Repositories, attachment: &Attachment) -> Result<()> {
+    storage.attachments.delete(&attachment.id)?;
+    release_blob(storage, &attachment.hash)
+}
+
+// matter / todo 被删除时调用
+pub fn delete_by_owner(storage: &Repositories, owner_type: &str, owner_id: &str) -> Result<()> {
+    for attachment in storage.attachments.get_by_owner(owner_type, owner_id)? {
+        delete(storage, &attachment)?;
+    }
+    Ok(())
+}
+
+fn release_blob(storage: &Repositories, hash: &str) -> Result<()> {
+    if storage.attachments.count_by_hash(hash)? == 0 {
+        storage.blobs.remove(hash)?;
+    }
+    Ok(())
+}
+
+pub fn owner_exists(storage: &Repositories, owner_type: &str, owner_id: &str) -> Result<bool> {
+    match owner_type {
+        OWNER_MATTER => Ok(storage.matters.get_by_id(owner_id)?.is_some()),
+        OWNER_TODO => Ok(storage.todos.get_by_id(owner_id)?.is_some()),
+        _ => Ok(false),
+    }
+}
+
+// 清理所属对象已不存在的附件，以及没有任何记录引用的内容文件
+// 返回删除的内容文件数量
+pub fn sweep_orphans(storage: &Repositories) -> Result<usize> {
+    let mut referenced = std::collections::HashSet::new();
+    for attachment in storage.attachments.get_all()? {
+        if owner_exists(storage, &attachment.owner_type, &attachment.owner_id)? {
+            referenced.insert(attachment.hash);
+        } else {
+            storage.attachments.delete(&attachment.id)?;
+        }
+    }
+
+    let mut removed = 0;
+    for hash in storage.blobs.list()? {
+        if !referenced.contains(&hash) {
+            storage.blobs.remove(&hash)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
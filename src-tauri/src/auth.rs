@@ -0,0 +1,176 @@
+// HTTP API 的令牌认证：请求需要带 Authorization: Bearer <token>
+// 令牌只以 SHA-256 哈希保存在数据库中，明文只在创建时返回一次；
+// read 令牌只能发 GET/HEAD 请求，write 令牌可以读写。
+// 应用自己的 webview 通过 get_app_api_token 命令拿到本次启动生成的令牌，拥有全部权限
+
+use crate::database::ApiToken;
+use crate::reminder;
+use crate::repository::Repositories;
+use axum::http::{header, HeaderMap, Method, Uri};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::command;
+use thiserror::Error;
+
+pub const SCOPE_READ: &str = "read";
+pub const SCOPE_WRITE: &str = "write";
+
+const TOKEN_PREFIX: &str = "fates_";
+// 列表中显示的明文长度（含 fates_ 前缀）
+const DISPLAY_PREFIX_LEN: usize = 12;
+// FATES_API_AUTH=off 时不检查令牌，仅用于本地调试
+const AUTH_ENV: &str = "FATES_API_AUTH";
+
+// EventSource 和浏览器的 WebSocket 不能设置请求头，事件接口允许用 ?access_token= 传令牌
+const QUERY_TOKEN_PATHS: &[&str] = &["/events", "/events/ws"];
+
+// 每次启动重新生成，不落盘；比较时只比较哈希
+static APP_TOKEN: Lazy<String> = Lazy::new(generate_token);
+static APP_TOKEN_HASH: Lazy<String> = Lazy::new(|| hash_token(&APP_TOKEN));
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("缺少访问令牌")]
+    MissingToken,
+    #[error("访问令牌无效")]
+    InvalidToken,
+    #[error("令牌没有写入权限")]
+    ReadOnly,
+    #[error("存储错误：{0}")]
+    Storage(String),
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedToken {
+    // 明文令牌，只在创建时返回
+    pub token: String,
+    #[serde(flatten)]
+    pub info: ApiToken,
+}
+
+fn generate_token() -> String {
+    format!(
+        "{}{}{}",
+        TOKEN_PREFIX,
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn auth_disabled() -> bool {
+    std::env::var(AUTH_ENV).is_ok_and(|v| v.eq_ignore_ascii_case("off"))
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+// 请求携带的令牌，优先使用 Authorization 头
+pub fn request_token<'a>(headers: &'a HeaderMap, uri: &'a Uri) -> Option<&'a str> {
+    bearer_token(headers).or_else(|| {
+        if !QUERY_TOKEN_PATHS.contains(&uri.path()) {
+            return None;
+        }
+        uri.query()?
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("access_token="))
+            .filter(|token| !token.is_empty())
+    })
+}
+
+fn is_read_only(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD)
+}
+
+// 检查请求的令牌和权限
+pub fn authorize(
+    storage: &Repositories,
+    method: &Method,
+    token: Option<&str>,
+) -> Result<(), AuthError> {
+    if auth_disabled() {
+        return Ok(());
+    }
+    let token = token.ok_or(AuthError::MissingToken)?;
+    let token_hash = hash_token(token);
+    if token_hash == *APP_TOKEN_HASH {
+        return Ok(());
+    }
+
+    let record = storage
+        .api_tokens
+        .get_by_hash(&token_hash)
+        .map_err(|e| AuthError::Storage(e.to_string()))?
+        .ok_or(AuthError::InvalidToken)?;
+    if record.scope != SCOPE_WRITE && !is_read_only(method) {
+        return Err(AuthError::ReadOnly);
+    }
+    if let Err(e) = storage.api_tokens.touch(&record.id, Utc::now()) {
+        log::warn!("Failed to update token usage: {}", e);
+    }
+    Ok(())
+}
+
+pub fn create_token(
+    storage: &Repositories,
+    name: &str,
+    scope: &str,
+) -> Result<CreatedToken, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("令牌名称不能为空".to_string());
+    }
+    if scope != SCOPE_READ && scope != SCOPE_WRITE {
+        return Err(format!("无效的权限：{}", scope));
+    }
+    let token = generate_token();
+    let info = ApiToken {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        token_hash: hash_token(&token),
+        prefix: token[..DISPLAY_PREFIX_LEN].to_string(),
+        scope: scope.to_string(),
+        created_at: Utc::now(),
+        last_used_at: None,
+    };
+    storage
+        .api_tokens
+        .create(&info)
+        .map_err(|e| e.to_string())?;
+    Ok(CreatedToken { token, info })
+}
+
+// 只有应用自己的 webview 能调用命令，因此用它来下发令牌
+#[command]
+pub fn get_app_api_token() -> String {
+    APP_TOKEN.clone()
+}
+
+#[command]
+pub async fn list_api_tokens() -> Result<Vec<ApiToken>, String> {
+    let storage = reminder::command_storage().await?;
+    storage.api_tokens.get_all().map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn create_api_token(name: String, scope: String) -> Result<CreatedToken, String> {
+    let storage = reminder::command_storage().await?;
+    create_token(&storage, &name, &scope)
+}
+
+#[command]
+pub async fn delete_api_token(id: String) -> Result<(), String> {
+    let storage = reminder::command_storage().await?;
+    storage.api_tokens.delete(&id).map_err(|e| e.to_string())
+}
@@ -0,0 +1,199 @@
+// 浏览器来源（Origin）白名单：白名单内的来源可以跨域访问并通过预检请求，
+// 其他来源的预检和写请求直接拒绝，读请求不返回 CORS 头，浏览器无法读取结果。
+// 没有 Origin 头的请求（命令行工具、脚本）不受影响，仍由令牌认证把关
+
+use crate::http_server::ServerError;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+// 应用自己的 webview 和开发服务器
+pub const DEFAULT_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+    "http://localhost:1420",
+];
+
+const ALLOW_METHODS: &str = "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS";
+const ALLOW_HEADERS: &str = "Authorization, Content-Type, If-Match, If-None-Match";
+const EXPOSE_HEADERS: &str = "ETag, Location";
+const MAX_AGE_SECS: &str = "600";
+
+pub struct CorsPolicy {
+    // "*" 表示允许任意来源
+    origins: Vec<String>,
+}
+
+impl CorsPolicy {
+    pub fn new(origins: &[String]) -> Self {
+        Self {
+            origins: origins
+                .iter()
+                .map(|o| o.trim().trim_end_matches('/').to_ascii_lowercase())
+                .filter(|o| !o.is_empty())
+                .collect(),
+        }
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/').to_ascii_lowercase();
+        self.origins.iter().any(|o| o == "*" || *o == origin)
+    }
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD)
+}
+
+fn is_loopback(authority: &str) -> bool {
+    let host = match authority.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+// 浏览器对同源的写请求也会带 Origin，例如内嵌的 /docs 页面。
+// Host 头不可信（DNS 重绑定的页面的 Origin 与 Host 一致），只放行回环地址，其他来源须在白名单中
+fn is_same_origin(origin: &str, headers: &HeaderMap) -> bool {
+    let Some(host) = headers.get(header::HOST).and_then(|h| h.to_str().ok()) else {
+        return false;
+    };
+    origin.split_once("://").is_some_and(|(_, authority)| {
+        let authority = authority.trim_end_matches('/');
+        authority.eq_ignore_ascii_case(host) && is_loopback(authority)
+    })
+}
+
+fn rejected(origin: &str) -> Response {
+    ServerError::Forbidden(format!("来源不在白名单中：{}", origin)).into_response()
+}
+
+fn set_cors_headers(headers: &mut HeaderMap, origin: HeaderValue) {
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.insert(
+        header::ACCESS_CONTROL_EXPOSE_HEADERS,
+        HeaderValue::from_static(EXPOSE_HEADERS),
+    );
+    headers.append(header::VARY, HeaderValue::from_static("Origin"));
+}
+
+fn preflight(request: &Request, origin: HeaderValue) -> Response {
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    let headers = response.headers_mut();
+    set_cors_headers(headers, origin);
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_METHODS,
+        HeaderValue::from_static(ALLOW_METHODS),
+    );
+    // 按请求声明的头放行，认证仍由令牌完成
+    let allow_headers = request
+        .headers()
+        .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        .cloned()
+        .unwrap_or_else(|| HeaderValue::from_static(ALLOW_HEADERS));
+    headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allow_headers);
+    headers.insert(
+        header::ACCESS_CONTROL_MAX_AGE,
+        HeaderValue::from_static(MAX_AGE_SECS),
+    );
+    response
+}
+
+pub async fn handle(
+    State(policy): State<Arc<CorsPolicy>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(origin) = request.headers().get(header::ORIGIN).cloned() else {
+        return next.run(request).await;
+    };
+    let origin_str = origin.to_str().unwrap_or_default().to_string();
+    if is_same_origin(&origin_str, request.headers()) {
+        return next.run(request).await;
+    }
+    let allowed = policy.allows(&origin_str);
+    let is_preflight = request.method() == Method::OPTIONS
+        && request
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+    if is_preflight {
+        return if allowed {
+            preflight(&request, origin)
+        } else {
+            log::warn!("Rejected CORS preflight from {}", origin_str);
+            rejected(&origin_str)
+        };
+    }
+    if !allowed {
+        if is_safe_method(request.method()) {
+            return next.run(request).await;
+        }
+        log::warn!(
+            "Rejected {} {} from {}",
+            request.method(),
+            request.uri().path(),
+            origin_str
+        );
+        return rejected(&origin_str);
+    }
+
+    let mut response = next.run(request).await;
+    set_cors_headers(response.headers_mut(), origin);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn same_origin_only_for_loopback() {
+        assert!(is_same_origin(
+            "http://localhost:8523",
+            &host("localhost:8523")
+        ));
+        assert!(is_same_origin(
+            "http://127.0.0.1:8523",
+            &host("127.0.0.1:8523")
+        ));
+        assert!(is_same_origin("http://[::1]:8523", &host("[::1]:8523")));
+        // DNS 重绑定：Origin 与 Host 一致但不是回环地址
+        assert!(!is_same_origin(
+            "http://evil.example:8523",
+            &host("evil.example:8523")
+        ));
+        assert!(!is_same_origin(
+            "http://192.168.1.2:8523",
+            &host("192.168.1.2:8523")
+        ));
+        assert!(!is_same_origin(
+            "http://localhost:8523",
+            &host("localhost:9000")
+        ));
+        assert!(!is_same_origin(
+            "http://localhost.evil.example:8523",
+            &host("localhost.evil.example:8523")
+        ));
+    }
+
+    #[test]
+    fn allowlist() {
+        let policy = CorsPolicy::new(&["http://localhost:3000/".to_string()]);
+        assert!(policy.allows("http://LOCALHOST:3000"));
+        assert!(!policy.allows("http://localhost:3001"));
+        assert!(CorsPolicy::new(&["*".to_string()]).allows("https://example.com"));
+    }
+}
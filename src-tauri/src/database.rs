@@ -1,16 +1,18 @@
 // https://github.com/RandomEngy/tauri-sqlite/blob/main/src-tauri/src/database.rs
 
-use crate::utils;
-use chrono::{DateTime, TimeZone, Utc};
-use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Result};
+use crate::recurrence::Recurrence;
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Result, Row, ToSql};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::RwLock;
-use tauri::AppHandle;
 
-const CURRENT_DB_VERSION: u32 = 1;
+const CURRENT_DB_VERSION: u32 = 8;
 
-const DB_NAME: &str = "fates.db";
+pub const DB_NAME: &str = "fates.db";
 
 fn default_datetime() -> DateTime<Utc> {
     Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap()
@@ -20,7 +22,7 @@ fn check_is_default_datetime(datetime: DateTime<Utc>) -> bool {
     datetime.timestamp() == 0 && datetime.timestamp_subsec_nanos() == 0
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Matter {
     #[serde(default)]
     pub id: String, // UUID
@@ -51,10 +53,14 @@ pub struct Matter {
     pub reserved_4: Option<String>,
     #[serde(default)]
     pub reserved_5: Option<String>,
+    // 创建时所在的 IANA 时区，例如 Asia/Shanghai；为空表示系统本地时区
+    #[serde(default)]
+    pub tz: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RepeatTask {
+    #[serde(default)]
     pub id: String,
     pub title: String,
     pub tags: Option<String>,
@@ -67,9 +73,20 @@ pub struct RepeatTask {
     pub priority: i32,
     #[serde(default)]
     pub description: Option<String>,
+    // RFC 5545 形式的重复规则（DTSTART/DURATION/RRULE），与 repeat_time 保持同步
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    #[serde(default)]
+    pub skip_holidays: bool,
+    // 调休上班的日子也生成一次
+    #[serde(default)]
+    pub makeup_workdays: bool,
+    // 按该 IANA 时区展开重复规则；为空表示系统本地时区
+    #[serde(default)]
+    pub tz: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KVStore {
     pub key: String,
     pub value: String,
@@ -79,7 +96,7 @@ pub struct KVStore {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Tag {
     pub name: String,
     #[serde(default = "default_datetime")]
@@ -88,8 +105,9 @@ pub struct Tag {
     pub last_used_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Todo {
+    #[serde(default)]
     pub id: String, // UUID
     pub title: String,
     pub status: String, // "todo", "in_progress", "completed"
@@ -97,10 +115,35 @@ pub struct Todo {
     pub created_at: DateTime<Utc>,
     #[serde(default = "default_datetime")]
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub due_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub start_time: Option<DateTime<Utc>>, // 计划开始时间
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tags: Option<String>,
+    #[serde(default)]
+    pub sort_order: Option<i64>, // 为空时追加到列表末尾
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Todo 列表过滤条件，所有字段均为可选
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct TodoFilter {
+    pub status: Option<String>,
+    pub tag: Option<String>,
+    pub priority: Option<i32>,
+    pub due_before: Option<DateTime<Utc>>,
+    pub due_after: Option<DateTime<Utc>>,
+    pub start_before: Option<DateTime<Utc>>,
+    pub start_after: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct NotificationRecord {
+    #[serde(default)]
     pub id: String,
     pub title: String,
     pub content: String,
@@ -116,12 +159,225 @@ pub struct NotificationRecord {
     pub reserved_3: Option<String>,
     pub reserved_4: Option<String>,
     pub reserved_5: Option<String>,
+    #[serde(default)]
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    // 稍后提醒或升级提醒下一次重新通知的时间
+    #[serde(default)]
+    pub next_notify_at: Option<DateTime<Utc>>,
+    // 未确认前是否反复提醒（高优先级 matter）
+    #[serde(default)]
+    pub escalate: bool,
+    #[serde(default)]
+    pub escalation_count: i32,
+    // 各投递渠道的汇总状态（DeliveryStatus），没有需要投递的渠道时为空
+    #[serde(default)]
+    pub delivery_status: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Attachment {
+    pub id: String,         // UUID
+    pub owner_type: String, // "matter" | "todo"
+    pub owner_id: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub hash: String, // 内容的 SHA-256，相同内容只保存一份
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MatterComment {
+    #[serde(default)]
+    pub id: String, // UUID
+    #[serde(default)]
+    pub matter_id: String,
+    pub content: String, // Markdown
+    #[serde(default = "default_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "default_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+// 单个 matter 的评论统计，用于列表接口
+#[derive(Debug, Clone, Serialize)]
+pub struct CommentSummary {
+    pub count: i64,
+    pub last_comment: MatterComment,
+}
+
+// 重复任务的一次发生与其生成的 matter，每个任务每天最多一条
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RepeatOccurrence {
+    pub task_id: String,
+    pub occurrence_date: NaiveDate, // 本地日期
+    pub matter_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// 提醒任务队列中的一条，id 由 matter、提醒类型和提前分钟数组成，保证重复同步时不会重复提醒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReminderJob {
+    pub id: String,
+    pub matter_id: String,
+    pub kind: String, // "start" | "end"
+    pub offset_minutes: i64,
+    pub fire_at: DateTime<Utc>,
+    pub status: i32,
+    pub fired_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 用户为 matter 或重复任务设置的提醒：相对开始/结束时间提前若干分钟，或者固定时间
+// 重复任务的提醒由其生成的 matter 继承，只能是相对提醒
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MatterReminder {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub owner_type: String, // "matter" | "repeat_task"
+    #[serde(default)]
+    pub owner_id: String,
+    #[serde(default = "default_anchor")]
+    pub anchor: String, // "start" | "end"
+    #[serde(default)]
+    pub offset_minutes: Option<i64>,
+    #[serde(default)]
+    pub remind_at: Option<DateTime<Utc>>,
+    #[serde(default = "default_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+fn default_anchor() -> String {
+    "start".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReminderStatus {
+    Pending = 0,
+    Fired = 1,
+    Missed = 2,
+    Cancelled = 3,
+}
+
+// 通知在某个渠道上的投递记录，id 由通知 id 和渠道 id 组成
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NotificationDelivery {
+    pub id: String,
+    pub notification_id: String,
+    pub channel_id: String,
+    pub status: i32,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 通知渠道，config 是不含密钥的渠道配置 JSON；密码、令牌和请求头单独保存在 secrets 中，不会随配置返回
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationChannelRecord {
+    pub id: String,
+    pub config: String,
+    pub secrets: String,
+}
+
+// HTTP API 的访问令牌，只保存 SHA-256 哈希；prefix 是明文的前几位，方便在列表中辨认
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub prefix: String,
+    pub scope: String, // "read" 只读，"write" 读写
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+// 出站 webhook 订阅：数据变更时向 url POST 事件，用 secret 做 HMAC-SHA256 签名
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Webhook {
+    #[serde(default)]
+    pub id: String,
+    pub url: String,
+    // 订阅的事件，例如 matter.created、todo.completed、matter.*；为空表示全部
+    #[serde(default)]
+    pub events: Vec<String>,
+    // 只在创建时返回
+    #[serde(default, skip_serializing)]
+    pub secret: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "default_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// 一次事件向一个 webhook 的投递，payload 是发送的请求体，重试时原样发送
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub event_id: i64,
+    pub event: String,
+    pub payload: String,
+    pub status: i32, // DeliveryStatus
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    // 最后一次请求的 HTTP 状态码
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 入站 webhook：外部工具向 POST /hooks/<token> 推送数据，按 mapping 生成 matter、todo 或通知。
+// 令牌和 ApiToken 一样只保存 SHA-256 哈希
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IncomingHook {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub target: String, // "matter" / "todo" / "notification"
+    // 目标字段 → 模板，例如 {"title": "构建失败：{{repository.name}}"}；为空时直接使用请求体中的同名字段
+    #[serde(default)]
+    pub mapping: BTreeMap<String, String>,
+    #[serde(default, skip_serializing)]
+    pub token_hash: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "default_datetime")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryStatus {
+    Pending = 0,
+    Delivered = 1,
+    Failed = 2,
+    // 只用于通知的汇总状态：部分渠道投递失败
+    Partial = 3,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NotificationStatus {
     Unread = 0,
     Read = 1,
+    Acknowledged = 2,
+    Dismissed = 3,
+    Snoozed = 4,
 }
 
 pub struct SafeConnection {
@@ -134,15 +390,21 @@ impl SafeConnection {
             conn: RwLock::new(conn),
         }
     }
+
+    // 等待正在进行的读写结束后关闭数据库文件，之后的读写都会失败
+    // 换成一个空的内存数据库占位，因为其他地方仍可能持有这个连接
+    pub fn close(&self) -> Result<()> {
+        let mut conn = self.conn.write().unwrap();
+        let closed = std::mem::replace(&mut *conn, Connection::open_in_memory()?);
+        closed.close().map_err(|(_, e)| e)
+    }
 }
 
 unsafe impl Send for SafeConnection {}
 unsafe impl Sync for SafeConnection {}
 
-pub fn initialize_database(app_handle: &AppHandle) -> Result<Arc<SafeConnection>> {
-    let app_dir = utils::get_app_data_dir(app_handle.clone()).unwrap();
-    let db_path = app_dir.join(DB_NAME);
-
+// 打开（不存在时创建）指定路径的数据库，并完成建表和迁移
+pub fn open_database(db_path: &Path) -> Result<Arc<SafeConnection>> {
     let flags = OpenFlags::SQLITE_OPEN_READ_WRITE
         | OpenFlags::SQLITE_OPEN_CREATE
         | OpenFlags::SQLITE_OPEN_NO_MUTEX;
@@ -241,190 +503,530 @@ pub fn initialize_database(app_handle: &AppHandle) -> Result<Arc<SafeConnection>
         [],
     )?;
 
-    Ok(Arc::new(SafeConnection::new(conn)))
-}
-
-impl Matter {
-    pub fn create(conn: &Arc<SafeConnection>, matter: &Matter) -> Result<()> {
-        let conn = conn.conn.write().unwrap();
-        conn.execute(
-            "INSERT INTO matter (
-                id, title, description, tags, start_time, end_time,
-                priority, type, created_at, updated_at,
-                reserved_1, reserved_2, reserved_3, reserved_4, reserved_5
-            ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15
-            )",
-            params![
-                matter.id,
-                matter.title,
-                matter.description,
-                matter.tags,
-                matter.start_time,
-                matter.end_time,
-                matter.priority,
-                matter.type_,
-                matter.created_at,
-                matter.updated_at,
-                matter.reserved_1,
-                matter.reserved_2,
-                matter.reserved_3,
-                matter.reserved_4,
-                matter.reserved_5
-            ],
-        )?;
-        Ok(())
-    }
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS attachment (
+            id TEXT PRIMARY KEY,
+            owner_type TEXT NOT NULL,
+            owner_id TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            hash TEXT NOT NULL,
+            created_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
 
-    pub fn get_by_id(conn: &Arc<SafeConnection>, id: &str) -> Result<Option<Matter>> {
-        let conn = conn.conn.read().unwrap();
-        let mut stmt = conn.prepare("SELECT * FROM matter WHERE id = ?1")?;
-
-        let matter = stmt
-            .query_row(params![id], |row| {
-                Ok(Matter {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    description: row.get(2)?,
-                    tags: row.get(3)?,
-                    start_time: row.get(4)?,
-                    end_time: row.get(5)?,
-                    priority: row.get(6)?,
-                    type_: row.get(7)?,
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                    reserved_1: row.get(10)?,
-                    reserved_2: row.get(11)?,
-                    reserved_3: row.get(12)?,
-                    reserved_4: row.get(13)?,
-                    reserved_5: row.get(14)?,
-                })
-            })
-            .optional()?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attachment_owner ON attachment(owner_type, owner_id)",
+        [],
+    )?;
 
-        Ok(matter)
-    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attachment_hash ON attachment(hash)",
+        [],
+    )?;
 
-    pub fn get_all(conn: &Arc<SafeConnection>) -> Result<Vec<Matter>> {
-        let conn = conn.conn.read().unwrap();
-        let mut stmt = conn.prepare("SELECT * FROM matter ORDER BY start_time")?;
-        let matters = stmt
-            .query_map([], |row| {
-                Ok(Matter {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    description: row.get(2)?,
-                    tags: row.get(3)?,
-                    start_time: row.get(4)?,
-                    end_time: row.get(5)?,
-                    priority: row.get(6)?,
-                    type_: row.get(7)?,
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                    reserved_1: row.get(10)?,
-                    reserved_2: row.get(11)?,
-                    reserved_3: row.get(12)?,
-                    reserved_4: row.get(13)?,
-                    reserved_5: row.get(14)?,
-                })
-            })?
-            .collect();
-        matters
-    }
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS matter_comment (
+            id TEXT PRIMARY KEY,
+            matter_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
 
-    pub fn get_by_time_range(
-        conn: &Arc<SafeConnection>,
-        start: DateTime<Utc>,
-        end: DateTime<Utc>,
-    ) -> Result<Vec<Matter>> {
-        let conn = conn.conn.read().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT * FROM matter
-            WHERE (start_time BETWEEN ?1 AND ?2)
-            OR (end_time BETWEEN ?1 AND ?2)
-            OR (start_time <= ?1 AND end_time >= ?2)
-            ORDER BY start_time",
-        )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_matter_comment_matter ON matter_comment(matter_id, created_at)",
+        [],
+    )?;
 
-        let matters = stmt
-            .query_map(params![start, end], |row| {
-                Ok(Matter {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    description: row.get(2)?,
-                    tags: row.get(3)?,
-                    start_time: row.get(4)?,
-                    end_time: row.get(5)?,
-                    priority: row.get(6)?,
-                    type_: row.get(7)?,
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                    reserved_1: row.get(10)?,
-                    reserved_2: row.get(11)?,
-                    reserved_3: row.get(12)?,
-                    reserved_4: row.get(13)?,
-                    reserved_5: row.get(14)?,
-                })
-            })?
-            .collect();
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS repeat_occurrence (
+            task_id TEXT NOT NULL,
+            occurrence_date DATE NOT NULL,
+            matter_id TEXT NOT NULL,
+            created_at DATETIME NOT NULL,
+            PRIMARY KEY (task_id, occurrence_date)
+        )",
+        [],
+    )?;
 
-        matters
-    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_repeat_occurrence_matter ON repeat_occurrence(matter_id)",
+        [],
+    )?;
 
-    pub fn update(&self, conn: &Arc<SafeConnection>) -> Result<()> {
-        let conn = conn.conn.write().unwrap();
-        conn.execute(
-            "UPDATE matter SET
-                title = ?1, description = ?2, tags = ?3,
-                start_time = ?4, end_time = ?5, priority = ?6,
-                type = ?7, updated_at = ?8,
-                reserved_1 = ?9, reserved_2 = ?10, reserved_3 = ?11,
-                reserved_4 = ?12, reserved_5 = ?13
-            WHERE id = ?14",
-            params![
-                self.title,
-                self.description,
-                self.tags,
-                self.start_time,
-                self.end_time,
-                self.priority,
-                self.type_,
-                self.updated_at,
-                self.reserved_1,
-                self.reserved_2,
-                self.reserved_3,
-                self.reserved_4,
-                self.reserved_5,
-                self.id
-            ],
-        )?;
-        Ok(())
-    }
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS reminder_job (
+            id TEXT PRIMARY KEY,
+            matter_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            offset_minutes INTEGER NOT NULL,
+            fire_at DATETIME NOT NULL,
+            status INTEGER NOT NULL DEFAULT 0,
+            fired_at DATETIME,
+            created_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
 
-    pub fn delete(conn: &Arc<SafeConnection>, id: &str) -> Result<()> {
-        let conn = conn.conn.write().unwrap();
-        conn.execute("DELETE FROM matter WHERE id = ?1", params![id])?;
-        Ok(())
-    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_reminder_job_due ON reminder_job(status, fire_at)",
+        [],
+    )?;
 
-    pub fn query_by_field(
-        conn: &Arc<SafeConnection>,
-        field: &str,
-        value: &str,
-        exact_match: bool,
-    ) -> Result<Vec<Matter>> {
-        let conn = conn.conn.read().unwrap();
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_reminder_job_matter ON reminder_job(matter_id)",
+        [],
+    )?;
 
-        // 构建查询语句
-        let query = if exact_match {
-            format!(
-                "SELECT * FROM matter WHERE {} = ?1 ORDER BY start_time",
-                field
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS matter_reminder (
+            id TEXT PRIMARY KEY,
+            owner_type TEXT NOT NULL,
+            owner_id TEXT NOT NULL,
+            anchor TEXT NOT NULL DEFAULT 'start',
+            offset_minutes INTEGER,
+            remind_at DATETIME,
+            created_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_matter_reminder_owner ON matter_reminder(owner_type, owner_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notification_delivery (
+            id TEXT PRIMARY KEY,
+            notification_id TEXT NOT NULL,
+            channel_id TEXT NOT NULL,
+            status INTEGER NOT NULL DEFAULT 0,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_at DATETIME NOT NULL,
+            last_error TEXT,
+            delivered_at DATETIME,
+            created_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_notification_delivery_due
+            ON notification_delivery(status, next_attempt_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_notification_delivery_notification
+            ON notification_delivery(notification_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notification_channel (
+            id TEXT PRIMARY KEY,
+            position INTEGER NOT NULL,
+            config TEXT NOT NULL,
+            secrets TEXT NOT NULL DEFAULT '{}'
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS api_token (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            prefix TEXT NOT NULL,
+            scope TEXT NOT NULL,
+            created_at DATETIME NOT NULL,
+            last_used_at DATETIME
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS webhook (
+            id TEXT PRIMARY KEY,
+            url TEXT NOT NULL,
+            events TEXT NOT NULL DEFAULT '[]',
+            secret TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS webhook_delivery (
+            id TEXT PRIMARY KEY,
+            webhook_id TEXT NOT NULL,
+            event_id INTEGER NOT NULL,
+            event TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            status INTEGER NOT NULL DEFAULT 0,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_at DATETIME NOT NULL,
+            response_status INTEGER,
+            last_error TEXT,
+            delivered_at DATETIME,
+            created_at DATETIME NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_webhook_delivery_due
+            ON webhook_delivery(status, next_attempt_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_webhook_delivery_webhook
+            ON webhook_delivery(webhook_id, created_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS incoming_hook (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            target TEXT NOT NULL,
+            mapping TEXT NOT NULL DEFAULT '{}',
+            token_hash TEXT NOT NULL UNIQUE,
+            prefix TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL,
+            last_used_at DATETIME
+        )",
+        [],
+    )?;
+
+    migrate(&conn)?;
+
+    Ok(Arc::new(SafeConnection::new(conn)))
+}
+
+// 在事务中执行一步迁移，出错时事务随 tx 丢弃回滚，不会留下未结束的事务
+fn migrate_batch(conn: &Connection, sql: &str) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(sql)?;
+    tx.commit()
+}
+
+// 根据 user_version 依次执行迁移，每一步都在事务中完成
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version >= CURRENT_DB_VERSION {
+        return Ok(());
+    }
+    log::info!(
+        "Migrating database from version {} to {}",
+        version,
+        CURRENT_DB_VERSION
+    );
+
+    if version < 2 {
+        // v2: todo 增加截止时间、计划开始时间、优先级、备注、标签和排序字段
+        migrate_batch(
+            conn,
+            "ALTER TABLE todo ADD COLUMN due_date DATETIME;
+            ALTER TABLE todo ADD COLUMN start_time DATETIME;
+            ALTER TABLE todo ADD COLUMN priority INTEGER DEFAULT 0;
+            ALTER TABLE todo ADD COLUMN notes TEXT DEFAULT '';
+            ALTER TABLE todo ADD COLUMN tags TEXT DEFAULT '';
+            ALTER TABLE todo ADD COLUMN sort_order INTEGER DEFAULT 0;
+            UPDATE todo SET sort_order = (
+                SELECT COUNT(*) FROM todo AS t WHERE t.created_at > todo.created_at
+            );
+            CREATE INDEX IF NOT EXISTS idx_todo_sort ON todo(sort_order);
+            PRAGMA user_version = 2;",
+        )?;
+    }
+
+    if version < 3 {
+        // v3: repeat_task 增加 RFC 5545 重复规则，由旧的 repeat_time 转换得到
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(
+            "ALTER TABLE repeat_task ADD COLUMN recurrence TEXT DEFAULT '';
+            ALTER TABLE repeat_task ADD COLUMN skip_holidays INTEGER DEFAULT 0;",
+        )?;
+        let legacy: Vec<(String, String, DateTime<Utc>)> = tx
+            .prepare("SELECT id, repeat_time, created_at FROM repeat_task")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<_>>()?;
+        for (id, repeat_time, created_at) in legacy {
+            let start_date = created_at.with_timezone(&Local).date_naive();
+            match Recurrence::from_legacy(&repeat_time, start_date) {
+                Ok((recurrence, skip_holidays)) => {
+                    tx.execute(
+                        "UPDATE repeat_task SET recurrence = ?1, skip_holidays = ?2 WHERE id = ?3",
+                        params![recurrence.to_string(), skip_holidays, id],
+                    )?;
+                }
+                Err(e) => log::warn!("Skipping repeat task {}: {}", id, e),
+            }
+        }
+        tx.execute_batch("PRAGMA user_version = 3;")?;
+        tx.commit()?;
+    }
+
+    if version < 4 {
+        // v4: repeat_task 增加调休上班日选项
+        migrate_batch(
+            conn,
+            "ALTER TABLE repeat_task ADD COLUMN makeup_workdays INTEGER DEFAULT 0;
+            PRAGMA user_version = 4;",
+        )?;
+    }
+
+    if version < 5 {
+        // v5: matter 和 repeat_task 增加 IANA 时区
+        migrate_batch(
+            conn,
+            "ALTER TABLE matter ADD COLUMN tz TEXT;
+            ALTER TABLE repeat_task ADD COLUMN tz TEXT;
+            PRAGMA user_version = 5;",
+        )?;
+    }
+
+    if version < 6 {
+        // v6: 通知增加确认时间、稍后提醒和升级提醒
+        migrate_batch(
+            conn,
+            "ALTER TABLE notification_records ADD COLUMN acknowledged_at DATETIME;
+            ALTER TABLE notification_records ADD COLUMN next_notify_at DATETIME;
+            ALTER TABLE notification_records ADD COLUMN escalate INTEGER DEFAULT 0;
+            ALTER TABLE notification_records ADD COLUMN escalation_count INTEGER DEFAULT 0;
+            CREATE INDEX IF NOT EXISTS idx_notification_next_notify
+                ON notification_records(next_notify_at);
+            PRAGMA user_version = 6;",
+        )?;
+    }
+
+    if version < 7 {
+        // v7: 通知增加投递状态
+        migrate_batch(
+            conn,
+            "ALTER TABLE notification_records ADD COLUMN delivery_status INTEGER;
+            PRAGMA user_version = 7;",
+        )?;
+    }
+
+    if version < 8 {
+        // v8: 通知渠道从 kv 的 notification.channels 移到单独的表，密钥与配置分开保存
+        let tx = conn.unchecked_transaction()?;
+        let legacy: Option<String> = tx
+            .query_row(
+                "SELECT value FROM kvstore WHERE key = 'notification.channels'",
+                [],
+                |row| row.get(0),
             )
+            .optional()?;
+        let configs = match legacy
+            .as_deref()
+            .map(serde_json::from_str::<Vec<serde_json::Value>>)
+        {
+            Some(Ok(configs)) => configs,
+            Some(Err(e)) => {
+                log::warn!("Skipping invalid notification channel config: {}", e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        for (position, mut config) in configs.into_iter().enumerate() {
+            let Some(object) = config.as_object_mut() else {
+                continue;
+            };
+            let Some(id) = object
+                .get("id")
+                .and_then(|id| id.as_str())
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            let secrets: serde_json::Map<String, serde_json::Value> =
+                ["token", "password", "headers"]
+                    .into_iter()
+                    .filter_map(|key| object.remove(key).map(|value| (key.to_string(), value)))
+                    .collect();
+            tx.execute(
+                "INSERT OR REPLACE INTO notification_channel (id, position, config, secrets)
+                VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    id,
+                    position as i64,
+                    config.to_string(),
+                    serde_json::Value::Object(secrets).to_string()
+                ],
+            )?;
+        }
+        tx.execute_batch(
+            "DELETE FROM kvstore WHERE key = 'notification.channels';
+            PRAGMA user_version = 8;",
+        )?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+// 显式列出列名，不依赖 ALTER TABLE 追加列的顺序
+const MATTER_SELECT: &str = "SELECT id, title, description, tags, start_time, end_time,
+    priority, type, created_at, updated_at, reserved_1, reserved_2, reserved_3,
+    reserved_4, reserved_5, tz FROM matter";
+
+impl Matter {
+    fn from_row(row: &Row) -> Result<Matter> {
+        Ok(Matter {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            description: row.get(2)?,
+            tags: row.get(3)?,
+            start_time: row.get(4)?,
+            end_time: row.get(5)?,
+            priority: row.get(6)?,
+            type_: row.get(7)?,
+            created_at: row.get(8)?,
+            updated_at: row.get(9)?,
+            reserved_1: row.get(10)?,
+            reserved_2: row.get(11)?,
+            reserved_3: row.get(12)?,
+            reserved_4: row.get(13)?,
+            reserved_5: row.get(14)?,
+            tz: row.get(15)?,
+        })
+    }
+
+    pub fn create(conn: &Arc<SafeConnection>, matter: &Matter) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "INSERT INTO matter (
+                id, title, description, tags, start_time, end_time,
+                priority, type, created_at, updated_at,
+                reserved_1, reserved_2, reserved_3, reserved_4, reserved_5, tz
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16
+            )",
+            params![
+                matter.id,
+                matter.title,
+                matter.description,
+                matter.tags,
+                matter.start_time,
+                matter.end_time,
+                matter.priority,
+                matter.type_,
+                matter.created_at,
+                matter.updated_at,
+                matter.reserved_1,
+                matter.reserved_2,
+                matter.reserved_3,
+                matter.reserved_4,
+                matter.reserved_5,
+                matter.tz
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_by_id(conn: &Arc<SafeConnection>, id: &str) -> Result<Option<Matter>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(&format!("{} WHERE id = ?1", MATTER_SELECT))?;
+
+        let matter = stmt.query_row(params![id], Matter::from_row).optional()?;
+
+        Ok(matter)
+    }
+
+    pub fn get_all(conn: &Arc<SafeConnection>) -> Result<Vec<Matter>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(&format!("{} ORDER BY start_time", MATTER_SELECT))?;
+        let matters = stmt.query_map([], Matter::from_row)?.collect();
+        matters
+    }
+
+    pub fn get_by_time_range(
+        conn: &Arc<SafeConnection>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Matter>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE (start_time BETWEEN ?1 AND ?2)
+            OR (end_time BETWEEN ?1 AND ?2)
+            OR (start_time <= ?1 AND end_time >= ?2)
+            ORDER BY start_time",
+            MATTER_SELECT
+        ))?;
+
+        let matters = stmt
+            .query_map(params![start, end], Matter::from_row)?
+            .collect();
+
+        matters
+    }
+
+    pub fn update(&self, conn: &Arc<SafeConnection>) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE matter SET
+                title = ?1, description = ?2, tags = ?3,
+                start_time = ?4, end_time = ?5, priority = ?6,
+                type = ?7, updated_at = ?8,
+                reserved_1 = ?9, reserved_2 = ?10, reserved_3 = ?11,
+                reserved_4 = ?12, reserved_5 = ?13, tz = ?14
+            WHERE id = ?15",
+            params![
+                self.title,
+                self.description,
+                self.tags,
+                self.start_time,
+                self.end_time,
+                self.priority,
+                self.type_,
+                self.updated_at,
+                self.reserved_1,
+                self.reserved_2,
+                self.reserved_3,
+                self.reserved_4,
+                self.reserved_5,
+                self.tz,
+                self.id
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(conn: &Arc<SafeConnection>, id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute("DELETE FROM matter WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn query_by_field(
+        conn: &Arc<SafeConnection>,
+        field: &str,
+        value: &str,
+        exact_match: bool,
+    ) -> Result<Vec<Matter>> {
+        let conn = conn.conn.read().unwrap();
+
+        // 构建查询语句
+        let query = if exact_match {
+            format!("{} WHERE {} = ?1 ORDER BY start_time", MATTER_SELECT, field)
         } else {
             format!(
-                "SELECT * FROM matter WHERE {} LIKE ?1 ORDER BY start_time",
-                field
+                "{} WHERE {} LIKE ?1 ORDER BY start_time",
+                MATTER_SELECT, field
             )
         };
 
@@ -437,27 +1039,7 @@ impl Matter {
             format!("%{}%", value)
         };
 
-        let matters = stmt
-            .query_map([search_value], |row| {
-                Ok(Matter {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    description: row.get(2)?,
-                    tags: row.get(3)?,
-                    start_time: row.get(4)?,
-                    end_time: row.get(5)?,
-                    priority: row.get(6)?,
-                    type_: row.get(7)?,
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                    reserved_1: row.get(10)?,
-                    reserved_2: row.get(11)?,
-                    reserved_3: row.get(12)?,
-                    reserved_4: row.get(13)?,
-                    reserved_5: row.get(14)?,
-                })
-            })?
-            .collect();
+        let matters = stmt.query_map([search_value], Matter::from_row)?.collect();
 
         matters
     }
@@ -534,16 +1116,40 @@ impl Tag {
     }
 }
 
+// 显式列出列名，不依赖 ALTER TABLE 追加列的顺序
+const REPEAT_TASK_SELECT: &str = "SELECT id, title, tags, repeat_time, status, created_at,
+    updated_at, priority, description, recurrence, skip_holidays, makeup_workdays,
+    tz FROM repeat_task";
+
 // RepeatTask 相关操作
 impl RepeatTask {
+    fn from_row(row: &Row) -> Result<RepeatTask> {
+        Ok(RepeatTask {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            tags: row.get(2)?,
+            repeat_time: row.get(3)?,
+            status: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+            priority: row.get(7)?,
+            description: row.get(8)?,
+            recurrence: row.get(9)?,
+            skip_holidays: row.get(10)?,
+            makeup_workdays: row.get(11)?,
+            tz: row.get(12)?,
+        })
+    }
+
     pub fn create(conn: &Arc<SafeConnection>, task: &RepeatTask) -> Result<()> {
         let conn = conn.conn.write().unwrap();
         conn.execute(
             "INSERT INTO repeat_task (
                 id, title, tags, repeat_time, status,
-                created_at, updated_at, priority, description
+                created_at, updated_at, priority, description,
+                recurrence, skip_holidays, makeup_workdays, tz
             ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13
             )",
             params![
                 task.id,
@@ -554,7 +1160,11 @@ impl RepeatTask {
                 task.created_at,
                 task.updated_at,
                 task.priority,
-                task.description
+                task.description,
+                task.recurrence,
+                task.skip_holidays,
+                task.makeup_workdays,
+                task.tz
             ],
         )?;
         Ok(())
@@ -562,67 +1172,27 @@ impl RepeatTask {
 
     pub fn get_by_id(conn: &Arc<SafeConnection>, id: &str) -> Result<Option<RepeatTask>> {
         let conn = conn.conn.read().unwrap();
-        let mut stmt = conn.prepare("SELECT * FROM repeat_task WHERE id = ?1")?;
-
+        let mut stmt = conn.prepare(&format!("{} WHERE id = ?1", REPEAT_TASK_SELECT))?;
         let task = stmt
-            .query_row(params![id], |row| {
-                Ok(RepeatTask {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    tags: row.get(2)?,
-                    repeat_time: row.get(3)?,
-                    status: row.get(4)?,
-                    created_at: row.get(5)?,
-                    updated_at: row.get(6)?,
-                    priority: row.get(7)?,
-                    description: row.get(8)?,
-                })
-            })
+            .query_row(params![id], RepeatTask::from_row)
             .optional()?;
-
         Ok(task)
     }
 
     pub fn get_all(conn: &Arc<SafeConnection>) -> Result<Vec<RepeatTask>> {
         let conn = conn.conn.read().unwrap();
-        let mut stmt = conn.prepare("SELECT * FROM repeat_task ORDER BY created_at DESC")?;
-        let tasks = stmt
-            .query_map([], |row| {
-                Ok(RepeatTask {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    tags: row.get(2)?,
-                    repeat_time: row.get(3)?,
-                    status: row.get(4)?,
-                    created_at: row.get(5)?,
-                    updated_at: row.get(6)?,
-                    priority: row.get(7)?,
-                    description: row.get(8)?,
-                })
-            })?
-            .collect();
+        let mut stmt = conn.prepare(&format!("{} ORDER BY created_at DESC", REPEAT_TASK_SELECT))?;
+        let tasks = stmt.query_map([], RepeatTask::from_row)?.collect();
         tasks
     }
 
     pub fn get_active_tasks(conn: &Arc<SafeConnection>) -> Result<Vec<RepeatTask>> {
         let conn = conn.conn.read().unwrap();
-        let mut stmt =
-            conn.prepare("SELECT * FROM repeat_task WHERE status = 1 ORDER BY created_at DESC")?;
-        let tasks = stmt
-            .query_map([], |row| {
-                Ok(RepeatTask {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    tags: row.get(2)?,
-                    repeat_time: row.get(3)?,
-                    status: row.get(4)?,
-                    created_at: row.get(5)?,
-                    updated_at: row.get(6)?,
-                    priority: row.get(7)?,
-                    description: row.get(8)?,
-                })
-            })?
-            .collect();
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE status = 1 ORDER BY created_at DESC",
+            REPEAT_TASK_SELECT
+        ))?;
+        let tasks = stmt.query_map([], RepeatTask::from_row)?.collect();
         tasks
     }
 
@@ -636,8 +1206,12 @@ impl RepeatTask {
                 status = ?4,
                 updated_at = ?5,
                 priority = ?6,
-                description = ?7
-            WHERE id = ?8",
+                description = ?7,
+                recurrence = ?8,
+                skip_holidays = ?9,
+                makeup_workdays = ?10,
+                tz = ?11
+            WHERE id = ?12",
             params![
                 self.title,
                 self.tags,
@@ -646,6 +1220,10 @@ impl RepeatTask {
                 self.updated_at,
                 self.priority,
                 self.description,
+                self.recurrence,
+                self.skip_holidays,
+                self.makeup_workdays,
+                self.tz,
                 self.id
             ],
         )?;
@@ -668,52 +1246,107 @@ impl RepeatTask {
     }
 }
 
+// 显式列出列名，不依赖 ALTER TABLE 追加列的顺序
+const TODO_SELECT: &str = "SELECT id, title, status, created_at, updated_at, due_date,
+    start_time, priority, notes, tags, sort_order FROM todo";
+
 impl Todo {
+    fn from_row(row: &Row) -> Result<Todo> {
+        Ok(Todo {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            status: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+            due_date: row.get(5)?,
+            start_time: row.get(6)?,
+            priority: row.get(7)?,
+            notes: row.get(8)?,
+            tags: row.get(9)?,
+            sort_order: row.get(10)?,
+        })
+    }
+
     pub fn create(conn: &Arc<SafeConnection>, todo: &Todo) -> Result<()> {
         let conn = conn.conn.write().unwrap();
         conn.execute(
-            "INSERT INTO todo (id, title, status, created_at, updated_at)
-    VALUES (?1, ?2, ?3, ?4, ?5)",
+            "INSERT INTO todo (
+                id, title, status, created_at, updated_at,
+                due_date, start_time, priority, notes, tags, sort_order
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
+                COALESCE(?11, (SELECT IFNULL(MAX(sort_order), -1) + 1 FROM todo))
+            )",
             params![
                 todo.id,
                 todo.title,
                 todo.status,
                 todo.created_at,
-                todo.updated_at
+                todo.updated_at,
+                todo.due_date,
+                todo.start_time,
+                todo.priority,
+                todo.notes,
+                todo.tags,
+                todo.sort_order
             ],
         )?;
         Ok(())
     }
     pub fn get_by_id(conn: &Arc<SafeConnection>, id: &str) -> Result<Option<Todo>> {
         let conn = conn.conn.read().unwrap();
-        let mut stmt = conn.prepare("SELECT FROM todo WHERE id = ?1")?;
-        let todo = stmt
-            .query_row(params![id], |row| {
-                Ok(Todo {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    status: row.get(2)?,
-                    created_at: row.get(3)?,
-                    updated_at: row.get(4)?,
-                })
-            })
-            .optional()?;
+        let mut stmt = conn.prepare(&format!("{} WHERE id = ?1", TODO_SELECT))?;
+        let todo = stmt.query_row(params![id], Todo::from_row).optional()?;
         Ok(todo)
     }
     pub fn get_all(conn: &Arc<SafeConnection>) -> Result<Vec<Todo>> {
+        Todo::query(conn, &TodoFilter::default())
+    }
+
+    pub fn query(conn: &Arc<SafeConnection>, filter: &TodoFilter) -> Result<Vec<Todo>> {
         let conn = conn.conn.read().unwrap();
-        let mut stmt = conn.prepare("SELECT * FROM todo ORDER BY created_at DESC")?;
-        let todos = stmt
-            .query_map([], |row| {
-                Ok(Todo {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    status: row.get(2)?,
-                    created_at: row.get(3)?,
-                    updated_at: row.get(4)?,
-                })
-            })?
-            .collect();
+
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut values: Vec<&dyn ToSql> = Vec::new();
+        if let Some(status) = &filter.status {
+            conditions.push("status = ?");
+            values.push(status);
+        }
+        // tags 以逗号分隔，只匹配完整的标签
+        if let Some(tag) = &filter.tag {
+            conditions.push("instr(',' || tags || ',', ',' || ? || ',') > 0");
+            values.push(tag);
+        }
+        if let Some(priority) = &filter.priority {
+            conditions.push("priority = ?");
+            values.push(priority);
+        }
+        if let Some(due_before) = &filter.due_before {
+            conditions.push("due_date <= ?");
+            values.push(due_before);
+        }
+        if let Some(due_after) = &filter.due_after {
+            conditions.push("due_date >= ?");
+            values.push(due_after);
+        }
+        if let Some(start_before) = &filter.start_before {
+            conditions.push("start_time <= ?");
+            values.push(start_before);
+        }
+        if let Some(start_after) = &filter.start_after {
+            conditions.push("start_time >= ?");
+            values.push(start_after);
+        }
+
+        let mut query = String::from(TODO_SELECT);
+        if !conditions.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&conditions.join(" AND "));
+        }
+        query.push_str(" ORDER BY sort_order, created_at DESC");
+
+        let mut stmt = conn.prepare(&query)?;
+        let todos = stmt.query_map(values.as_slice(), Todo::from_row)?.collect();
         todos
     }
 
@@ -723,12 +1356,43 @@ impl Todo {
             "UPDATE todo SET
         title = ?1,
         status = ?2,
-        updated_at = ?3
-        WHERE id = ?4",
-            params![self.title, self.status, self.updated_at, self.id],
-        )?;
-        Ok(())
-    }
+        updated_at = ?3,
+        due_date = ?4,
+        start_time = ?5,
+        priority = ?6,
+        notes = ?7,
+        tags = ?8,
+        sort_order = COALESCE(?9, sort_order)
+        WHERE id = ?10",
+            params![
+                self.title,
+                self.status,
+                self.updated_at,
+                self.due_date,
+                self.start_time,
+                self.priority,
+                self.notes,
+                self.tags,
+                self.sort_order,
+                self.id
+            ],
+        )?;
+        Ok(())
+    }
+
+    // 按给定 id 顺序重写 sort_order，未列出的 todo 保持原值
+    pub fn reorder(conn: &Arc<SafeConnection>, ids: &[String]) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        let now = Utc::now();
+        for (index, id) in ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE todo SET sort_order = ?1, updated_at = ?2 WHERE id = ?3",
+                params![index as i64, now, id],
+            )?;
+        }
+        tx.commit()
+    }
 
     pub fn delete(conn: &Arc<SafeConnection>, id: &str) -> Result<()> {
         let conn = conn.conn.write().unwrap();
@@ -738,15 +1402,42 @@ impl Todo {
 }
 
 impl NotificationRecord {
+    fn from_row(row: &Row) -> Result<NotificationRecord> {
+        Ok(NotificationRecord {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            content: row.get(2)?,
+            type_: row.get(3)?,
+            status: row.get(4)?,
+            related_task_id: row.get(5)?,
+            created_at: row.get(6)?,
+            read_at: row.get(7)?,
+            expire_at: row.get(8)?,
+            action_url: row.get(9)?,
+            reserved_1: row.get(10)?,
+            reserved_2: row.get(11)?,
+            reserved_3: row.get(12)?,
+            reserved_4: row.get(13)?,
+            reserved_5: row.get(14)?,
+            acknowledged_at: row.get(15)?,
+            next_notify_at: row.get(16)?,
+            escalate: row.get(17)?,
+            escalation_count: row.get(18)?,
+            delivery_status: row.get(19)?,
+        })
+    }
+
     pub fn create(conn: &Arc<SafeConnection>, notification: &NotificationRecord) -> Result<()> {
         let conn = conn.conn.write().unwrap();
         conn.execute(
             "INSERT INTO notification_records (
                 id, title, content, type, status, related_task_id,
                 created_at, read_at, expire_at, action_url,
-                reserved_1, reserved_2, reserved_3, reserved_4, reserved_5
+                reserved_1, reserved_2, reserved_3, reserved_4, reserved_5,
+                acknowledged_at, next_notify_at, escalate, escalation_count, delivery_status
             ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+                ?16, ?17, ?18, ?19, ?20
             )",
             params![
                 notification.id,
@@ -763,7 +1454,12 @@ impl NotificationRecord {
                 notification.reserved_2,
                 notification.reserved_3,
                 notification.reserved_4,
-                notification.reserved_5
+                notification.reserved_5,
+                notification.acknowledged_at,
+                notification.next_notify_at,
+                notification.escalate,
+                notification.escalation_count,
+                notification.delivery_status
             ],
         )?;
         Ok(())
@@ -777,27 +1473,7 @@ impl NotificationRecord {
             ORDER BY created_at DESC",
         )?;
 
-        let notifications = stmt
-            .query_map([], |row| {
-                Ok(NotificationRecord {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    content: row.get(2)?,
-                    type_: row.get(3)?,
-                    status: row.get(4)?,
-                    related_task_id: row.get(5)?,
-                    created_at: row.get(6)?,
-                    read_at: row.get(7)?,
-                    expire_at: row.get(8)?,
-                    action_url: row.get(9)?,
-                    reserved_1: row.get(10)?,
-                    reserved_2: row.get(11)?,
-                    reserved_3: row.get(12)?,
-                    reserved_4: row.get(13)?,
-                    reserved_5: row.get(14)?,
-                })
-            })?
-            .collect();
+        let notifications = stmt.query_map([], NotificationRecord::from_row)?.collect();
 
         notifications
     }
@@ -840,25 +1516,7 @@ impl NotificationRecord {
         let mut stmt = conn.prepare("SELECT * FROM notification_records WHERE id = ?1")?;
 
         let notification = stmt
-            .query_row(params![id], |row| {
-                Ok(NotificationRecord {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    content: row.get(2)?,
-                    type_: row.get(3)?,
-                    status: row.get(4)?,
-                    related_task_id: row.get(5)?,
-                    created_at: row.get(6)?,
-                    read_at: row.get(7)?,
-                    expire_at: row.get(8)?,
-                    action_url: row.get(9)?,
-                    reserved_1: row.get(10)?,
-                    reserved_2: row.get(11)?,
-                    reserved_3: row.get(12)?,
-                    reserved_4: row.get(13)?,
-                    reserved_5: row.get(14)?,
-                })
-            })
+            .query_row(params![id], NotificationRecord::from_row)
             .optional()?;
 
         Ok(notification)
@@ -900,6 +1558,70 @@ impl NotificationRecord {
         Ok(())
     }
 
+    // 需要重新通知的记录：稍后提醒到期，或升级提醒尚未确认
+    pub fn get_renotify_due(
+        conn: &Arc<SafeConnection>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<NotificationRecord>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM notification_records
+            WHERE next_notify_at IS NOT NULL AND next_notify_at <= ?1
+            AND status IN (?2, ?3, ?4)
+            ORDER BY next_notify_at",
+        )?;
+        let notifications = stmt
+            .query_map(
+                params![
+                    now,
+                    NotificationStatus::Unread as i32,
+                    NotificationStatus::Read as i32,
+                    NotificationStatus::Snoozed as i32
+                ],
+                NotificationRecord::from_row,
+            )?
+            .collect();
+        notifications
+    }
+
+    // 只更新状态相关的字段，不影响标题、内容等
+    pub fn update_state(&self, conn: &Arc<SafeConnection>) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE notification_records SET
+                status = ?1,
+                read_at = ?2,
+                acknowledged_at = ?3,
+                next_notify_at = ?4,
+                escalate = ?5,
+                escalation_count = ?6
+            WHERE id = ?7",
+            params![
+                self.status,
+                self.read_at,
+                self.acknowledged_at,
+                self.next_notify_at,
+                self.escalate,
+                self.escalation_count,
+                self.id
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_delivery_status(
+        conn: &Arc<SafeConnection>,
+        id: &str,
+        status: Option<i32>,
+    ) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE notification_records SET delivery_status = ?1 WHERE id = ?2",
+            params![status, id],
+        )?;
+        Ok(())
+    }
+
     pub fn delete(conn: &Arc<SafeConnection>, id: &str) -> Result<()> {
         let conn = conn.conn.write().unwrap();
         conn.execute(
@@ -909,3 +1631,986 @@ impl NotificationRecord {
         Ok(())
     }
 }
+
+impl Attachment {
+    fn from_row(row: &Row) -> Result<Attachment> {
+        Ok(Attachment {
+            id: row.get(0)?,
+            owner_type: row.get(1)?,
+            owner_id: row.get(2)?,
+            file_name: row.get(3)?,
+            content_type: row.get(4)?,
+            size: row.get(5)?,
+            hash: row.get(6)?,
+            created_at: row.get(7)?,
+        })
+    }
+
+    pub fn create(conn: &Arc<SafeConnection>, attachment: &Attachment) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "INSERT INTO attachment (
+                id, owner_type, owner_id, file_name, content_type, size, hash, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                attachment.id,
+                attachment.owner_type,
+                attachment.owner_id,
+                attachment.file_name,
+                attachment.content_type,
+                attachment.size,
+                attachment.hash,
+                attachment.created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_by_id(conn: &Arc<SafeConnection>, id: &str) -> Result<Option<Attachment>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare("SELECT * FROM attachment WHERE id = ?1")?;
+        let attachment = stmt
+            .query_row(params![id], Attachment::from_row)
+            .optional()?;
+        Ok(attachment)
+    }
+
+    pub fn get_by_owner(
+        conn: &Arc<SafeConnection>,
+        owner_type: &str,
+        owner_id: &str,
+    ) -> Result<Vec<Attachment>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM attachment
+            WHERE owner_type = ?1 AND owner_id = ?2
+            ORDER BY created_at",
+        )?;
+        let attachments = stmt
+            .query_map(params![owner_type, owner_id], Attachment::from_row)?
+            .collect();
+        attachments
+    }
+
+    pub fn get_all(conn: &Arc<SafeConnection>) -> Result<Vec<Attachment>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare("SELECT * FROM attachment ORDER BY created_at")?;
+        let attachments = stmt.query_map([], Attachment::from_row)?.collect();
+        attachments
+    }
+
+    pub fn count_by_hash(conn: &Arc<SafeConnection>, hash: &str) -> Result<i64> {
+        let conn = conn.conn.read().unwrap();
+        conn.query_row(
+            "SELECT COUNT(*) FROM attachment WHERE hash = ?1",
+            params![hash],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn delete(conn: &Arc<SafeConnection>, id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute("DELETE FROM attachment WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+}
+
+impl MatterComment {
+    fn from_row(row: &Row) -> Result<MatterComment> {
+        Ok(MatterComment {
+            id: row.get(0)?,
+            matter_id: row.get(1)?,
+            content: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+        })
+    }
+
+    pub fn create(conn: &Arc<SafeConnection>, comment: &MatterComment) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "INSERT INTO matter_comment (id, matter_id, content, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                comment.id,
+                comment.matter_id,
+                comment.content,
+                comment.created_at,
+                comment.updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_by_id(conn: &Arc<SafeConnection>, id: &str) -> Result<Option<MatterComment>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare("SELECT * FROM matter_comment WHERE id = ?1")?;
+        let comment = stmt
+            .query_row(params![id], MatterComment::from_row)
+            .optional()?;
+        Ok(comment)
+    }
+
+    pub fn get_by_matter(
+        conn: &Arc<SafeConnection>,
+        matter_id: &str,
+    ) -> Result<Vec<MatterComment>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT * FROM matter_comment WHERE matter_id = ?1 ORDER BY created_at")?;
+        let comments = stmt
+            .query_map(params![matter_id], MatterComment::from_row)?
+            .collect();
+        comments
+    }
+
+    // 每个有评论的 matter 的评论数和最后一条评论
+    pub fn get_summaries(conn: &Arc<SafeConnection>) -> Result<HashMap<String, CommentSummary>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT c.*, s.count FROM matter_comment c
+            JOIN (
+                SELECT matter_id, COUNT(*) AS count, MAX(created_at) AS last_at
+                FROM matter_comment GROUP BY matter_id
+            ) s ON c.matter_id = s.matter_id AND c.created_at = s.last_at",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(CommentSummary {
+                count: row.get(5)?,
+                last_comment: MatterComment::from_row(row)?,
+            })
+        })?;
+
+        let mut summaries = HashMap::new();
+        for summary in rows {
+            let summary = summary?;
+            summaries.insert(summary.last_comment.matter_id.clone(), summary);
+        }
+        Ok(summaries)
+    }
+
+    pub fn update(&self, conn: &Arc<SafeConnection>) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE matter_comment SET content = ?1, updated_at = ?2 WHERE id = ?3",
+            params![self.content, self.updated_at, self.id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(conn: &Arc<SafeConnection>, id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute("DELETE FROM matter_comment WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn delete_by_matter(conn: &Arc<SafeConnection>, matter_id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "DELETE FROM matter_comment WHERE matter_id = ?1",
+            params![matter_id],
+        )?;
+        Ok(())
+    }
+}
+
+impl RepeatOccurrence {
+    fn from_row(row: &Row) -> Result<RepeatOccurrence> {
+        Ok(RepeatOccurrence {
+            task_id: row.get(0)?,
+            occurrence_date: row.get(1)?,
+            matter_id: row.get(2)?,
+            created_at: row.get(3)?,
+        })
+    }
+
+    // 已存在同一天的记录时不写入，返回是否写入
+    pub fn create(conn: &Arc<SafeConnection>, occurrence: &RepeatOccurrence) -> Result<bool> {
+        let conn = conn.conn.write().unwrap();
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO repeat_occurrence (task_id, occurrence_date, matter_id, created_at)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![
+                occurrence.task_id,
+                occurrence.occurrence_date,
+                occurrence.matter_id,
+                occurrence.created_at
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    pub fn exists(conn: &Arc<SafeConnection>, task_id: &str, date: NaiveDate) -> Result<bool> {
+        let conn = conn.conn.read().unwrap();
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM repeat_occurrence WHERE task_id = ?1 AND occurrence_date = ?2)",
+            params![task_id, date],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn get_by_task(conn: &Arc<SafeConnection>, task_id: &str) -> Result<Vec<RepeatOccurrence>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM repeat_occurrence WHERE task_id = ?1 ORDER BY occurrence_date DESC",
+        )?;
+        let occurrences = stmt
+            .query_map(params![task_id], RepeatOccurrence::from_row)?
+            .collect();
+        occurrences
+    }
+
+    pub fn get_by_matter(
+        conn: &Arc<SafeConnection>,
+        matter_id: &str,
+    ) -> Result<Option<RepeatOccurrence>> {
+        let conn = conn.conn.read().unwrap();
+        conn.query_row(
+            "SELECT * FROM repeat_occurrence WHERE matter_id = ?1",
+            params![matter_id],
+            RepeatOccurrence::from_row,
+        )
+        .optional()
+    }
+
+    pub fn delete_by_task(conn: &Arc<SafeConnection>, task_id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "DELETE FROM repeat_occurrence WHERE task_id = ?1",
+            params![task_id],
+        )?;
+        Ok(())
+    }
+}
+
+impl ReminderJob {
+    fn from_row(row: &Row) -> Result<ReminderJob> {
+        Ok(ReminderJob {
+            id: row.get(0)?,
+            matter_id: row.get(1)?,
+            kind: row.get(2)?,
+            offset_minutes: row.get(3)?,
+            fire_at: row.get(4)?,
+            status: row.get(5)?,
+            fired_at: row.get(6)?,
+            created_at: row.get(7)?,
+        })
+    }
+
+    // 新任务直接写入；已存在的任务在提醒时间变化（matter 被修改）或之前被取消时重新进入待提醒状态
+    pub fn upsert(conn: &Arc<SafeConnection>, job: &ReminderJob) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "INSERT INTO reminder_job (
+                id, matter_id, kind, offset_minutes, fire_at, status, fired_at, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT(id) DO UPDATE SET
+                fire_at = excluded.fire_at,
+                status = excluded.status,
+                fired_at = NULL
+            WHERE reminder_job.fire_at <> excluded.fire_at OR reminder_job.status = ?9",
+            params![
+                job.id,
+                job.matter_id,
+                job.kind,
+                job.offset_minutes,
+                job.fire_at,
+                job.status,
+                job.fired_at,
+                job.created_at,
+                ReminderStatus::Cancelled as i32
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_due(conn: &Arc<SafeConnection>, now: DateTime<Utc>) -> Result<Vec<ReminderJob>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM reminder_job WHERE status = ?1 AND fire_at <= ?2 ORDER BY fire_at",
+        )?;
+        let jobs = stmt
+            .query_map(
+                params![ReminderStatus::Pending as i32, now],
+                ReminderJob::from_row,
+            )?
+            .collect();
+        jobs
+    }
+
+    pub fn get_by_matter(conn: &Arc<SafeConnection>, matter_id: &str) -> Result<Vec<ReminderJob>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT * FROM reminder_job WHERE matter_id = ?1 ORDER BY fire_at")?;
+        let jobs = stmt
+            .query_map(params![matter_id], ReminderJob::from_row)?
+            .collect();
+        jobs
+    }
+
+    pub fn update_status(
+        conn: &Arc<SafeConnection>,
+        id: &str,
+        status: ReminderStatus,
+        fired_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE reminder_job SET status = ?1, fired_at = ?2 WHERE id = ?3",
+            params![status as i32, fired_at, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_by_matter(conn: &Arc<SafeConnection>, matter_id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "DELETE FROM reminder_job WHERE matter_id = ?1",
+            params![matter_id],
+        )?;
+        Ok(())
+    }
+}
+
+impl MatterReminder {
+    fn from_row(row: &Row) -> Result<MatterReminder> {
+        Ok(MatterReminder {
+            id: row.get(0)?,
+            owner_type: row.get(1)?,
+            owner_id: row.get(2)?,
+            anchor: row.get(3)?,
+            offset_minutes: row.get(4)?,
+            remind_at: row.get(5)?,
+            created_at: row.get(6)?,
+        })
+    }
+
+    pub fn create(conn: &Arc<SafeConnection>, reminder: &MatterReminder) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "INSERT INTO matter_reminder (
+                id, owner_type, owner_id, anchor, offset_minutes, remind_at, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                reminder.id,
+                reminder.owner_type,
+                reminder.owner_id,
+                reminder.anchor,
+                reminder.offset_minutes,
+                reminder.remind_at,
+                reminder.created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_by_id(conn: &Arc<SafeConnection>, id: &str) -> Result<Option<MatterReminder>> {
+        let conn = conn.conn.read().unwrap();
+        conn.query_row(
+            "SELECT * FROM matter_reminder WHERE id = ?1",
+            params![id],
+            MatterReminder::from_row,
+        )
+        .optional()
+    }
+
+    pub fn get_by_owner(
+        conn: &Arc<SafeConnection>,
+        owner_type: &str,
+        owner_id: &str,
+    ) -> Result<Vec<MatterReminder>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM matter_reminder WHERE owner_type = ?1 AND owner_id = ?2
+            ORDER BY created_at",
+        )?;
+        let reminders = stmt
+            .query_map(params![owner_type, owner_id], MatterReminder::from_row)?
+            .collect();
+        reminders
+    }
+
+    // 固定时间落在 [start, end] 内的提醒
+    pub fn get_absolute_between(
+        conn: &Arc<SafeConnection>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<MatterReminder>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM matter_reminder WHERE remind_at BETWEEN ?1 AND ?2 ORDER BY remind_at",
+        )?;
+        let reminders = stmt
+            .query_map(params![start, end], MatterReminder::from_row)?
+            .collect();
+        reminders
+    }
+
+    // 所有相对提醒中最大的提前分钟数，用于确定需要检查的时间范围
+    pub fn max_offset(conn: &Arc<SafeConnection>) -> Result<Option<i64>> {
+        let conn = conn.conn.read().unwrap();
+        conn.query_row(
+            "SELECT MAX(offset_minutes) FROM matter_reminder",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn update(conn: &Arc<SafeConnection>, reminder: &MatterReminder) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE matter_reminder SET anchor = ?1, offset_minutes = ?2, remind_at = ?3
+            WHERE id = ?4",
+            params![
+                reminder.anchor,
+                reminder.offset_minutes,
+                reminder.remind_at,
+                reminder.id
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(conn: &Arc<SafeConnection>, id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute("DELETE FROM matter_reminder WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn delete_by_owner(
+        conn: &Arc<SafeConnection>,
+        owner_type: &str,
+        owner_id: &str,
+    ) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "DELETE FROM matter_reminder WHERE owner_type = ?1 AND owner_id = ?2",
+            params![owner_type, owner_id],
+        )?;
+        Ok(())
+    }
+}
+
+impl NotificationChannelRecord {
+    fn from_row(row: &Row) -> Result<NotificationChannelRecord> {
+        Ok(NotificationChannelRecord {
+            id: row.get(0)?,
+            config: row.get(1)?,
+            secrets: row.get(2)?,
+        })
+    }
+
+    pub fn get_all(conn: &Arc<SafeConnection>) -> Result<Vec<NotificationChannelRecord>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT id, config, secrets FROM notification_channel ORDER BY position")?;
+        let channels = stmt
+            .query_map([], NotificationChannelRecord::from_row)?
+            .collect();
+        channels
+    }
+
+    // 整体替换，顺序即数组中的顺序
+    pub fn replace_all(
+        conn: &Arc<SafeConnection>,
+        channels: &[NotificationChannelRecord],
+    ) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM notification_channel", [])?;
+        for (position, channel) in channels.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO notification_channel (id, position, config, secrets)
+                VALUES (?1, ?2, ?3, ?4)",
+                params![channel.id, position as i64, channel.config, channel.secrets],
+            )?;
+        }
+        tx.commit()
+    }
+}
+
+impl NotificationDelivery {
+    fn from_row(row: &Row) -> Result<NotificationDelivery> {
+        Ok(NotificationDelivery {
+            id: row.get(0)?,
+            notification_id: row.get(1)?,
+            channel_id: row.get(2)?,
+            status: row.get(3)?,
+            attempts: row.get(4)?,
+            next_attempt_at: row.get(5)?,
+            last_error: row.get(6)?,
+            delivered_at: row.get(7)?,
+            created_at: row.get(8)?,
+        })
+    }
+
+    // 重新投递（例如稍后提醒再次触发）时重置尝试次数
+    pub fn upsert(conn: &Arc<SafeConnection>, delivery: &NotificationDelivery) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "INSERT INTO notification_delivery (
+                id, notification_id, channel_id, status, attempts,
+                next_attempt_at, last_error, delivered_at, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            ON CONFLICT(id) DO UPDATE SET
+                status = excluded.status,
+                attempts = excluded.attempts,
+                next_attempt_at = excluded.next_attempt_at,
+                last_error = NULL,
+                delivered_at = NULL",
+            params![
+                delivery.id,
+                delivery.notification_id,
+                delivery.channel_id,
+                delivery.status,
+                delivery.attempts,
+                delivery.next_attempt_at,
+                delivery.last_error,
+                delivery.delivered_at,
+                delivery.created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_due(
+        conn: &Arc<SafeConnection>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<NotificationDelivery>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM notification_delivery
+            WHERE status = ?1 AND next_attempt_at <= ?2
+            ORDER BY next_attempt_at",
+        )?;
+        let deliveries = stmt
+            .query_map(
+                params![DeliveryStatus::Pending as i32, now],
+                NotificationDelivery::from_row,
+            )?
+            .collect();
+        deliveries
+    }
+
+    pub fn get_by_notification(
+        conn: &Arc<SafeConnection>,
+        notification_id: &str,
+    ) -> Result<Vec<NotificationDelivery>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM notification_delivery WHERE notification_id = ?1 ORDER BY channel_id",
+        )?;
+        let deliveries = stmt
+            .query_map(params![notification_id], NotificationDelivery::from_row)?
+            .collect();
+        deliveries
+    }
+
+    pub fn update(&self, conn: &Arc<SafeConnection>) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE notification_delivery SET
+                status = ?1,
+                attempts = ?2,
+                next_attempt_at = ?3,
+                last_error = ?4,
+                delivered_at = ?5
+            WHERE id = ?6",
+            params![
+                self.status,
+                self.attempts,
+                self.next_attempt_at,
+                self.last_error,
+                self.delivered_at,
+                self.id
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_by_notification(conn: &Arc<SafeConnection>, notification_id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "DELETE FROM notification_delivery WHERE notification_id = ?1",
+            params![notification_id],
+        )?;
+        Ok(())
+    }
+}
+
+impl ApiToken {
+    fn from_row(row: &Row) -> Result<ApiToken> {
+        Ok(ApiToken {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            token_hash: row.get(2)?,
+            prefix: row.get(3)?,
+            scope: row.get(4)?,
+            created_at: row.get(5)?,
+            last_used_at: row.get(6)?,
+        })
+    }
+
+    pub fn create(conn: &Arc<SafeConnection>, token: &ApiToken) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "INSERT INTO api_token (
+                id, name, token_hash, prefix, scope, created_at, last_used_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                token.id,
+                token.name,
+                token.token_hash,
+                token.prefix,
+                token.scope,
+                token.created_at,
+                token.last_used_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_all(conn: &Arc<SafeConnection>) -> Result<Vec<ApiToken>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare("SELECT * FROM api_token ORDER BY created_at")?;
+        let tokens = stmt.query_map([], ApiToken::from_row)?.collect();
+        tokens
+    }
+
+    pub fn get_by_hash(conn: &Arc<SafeConnection>, token_hash: &str) -> Result<Option<ApiToken>> {
+        let conn = conn.conn.read().unwrap();
+        conn.query_row(
+            "SELECT * FROM api_token WHERE token_hash = ?1",
+            params![token_hash],
+            ApiToken::from_row,
+        )
+        .optional()
+    }
+
+    pub fn touch(conn: &Arc<SafeConnection>, id: &str, used_at: DateTime<Utc>) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE api_token SET last_used_at = ?1 WHERE id = ?2",
+            params![used_at, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(conn: &Arc<SafeConnection>, id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute("DELETE FROM api_token WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+}
+
+impl Webhook {
+    fn from_row(row: &Row) -> Result<Webhook> {
+        let events: String = row.get(2)?;
+        Ok(Webhook {
+            id: row.get(0)?,
+            url: row.get(1)?,
+            events: serde_json::from_str(&events).unwrap_or_default(),
+            secret: row.get(3)?,
+            enabled: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    }
+
+    fn events_json(&self) -> String {
+        serde_json::to_string(&self.events).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    pub fn create(conn: &Arc<SafeConnection>, webhook: &Webhook) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "INSERT INTO webhook (
+                id, url, events, secret, enabled, created_at, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                webhook.id,
+                webhook.url,
+                webhook.events_json(),
+                webhook.secret,
+                webhook.enabled,
+                webhook.created_at,
+                webhook.updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_by_id(conn: &Arc<SafeConnection>, id: &str) -> Result<Option<Webhook>> {
+        let conn = conn.conn.read().unwrap();
+        conn.query_row(
+            "SELECT * FROM webhook WHERE id = ?1",
+            params![id],
+            Webhook::from_row,
+        )
+        .optional()
+    }
+
+    pub fn get_all(conn: &Arc<SafeConnection>) -> Result<Vec<Webhook>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare("SELECT * FROM webhook ORDER BY created_at")?;
+        let webhooks = stmt.query_map([], Webhook::from_row)?.collect();
+        webhooks
+    }
+
+    pub fn update(&self, conn: &Arc<SafeConnection>) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE webhook SET
+                url = ?1,
+                events = ?2,
+                secret = ?3,
+                enabled = ?4,
+                updated_at = ?5
+            WHERE id = ?6",
+            params![
+                self.url,
+                self.events_json(),
+                self.secret,
+                self.enabled,
+                self.updated_at,
+                self.id
+            ],
+        )?;
+        Ok(())
+    }
+
+    // 同时删除投递记录
+    pub fn delete(conn: &Arc<SafeConnection>, id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM webhook_delivery WHERE webhook_id = ?1",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM webhook WHERE id = ?1", params![id])?;
+        tx.commit()
+    }
+}
+
+impl WebhookDelivery {
+    fn from_row(row: &Row) -> Result<WebhookDelivery> {
+        Ok(WebhookDelivery {
+            id: row.get(0)?,
+            webhook_id: row.get(1)?,
+            event_id: row.get(2)?,
+            event: row.get(3)?,
+            payload: row.get(4)?,
+            status: row.get(5)?,
+            attempts: row.get(6)?,
+            next_attempt_at: row.get(7)?,
+            response_status: row.get(8)?,
+            last_error: row.get(9)?,
+            delivered_at: row.get(10)?,
+            created_at: row.get(11)?,
+        })
+    }
+
+    pub fn create(conn: &Arc<SafeConnection>, delivery: &WebhookDelivery) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "INSERT INTO webhook_delivery (
+                id, webhook_id, event_id, event, payload, status, attempts,
+                next_attempt_at, response_status, last_error, delivered_at, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                delivery.id,
+                delivery.webhook_id,
+                delivery.event_id,
+                delivery.event,
+                delivery.payload,
+                delivery.status,
+                delivery.attempts,
+                delivery.next_attempt_at,
+                delivery.response_status,
+                delivery.last_error,
+                delivery.delivered_at,
+                delivery.created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_due(conn: &Arc<SafeConnection>, now: DateTime<Utc>) -> Result<Vec<WebhookDelivery>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM webhook_delivery
+            WHERE status = ?1 AND next_attempt_at <= ?2
+            ORDER BY next_attempt_at, event_id",
+        )?;
+        let deliveries = stmt
+            .query_map(
+                params![DeliveryStatus::Pending as i32, now],
+                WebhookDelivery::from_row,
+            )?
+            .collect();
+        deliveries
+    }
+
+    // 最近的投递记录，新的在前
+    pub fn get_by_webhook(
+        conn: &Arc<SafeConnection>,
+        webhook_id: &str,
+        limit: usize,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM webhook_delivery WHERE webhook_id = ?1
+            ORDER BY created_at DESC, event_id DESC LIMIT ?2",
+        )?;
+        let deliveries = stmt
+            .query_map(params![webhook_id, limit as i64], WebhookDelivery::from_row)?
+            .collect();
+        deliveries
+    }
+
+    pub fn update(&self, conn: &Arc<SafeConnection>) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE webhook_delivery SET
+                status = ?1,
+                attempts = ?2,
+                next_attempt_at = ?3,
+                response_status = ?4,
+                last_error = ?5,
+                delivered_at = ?6
+            WHERE id = ?7",
+            params![
+                self.status,
+                self.attempts,
+                self.next_attempt_at,
+                self.response_status,
+                self.last_error,
+                self.delivered_at,
+                self.id
+            ],
+        )?;
+        Ok(())
+    }
+
+    // 删除 before 之前创建且已经结束的投递记录
+    pub fn prune(conn: &Arc<SafeConnection>, before: DateTime<Utc>) -> Result<usize> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "DELETE FROM webhook_delivery WHERE status != ?1 AND created_at < ?2",
+            params![DeliveryStatus::Pending as i32, before],
+        )
+    }
+}
+
+impl IncomingHook {
+    fn from_row(row: &Row) -> Result<IncomingHook> {
+        let mapping: String = row.get(3)?;
+        Ok(IncomingHook {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            target: row.get(2)?,
+            mapping: serde_json::from_str(&mapping).unwrap_or_default(),
+            token_hash: row.get(4)?,
+            prefix: row.get(5)?,
+            enabled: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+            last_used_at: row.get(9)?,
+        })
+    }
+
+    fn mapping_json(&self) -> String {
+        serde_json::to_string(&self.mapping).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    pub fn create(conn: &Arc<SafeConnection>, hook: &IncomingHook) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "INSERT INTO incoming_hook (
+                id, name, target, mapping, token_hash, prefix, enabled,
+                created_at, updated_at, last_used_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                hook.id,
+                hook.name,
+                hook.target,
+                hook.mapping_json(),
+                hook.token_hash,
+                hook.prefix,
+                hook.enabled,
+                hook.created_at,
+                hook.updated_at,
+                hook.last_used_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_by_id(conn: &Arc<SafeConnection>, id: &str) -> Result<Option<IncomingHook>> {
+        let conn = conn.conn.read().unwrap();
+        conn.query_row(
+            "SELECT * FROM incoming_hook WHERE id = ?1",
+            params![id],
+            IncomingHook::from_row,
+        )
+        .optional()
+    }
+
+    pub fn get_by_hash(
+        conn: &Arc<SafeConnection>,
+        token_hash: &str,
+    ) -> Result<Option<IncomingHook>> {
+        let conn = conn.conn.read().unwrap();
+        conn.query_row(
+            "SELECT * FROM incoming_hook WHERE token_hash = ?1",
+            params![token_hash],
+            IncomingHook::from_row,
+        )
+        .optional()
+    }
+
+    pub fn get_all(conn: &Arc<SafeConnection>) -> Result<Vec<IncomingHook>> {
+        let conn = conn.conn.read().unwrap();
+        let mut stmt = conn.prepare("SELECT * FROM incoming_hook ORDER BY created_at")?;
+        let hooks = stmt.query_map([], IncomingHook::from_row)?.collect();
+        hooks
+    }
+
+    // 令牌不在这里修改
+    pub fn update(&self, conn: &Arc<SafeConnection>) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE incoming_hook SET
+                name = ?1,
+                target = ?2,
+                mapping = ?3,
+                enabled = ?4,
+                updated_at = ?5
+            WHERE id = ?6",
+            params![
+                self.name,
+                self.target,
+                self.mapping_json(),
+                self.enabled,
+                self.updated_at,
+                self.id
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn touch(conn: &Arc<SafeConnection>, id: &str, used_at: DateTime<Utc>) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute(
+            "UPDATE incoming_hook SET last_used_at = ?1 WHERE id = ?2",
+            params![used_at, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(conn: &Arc<SafeConnection>, id: &str) -> Result<()> {
+        let conn = conn.conn.write().unwrap();
+        conn.execute("DELETE FROM incoming_hook WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+}
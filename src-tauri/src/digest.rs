@@ -0,0 +1,316 @@
+// 每日简报：在设定的本地时间把今天的 matter、到期的 todo、重复任务生成的 matter
+// 以及昨天未完成的 todo 汇总成一条通知，通过通知渠道发出，也可以写成 Markdown 文件。
+// 每天最多发送一次；应用在设定时间之后才启动时会补发当天的简报
+
+use crate::database::{Matter, Todo, TodoFilter};
+use crate::http_server;
+use crate::recurrence::{parse_tz, utc_to_zoned, zoned_to_utc};
+use crate::reminder::{self, NOTIFICATION_TYPE_DIGEST};
+use crate::repository::{Repositories, Result};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::{command, AppHandle};
+
+// 发送时间，格式 HH:MM，为空表示不发送
+const TIME_KEY: &str = "digest.time";
+// 计算“今天”所用的 IANA 时区，为空表示系统本地时区
+const TZ_KEY: &str = "digest.tz";
+// 设置后同时把简报写到这个目录下的 digest-YYYY-MM-DD.md
+const MARKDOWN_DIR_KEY: &str = "digest.markdown_dir";
+// 上次发送的日期
+const LAST_DATE_KEY: &str = "digest.last_date";
+
+const CHECK_INTERVAL_SECS: u64 = 60;
+// 通知正文中每一类最多列出的标题数
+const SUMMARY_LIMIT: usize = 5;
+
+const TODO_COMPLETED: &str = "completed";
+const MATTER_TYPE_REPEAT: i32 = 1;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Digest {
+    pub date: NaiveDate,
+    pub tz: Option<String>,
+    pub matters: Vec<Matter>,
+    // 由重复任务生成的 matter
+    pub repeat_matters: Vec<Matter>,
+    pub due_todos: Vec<Todo>,
+    // 昨天到期但没有完成的 todo
+    pub unfinished_todos: Vec<Todo>,
+}
+
+// 某天在指定时区的起止时间
+fn day_bounds(date: NaiveDate, tz: Option<Tz>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = zoned_to_utc(date.and_time(NaiveTime::MIN), tz);
+    let end = zoned_to_utc((date + Duration::days(1)).and_time(NaiveTime::MIN), tz);
+    (start, end - Duration::seconds(1))
+}
+
+fn unfinished_todos_between(
+    storage: &Repositories,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<Todo>> {
+    let todos = storage.todos.query(&TodoFilter {
+        due_after: Some(start),
+        due_before: Some(end),
+        ..Default::default()
+    })?;
+    Ok(todos
+        .into_iter()
+        .filter(|t| t.status != TODO_COMPLETED)
+        .collect())
+}
+
+pub fn build(storage: &Repositories, date: NaiveDate) -> Result<Digest> {
+    let tz = digest_tz(storage)?;
+    let (start, end) = day_bounds(date, tz);
+    let (repeat_matters, matters) = storage
+        .matters
+        .get_by_time_range(start, end)?
+        .into_iter()
+        .partition(|m| m.type_ == MATTER_TYPE_REPEAT);
+
+    let (yesterday_start, _) = day_bounds(date - Duration::days(1), tz);
+    Ok(Digest {
+        date,
+        tz: tz.map(|tz| tz.name().to_string()),
+        matters,
+        repeat_matters,
+        due_todos: unfinished_todos_between(storage, start, end)?,
+        unfinished_todos: unfinished_todos_between(
+            storage,
+            yesterday_start,
+            start - Duration::seconds(1),
+        )?,
+    })
+}
+
+impl Digest {
+    fn zone(&self) -> Option<Tz> {
+        self.tz.as_deref().and_then(|tz| tz.parse().ok())
+    }
+
+    fn time(&self, dt: DateTime<Utc>) -> String {
+        utc_to_zoned(dt, self.zone()).format("%H:%M").to_string()
+    }
+
+    fn matter_line(&self, matter: &Matter) -> String {
+        format!(
+            "{}-{} {}",
+            self.time(matter.start_time),
+            self.time(matter.end_time),
+            matter.title
+        )
+    }
+
+    fn todo_line(&self, todo: &Todo) -> String {
+        match todo.due_date {
+            Some(due) => format!("{} {}", self.time(due), todo.title),
+            None => todo.title.clone(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matters.is_empty()
+            && self.repeat_matters.is_empty()
+            && self.due_todos.is_empty()
+            && self.unfinished_todos.is_empty()
+    }
+
+    fn sections(&self) -> Vec<(&'static str, Vec<String>)> {
+        vec![
+            (
+                "今日事项",
+                self.matters.iter().map(|m| self.matter_line(m)).collect(),
+            ),
+            (
+                "重复任务",
+                self.repeat_matters
+                    .iter()
+                    .map(|m| self.matter_line(m))
+                    .collect(),
+            ),
+            (
+                "今日到期",
+                self.due_todos.iter().map(|t| self.todo_line(t)).collect(),
+            ),
+            (
+                "昨日未完成",
+                self.unfinished_todos
+                    .iter()
+                    .map(|t| self.todo_line(t))
+                    .collect(),
+            ),
+        ]
+    }
+
+    pub fn title(&self) -> String {
+        format!("{} 每日简报", self.date.format("%Y-%m-%d"))
+    }
+
+    // 通知正文，每类只列出前几条
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "今天没有安排".to_string();
+        }
+        let mut lines = Vec::new();
+        for (name, items) in self.sections() {
+            if items.is_empty() {
+                continue;
+            }
+            lines.push(format!("{}（{}）", name, items.len()));
+            lines.extend(items.iter().take(SUMMARY_LIMIT).map(|i| format!("· {}", i)));
+            if items.len() > SUMMARY_LIMIT {
+                lines.push(format!("· 等 {} 项", items.len()));
+            }
+        }
+        lines.join("\n")
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n", self.title());
+        for (name, items) in self.sections() {
+            out.push_str(&format!("\n## {}\n\n", name));
+            if items.is_empty() {
+                out.push_str("无\n");
+            }
+            for item in items {
+                out.push_str(&format!("- {}\n", item));
+            }
+        }
+        out
+    }
+}
+
+// 简报所用的时区，设置无效时退回系统本地时区
+fn digest_tz(storage: &Repositories) -> Result<Option<Tz>> {
+    let value = storage.kv.get(TZ_KEY, "")?;
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    match parse_tz(&value) {
+        Ok(tz) => Ok(Some(tz)),
+        Err(e) => {
+            log::warn!("Invalid digest time zone: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+fn send_time(storage: &Repositories) -> Result<Option<NaiveTime>> {
+    let value = storage.kv.get(TIME_KEY, "")?;
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match NaiveTime::parse_from_str(value, "%H:%M") {
+        Ok(time) => Ok(Some(time)),
+        Err(_) => {
+            log::warn!("Invalid digest time: {}", value);
+            Ok(None)
+        }
+    }
+}
+
+fn write_markdown(storage: &Repositories, digest: &Digest) -> Result<()> {
+    let dir = storage.kv.get(MARKDOWN_DIR_KEY, "")?;
+    if dir.trim().is_empty() {
+        return Ok(());
+    }
+    let dir = PathBuf::from(dir.trim());
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("digest-{}.md", digest.date.format("%Y-%m-%d")));
+    std::fs::write(path, digest.to_markdown())?;
+    Ok(())
+}
+
+// 立即生成并发送某天的简报
+pub fn send(app: &AppHandle, storage: &Repositories, date: NaiveDate) -> Result<Digest> {
+    let digest = build(storage, date)?;
+    let record = reminder::new_record(
+        digest.title(),
+        digest.summary(),
+        NOTIFICATION_TYPE_DIGEST,
+        None,
+    );
+    reminder::deliver(app, storage, &record)?;
+    if let Err(e) = write_markdown(storage, &digest) {
+        log::error!("Failed to write digest file: {}", e);
+    }
+    Ok(digest)
+}
+
+fn process(app: &AppHandle, storage: &Repositories, now: DateTime<Utc>) -> Result<()> {
+    let Some(time) = send_time(storage)? else {
+        return Ok(());
+    };
+    let local = utc_to_zoned(now, digest_tz(storage)?);
+    let today = local.date();
+    if local.time() < time {
+        return Ok(());
+    }
+    let last = storage.kv.get(LAST_DATE_KEY, "")?.parse::<NaiveDate>().ok();
+    if last.is_some_and(|last| last >= today) {
+        return Ok(());
+    }
+    // 先记录日期，发送失败也不会每分钟重复发送
+    storage.kv.set(LAST_DATE_KEY, &today.to_string())?;
+    send(app, storage, today)?;
+    log::info!("Sent daily digest for {}", today);
+    Ok(())
+}
+
+async fn run_once(app: &AppHandle) {
+    let Some(storage) = http_server::current_storage().await else {
+        return;
+    };
+    let app = app.clone();
+    let result =
+        tauri::async_runtime::spawn_blocking(move || process(&app, &storage, Utc::now())).await;
+
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::error!("Failed to send daily digest: {}", e),
+        Err(e) => log::error!("Digest job panicked: {}", e),
+    }
+}
+
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            run_once(&app).await;
+        }
+    });
+}
+
+// 简报时区的今天
+pub fn today(storage: &Repositories) -> Result<NaiveDate> {
+    Ok(utc_to_zoned(Utc::now(), digest_tz(storage)?).date())
+}
+
+// 不指定日期时为今天
+#[command]
+pub async fn get_digest(date: Option<String>) -> std::result::Result<Digest, String> {
+    let storage = reminder::command_storage().await?;
+    let date = match date {
+        Some(date) => date
+            .parse::<NaiveDate>()
+            .map_err(|_| format!("无效的日期：{}", date))?,
+        None => today(&storage).map_err(|e| e.to_string())?,
+    };
+    build(&storage, date).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn send_digest(app: AppHandle) -> std::result::Result<Digest, String> {
+    let storage = reminder::command_storage().await?;
+    let date = today(&storage).map_err(|e| e.to_string())?;
+    send(&app, &storage, date).map_err(|e| e.to_string())
+}
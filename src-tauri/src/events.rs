@@ -0,0 +1,221 @@
+// 数据变更事件：存储层的每次写入都会发布一条事件，HTTP 服务器通过 GET /events（SSE）
+// 和 GET /events/ws（WebSocket）推送给客户端，客户端不用再轮询。
+// 事件 id 单调递增，最近的事件保留在内存中，客户端断线重连时带上最后收到的 id 即可补齐；
+// 要补的事件已经不在内存中（或来自上次启动）时发送 reset，客户端应重新拉取全部数据
+
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, StreamExt};
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch;
+
+// 内存中保留的最近事件数，用于断线重连后补发
+const HISTORY_SIZE: usize = 1024;
+// 单个订阅者来不及处理的事件数超过该值时收到 reset
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeType {
+    Created,
+    Updated,
+    Deleted,
+    // todo 变为已完成，同时也会发布 updated
+    Completed,
+}
+
+impl ChangeType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeType::Created => "created",
+            ChangeType::Updated => "updated",
+            ChangeType::Deleted => "deleted",
+            ChangeType::Completed => "completed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ChangeEvent {
+    pub id: u64,
+    // matter / repeat_task / todo / tag / kv / notification / comment / reminder / attachment
+    pub entity: &'static str,
+    #[serde(rename = "type")]
+    pub type_: ChangeType,
+    // 批量操作（例如全部标记为已读）时为空
+    pub entity_id: Option<String>,
+    // 变更后的数据，删除时为空；批量操作时为操作参数
+    pub data: Option<Value>,
+    pub at: DateTime<Utc>,
+}
+
+// 推送给订阅者的内容
+#[derive(Debug, Clone)]
+pub enum StreamItem {
+    Change(ChangeEvent),
+    // 有事件丢失，客户端需要重新拉取
+    Reset,
+}
+
+// 为空的条件不过滤
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub entities: Option<HashSet<String>>,
+    pub types: Option<HashSet<String>>,
+}
+
+fn parse_list(value: Option<&str>) -> Option<HashSet<String>> {
+    let items: HashSet<String> = value?
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    (!items.is_empty()).then_some(items)
+}
+
+impl EventFilter {
+    // entity 和 type 均为逗号分隔的列表，例如 entity=matter,todo&type=created,deleted
+    pub fn parse(entity: Option<&str>, type_: Option<&str>) -> Self {
+        Self {
+            entities: parse_list(entity),
+            types: parse_list(type_),
+        }
+    }
+
+    fn matches(&self, item: &StreamItem) -> bool {
+        let StreamItem::Change(event) = item else {
+            return true;
+        };
+        self.entities
+            .as_ref()
+            .is_none_or(|e| e.contains(event.entity))
+            && self
+                .types
+                .as_ref()
+                .is_none_or(|t| t.contains(event.type_.as_str()))
+    }
+}
+
+struct History {
+    next_id: u64,
+    recent: VecDeque<ChangeEvent>,
+}
+
+struct EventBus {
+    history: Mutex<History>,
+    sender: broadcast::Sender<ChangeEvent>,
+    // 每次 close_streams 加一，订阅者看到变化后结束
+    epoch: watch::Sender<u64>,
+}
+
+static BUS: Lazy<EventBus> = Lazy::new(|| EventBus {
+    history: Mutex::new(History {
+        // 以启动时间开头，重启后客户端带来的旧 id 不会和新事件混淆
+        next_id: Utc::now().timestamp_millis() as u64 * 1000,
+        recent: VecDeque::with_capacity(HISTORY_SIZE),
+    }),
+    sender: broadcast::channel(CHANNEL_CAPACITY).0,
+    epoch: watch::channel(0).0,
+});
+
+pub fn publish(
+    entity: &'static str,
+    type_: ChangeType,
+    entity_id: Option<String>,
+    data: Option<Value>,
+) {
+    // 持锁发送，保证订阅时补发的历史和实时事件之间不重不漏
+    let mut history = BUS.history.lock().unwrap();
+    let event = ChangeEvent {
+        id: history.next_id,
+        entity,
+        type_,
+        entity_id,
+        data,
+        at: Utc::now(),
+    };
+    history.next_id += 1;
+    if history.recent.len() == HISTORY_SIZE {
+        history.recent.pop_front();
+    }
+    history.recent.push_back(event.clone());
+    // 没有订阅者时发送失败，忽略即可
+    let _ = BUS.sender.send(event);
+}
+
+// 补发 last_id 之后的事件，last_id 太旧或来自上次启动时返回 reset
+fn replay(history: &History, last_id: Option<u64>) -> Vec<StreamItem> {
+    let Some(last_id) = last_id else {
+        return Vec::new();
+    };
+    let oldest = history
+        .recent
+        .front()
+        .map_or(history.next_id, |event| event.id);
+    if last_id.saturating_add(1) < oldest || last_id >= history.next_id {
+        return vec![StreamItem::Reset];
+    }
+    history
+        .recent
+        .iter()
+        .filter(|event| event.id > last_id)
+        .cloned()
+        .map(StreamItem::Change)
+        .collect()
+}
+
+// 订阅变更事件，close_streams 后结束
+pub fn subscribe(
+    filter: EventFilter,
+    last_id: Option<u64>,
+) -> impl Stream<Item = StreamItem> + Send + 'static {
+    let (missed, receiver) = {
+        let history = BUS.history.lock().unwrap();
+        (replay(&history, last_id), BUS.sender.subscribe())
+    };
+    let live = stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            Ok(event) => Some((StreamItem::Change(event), receiver)),
+            Err(RecvError::Lagged(skipped)) => {
+                log::warn!("Event subscriber lagged, skipped {} events", skipped);
+                Some((StreamItem::Reset, receiver))
+            }
+            Err(RecvError::Closed) => None,
+        }
+    });
+    let mut epoch = BUS.epoch.subscribe();
+    let closed = async move {
+        let _ = epoch.changed().await;
+    };
+    stream::iter(missed)
+        .chain(live)
+        .filter(move |item| futures::future::ready(filter.matches(item)))
+        .take_until(closed)
+}
+
+// 进程内的订阅者（例如出站 webhook）使用，不补发历史，也不受 close_streams 影响
+pub fn listen() -> broadcast::Receiver<ChangeEvent> {
+    BUS.sender.subscribe()
+}
+
+// 结束所有订阅，HTTP 服务器停止时调用，避免长连接拖住关闭
+pub fn close_streams() {
+    BUS.epoch.send_modify(|epoch| *epoch += 1);
+}
+
+// 数据整体替换后（例如切换 profile）调用：清空历史并结束所有订阅，
+// 客户端带着之前的 id 重连时收到 reset，重新拉取全部数据
+pub fn reset() {
+    {
+        let mut history = BUS.history.lock().unwrap();
+        history.recent.clear();
+        // 空出一个 id，最后一个事件的 id 也会被当作过旧
+        history.next_id += 1;
+    }
+    close_streams();
+}
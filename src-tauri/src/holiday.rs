@@ -0,0 +1,489 @@
+// 节假日与调休日历
+// 内置中国大陆法定节假日安排，另外可以从 <app_data>/holidays/*.ics 加载更多日历，
+// 同一天以后加载的为准（文件按文件名顺序加载，都晚于内置数据）
+
+use crate::database::RepeatTask;
+use crate::recurrence::{Occurrence, Recurrence};
+use chrono::{NaiveDate, NaiveDateTime};
+use once_cell::sync::{Lazy, OnceCell};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tauri::command;
+use thiserror::Error;
+
+const BUILTIN_SOURCE: &str = "cn";
+const ICS_EXTENSION: &str = "ics";
+// 单个事件最多展开的天数，更长的事件不是节假日安排，跳过以免逐日展开
+const MAX_EVENT_DAYS: i64 = 31;
+
+// 法定节假日（起止日期均包含）
+const CN_HOLIDAYS: &[(&str, &str, &str)] = &[
+    ("2024-01-01", "2024-01-01", "元旦"),
+    ("2024-02-10", "2024-02-17", "春节"),
+    ("2024-04-04", "2024-04-06", "清明节"),
+    ("2024-05-01", "2024-05-05", "劳动节"),
+    ("2024-06-08", "2024-06-10", "端午节"),
+    ("2024-09-15", "2024-09-17", "中秋节"),
+    ("2024-10-01", "2024-10-07", "国庆节"),
+    ("2025-01-01", "2025-01-01", "元旦"),
+    ("2025-01-28", "2025-02-04", "春节"),
+    ("2025-04-04", "2025-04-06", "清明节"),
+    ("2025-05-01", "2025-05-05", "劳动节"),
+    ("2025-05-31", "2025-06-02", "端午节"),
+    ("2025-10-01", "2025-10-08", "国庆节、中秋节"),
+    ("2026-01-01", "2026-01-03", "元旦"),
+    ("2026-02-15", "2026-02-23", "春节"),
+    ("2026-04-04", "2026-04-06", "清明节"),
+    ("2026-05-01", "2026-05-05", "劳动节"),
+    ("2026-06-19", "2026-06-21", "端午节"),
+    ("2026-09-25", "2026-09-27", "中秋节"),
+    ("2026-10-01", "2026-10-07", "国庆节"),
+];
+
+// 调休上班的周末
+const CN_WORKDAYS: &[(&str, &str)] = &[
+    ("2024-02-04", "春节"),
+    ("2024-02-18", "春节"),
+    ("2024-04-07", "清明节"),
+    ("2024-04-28", "劳动节"),
+    ("2024-05-11", "劳动节"),
+    ("2024-09-14", "中秋节"),
+    ("2024-09-29", "国庆节"),
+    ("2024-10-12", "国庆节"),
+    ("2025-01-26", "春节"),
+    ("2025-02-08", "春节"),
+    ("2025-04-27", "劳动节"),
+    ("2025-09-28", "国庆节、中秋节"),
+    ("2025-10-11", "国庆节、中秋节"),
+    ("2026-01-04", "元旦"),
+    ("2026-02-14", "春节"),
+    ("2026-02-28", "春节"),
+    ("2026-05-09", "劳动节"),
+    ("2026-09-20", "国庆节"),
+    ("2026-10-10", "国庆节"),
+];
+
+#[derive(Error, Debug)]
+pub enum HolidayError {
+    #[error("无效的 iCalendar 文件：{0}")]
+    InvalidIcs(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DayKind {
+    Holiday,
+    // 调休上班
+    Workday,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct HolidayEntry {
+    pub date: NaiveDate,
+    pub kind: DayKind,
+    pub name: String,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HolidayCalendar {
+    days: BTreeMap<NaiveDate, HolidayEntry>,
+}
+
+// 重复任务在节假日上的处理方式
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HolidayRule {
+    pub skip_holidays: bool,
+    pub makeup_workdays: bool,
+}
+
+impl From<&RepeatTask> for HolidayRule {
+    fn from(task: &RepeatTask) -> Self {
+        HolidayRule {
+            skip_holidays: task.skip_holidays,
+            makeup_workdays: task.makeup_workdays,
+        }
+    }
+}
+
+fn parse_date(text: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").expect("内置节假日数据格式错误")
+}
+
+impl HolidayCalendar {
+    pub fn builtin() -> Self {
+        let mut calendar = HolidayCalendar::default();
+        for (start, end, name) in CN_HOLIDAYS {
+            let (start, end) = (parse_date(start), parse_date(end));
+            for date in start.iter_days().take_while(|d| *d <= end) {
+                calendar.insert(date, DayKind::Holiday, name, BUILTIN_SOURCE);
+            }
+        }
+        for (date, name) in CN_WORKDAYS {
+            calendar.insert(parse_date(date), DayKind::Workday, name, BUILTIN_SOURCE);
+        }
+        calendar
+    }
+
+    fn insert(&mut self, date: NaiveDate, kind: DayKind, name: &str, source: &str) {
+        self.days.insert(
+            date,
+            HolidayEntry {
+                date,
+                kind,
+                name: name.to_string(),
+                source: source.to_string(),
+            },
+        );
+    }
+
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.days
+            .get(&date)
+            .is_some_and(|e| e.kind == DayKind::Holiday)
+    }
+
+    pub fn entries_in_year(&self, year: i32) -> Vec<HolidayEntry> {
+        let (Some(from), Some(to)) = (
+            NaiveDate::from_ymd_opt(year, 1, 1),
+            NaiveDate::from_ymd_opt(year, 12, 31),
+        ) else {
+            return Vec::new();
+        };
+        self.days.range(from..=to).map(|(_, e)| e.clone()).collect()
+    }
+
+    // 加载 .ics 中的全天事件，返回加载的天数
+    // SUMMARY 含“班”或 X-APPLE-SPECIAL-DAY:ALTERNATE-WORKDAY 的事件视为调休上班，其余视为放假
+    pub fn load_ics(&mut self, source: &str, text: &str) -> Result<usize, HolidayError> {
+        let mut loaded = 0;
+        for event in parse_events(text)? {
+            let end = event
+                .end
+                .unwrap_or(event.start)
+                .max(event.start + chrono::Duration::days(1));
+            let days = (end - event.start).num_days();
+            if days > MAX_EVENT_DAYS {
+                log::warn!("Skipping {} day event {} in {}", days, event.name, source);
+                continue;
+            }
+            for date in event.start.iter_days().take_while(|d| *d < end) {
+                self.insert(date, event.kind, &event.name, source);
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+
+    fn rule_applies(&self, recurrence: &Recurrence, date: NaiveDate) -> bool {
+        // 调休日只在规则仍然有效的时间段内补上（不早于 DTSTART，不晚于 UNTIL/COUNT 结束）
+        date >= recurrence.dtstart.date()
+            && !recurrence
+                .next_occurrences(date.and_hms_opt(0, 0, 0).unwrap(), 1)
+                .is_empty()
+    }
+
+    fn makeup_occurrences(
+        &self,
+        recurrence: &Recurrence,
+        from: NaiveDateTime,
+        to: Option<NaiveDateTime>,
+    ) -> Vec<Occurrence> {
+        self.days
+            .values()
+            .filter(|e| e.kind == DayKind::Workday)
+            .map(|e| recurrence.occurrence_at(e.date.and_time(recurrence.dtstart.time())))
+            .filter(|o| o.start >= from && to.is_none_or(|to| o.start < to))
+            .filter(|o| self.rule_applies(recurrence, o.start.date()))
+            .collect()
+    }
+
+    // 按任务的节假日设置调整后的发生：跳过放假的日期，并在调休上班日补一次
+    pub fn occurrences_between(
+        &self,
+        recurrence: &Recurrence,
+        rule: HolidayRule,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Vec<Occurrence> {
+        let mut occurrences: Vec<Occurrence> = recurrence
+            .occurrences_between(from, to)
+            .into_iter()
+            .filter(|o| !(rule.skip_holidays && self.is_holiday(o.start.date())))
+            .collect();
+        if rule.makeup_workdays {
+            occurrences.extend(self.makeup_occurrences(recurrence, from, Some(to)));
+        }
+        merge(occurrences)
+    }
+
+    pub fn next_occurrences(
+        &self,
+        recurrence: &Recurrence,
+        rule: HolidayRule,
+        after: NaiveDateTime,
+        limit: usize,
+    ) -> Vec<Occurrence> {
+        let mut occurrences: Vec<Occurrence> = recurrence
+            .occurrences()
+            .filter(|o| o.start >= after)
+            .filter(|o| !(rule.skip_holidays && self.is_holiday(o.start.date())))
+            .take(limit)
+            .collect();
+        if rule.makeup_workdays {
+            // 超过第 limit 次原有发生之后的调休日不会出现在结果里
+            let to = match occurrences.len() {
+                n if n == limit => occurrences.last().map(|o| o.start),
+                _ => None,
+            };
+            occurrences.extend(self.makeup_occurrences(recurrence, after, to));
+        }
+        let mut occurrences = merge(occurrences);
+        occurrences.truncate(limit);
+        occurrences
+    }
+}
+
+// 按开始时间排序，同一天只保留一次
+fn merge(mut occurrences: Vec<Occurrence>) -> Vec<Occurrence> {
+    occurrences.sort_by_key(|o| o.start);
+    occurrences.dedup_by_key(|o| o.start.date());
+    occurrences
+}
+
+struct IcsEvent {
+    start: NaiveDate,
+    end: Option<NaiveDate>,
+    name: String,
+    kind: DayKind,
+}
+
+fn ics_date(value: &str) -> Result<NaiveDate, HolidayError> {
+    let digits = value.get(..8).unwrap_or(value);
+    NaiveDate::parse_from_str(digits, "%Y%m%d")
+        .map_err(|_| HolidayError::InvalidIcs(format!("无法解析日期 {}", value)))
+}
+
+fn ics_unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+fn parse_events(text: &str) -> Result<Vec<IcsEvent>, HolidayError> {
+    // 展开折行：以空格或制表符开头的行是上一行的延续
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match line.strip_prefix([' ', '\t']) {
+            Some(rest) if !lines.is_empty() => lines.last_mut().unwrap().push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    if !lines
+        .iter()
+        .any(|l| l.trim().eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err(HolidayError::InvalidIcs("缺少 BEGIN:VCALENDAR".into()));
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<(Option<NaiveDate>, Option<NaiveDate>, String, bool)> = None;
+    for line in &lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // 属性参数（如 DTSTART;VALUE=DATE）不影响解析
+        let name = name.split(';').next().unwrap_or(name).to_ascii_uppercase();
+        let value = value.trim();
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", _) if value.eq_ignore_ascii_case("VEVENT") => {
+                current = Some((None, None, String::new(), false));
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                let (start, end, summary, workday) = current.take().unwrap();
+                let start =
+                    start.ok_or_else(|| HolidayError::InvalidIcs("事件缺少 DTSTART".into()))?;
+                let kind = if workday || summary.contains('班') {
+                    DayKind::Workday
+                } else {
+                    DayKind::Holiday
+                };
+                events.push(IcsEvent {
+                    start,
+                    end,
+                    name: summary,
+                    kind,
+                });
+            }
+            ("DTSTART", Some(event)) => event.0 = Some(ics_date(value)?),
+            ("DTEND", Some(event)) => event.1 = Some(ics_date(value)?),
+            ("SUMMARY", Some(event)) => event.2 = ics_unescape(value),
+            ("X-APPLE-SPECIAL-DAY", Some(event)) => {
+                event.3 = value.eq_ignore_ascii_case("ALTERNATE-WORKDAY");
+            }
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+static CALENDAR: Lazy<RwLock<Arc<HolidayCalendar>>> =
+    Lazy::new(|| RwLock::new(Arc::new(HolidayCalendar::builtin())));
+static CALENDAR_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+// 当前使用的日历
+pub fn calendar() -> Arc<HolidayCalendar> {
+    CALENDAR.read().unwrap().clone()
+}
+
+fn load_dir(dir: &Path) -> HolidayCalendar {
+    let mut calendar = HolidayCalendar::builtin();
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == ICS_EXTENSION))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    for path in files {
+        let source = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let result = std::fs::read_to_string(&path)
+            .map_err(HolidayError::from)
+            .and_then(|text| calendar.load_ics(&source, &text));
+        match result {
+            Ok(count) => log::info!("Loaded {} holiday entries from {}", count, path.display()),
+            Err(e) => log::warn!("Failed to load holidays from {}: {}", path.display(), e),
+        }
+    }
+    calendar
+}
+
+// 启动时调用，加载目录中的 .ics 日历
+pub fn init(dir: PathBuf) {
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        log::warn!("Failed to create holiday directory: {}", e);
+    }
+    let calendar = load_dir(&dir);
+    *CALENDAR.write().unwrap() = Arc::new(calendar);
+    let _ = CALENDAR_DIR.set(dir);
+}
+
+// 导入 .ics 文件：先校验内容，再复制到日历目录并重新加载，返回导入的天数
+#[command]
+pub async fn import_holiday_calendar(path: String) -> Result<usize, String> {
+    let dir = CALENDAR_DIR
+        .get()
+        .ok_or_else(|| "节假日日历尚未初始化".to_string())?;
+    let path = PathBuf::from(path);
+    let text = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let count = HolidayCalendar::default()
+        .load_ics("", &text)
+        .map_err(|e| e.to_string())?;
+    let file_name = path
+        .file_stem()
+        .map(|s| format!("{}.{}", s.to_string_lossy(), ICS_EXTENSION))
+        .ok_or_else(|| "无效的文件名".to_string())?;
+    std::fs::write(dir.join(file_name), text).map_err(|e| e.to_string())?;
+
+    *CALENDAR.write().unwrap() = Arc::new(load_dir(dir));
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        parse_date(text)
+    }
+
+    fn calendar(ics: &str) -> HolidayCalendar {
+        let mut calendar = HolidayCalendar::default();
+        calendar.load_ics("test", ics).unwrap();
+        calendar
+    }
+
+    const ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20270101\r
+DTEND;VALUE=DATE:20270104\r
+SUMMARY:元旦\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20270109\r
+SUMMARY:元旦补\r
+ 班\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20270110T000000\r
+SUMMARY:Makeup day\r
+X-APPLE-SPECIAL-DAY:ALTERNATE-WORKDAY\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn parses_events_and_folded_lines() {
+        let events = parse_events(ICS).unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].start, date("2027-01-01"));
+        assert_eq!(events[0].end, Some(date("2027-01-04")));
+        assert_eq!(events[0].kind, DayKind::Holiday);
+        // 折行拼接后才出现“班”
+        assert_eq!(events[1].name, "元旦补班");
+        assert_eq!(events[1].kind, DayKind::Workday);
+        assert_eq!(events[2].start, date("2027-01-10"));
+        assert_eq!(events[2].kind, DayKind::Workday);
+    }
+
+    #[test]
+    fn loads_each_day_until_dtend() {
+        let calendar = calendar(ICS);
+        // DTEND 不包含在内，没有 DTEND 的事件只有一天
+        assert!(calendar.is_holiday(date("2027-01-03")));
+        assert!(!calendar.is_holiday(date("2027-01-04")));
+        let entries = calendar.entries_in_year(2027);
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[3].date, date("2027-01-09"));
+        assert_eq!(entries[3].source, "test");
+    }
+
+    #[test]
+    fn skips_overlong_events() {
+        let calendar = calendar(
+            "BEGIN:VCALENDAR
+BEGIN:VEVENT
+DTSTART:20270101
+DTEND:99991231
+SUMMARY:全年
+END:VEVENT
+BEGIN:VEVENT
+DTSTART:20270201
+DTEND:20270310
+SUMMARY:寒假
+END:VEVENT
+END:VCALENDAR",
+        );
+        assert!(calendar.entries_in_year(2027).is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert!(parse_events("BEGIN:VEVENT\nEND:VEVENT").is_err());
+        assert!(parse_events("BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:x\nEND:VEVENT").is_err());
+        assert!(parse_events("BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:2027\nEND:VEVENT").is_err());
+    }
+}
@@ -1,16 +1,44 @@
-use crate::database::SafeConnection;
-use crate::database::{KVStore, Matter, NotificationRecord, RepeatTask, Tag, Todo};
+use crate::attachment::{self, MAX_ATTACHMENT_SIZE, OWNER_MATTER, OWNER_TODO};
+use crate::auth::{self, AuthError};
+use crate::cors::{self, CorsPolicy};
+use crate::database::{
+    IncomingHook, Matter, MatterComment, MatterReminder, NotificationRecord, RepeatTask, Todo,
+    TodoFilter, Webhook,
+};
+use crate::digest;
+use crate::events::{self, EventFilter, StreamItem};
+use crate::holiday::{self, HolidayRule};
+use crate::incoming_hook::{self, HookError};
+use crate::notification_channel::{self, ChannelConfig};
+use crate::openapi;
+use crate::patch::{self, Versioned};
+use crate::problem::{self, ErrorDetail, Lang, V2Request};
+use crate::recurrence::{self, Recurrence};
+use crate::reminder::{self, OWNER_REPEAT_TASK};
+use crate::repeat_scheduler;
+use crate::repository::Repositories;
+use crate::server_config::ServerConfig;
+use crate::validation::{self, Api, Mode, ValidationError};
+use crate::webhook::{self, WebhookError};
 use axum::{
-    extract::{Path, Query, State},
-    response::IntoResponse,
-    routing::{delete, get, post, put},
-    Json, Router,
+    body::Bytes,
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::{DefaultBodyLimit, FromRequest, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::sse::{Event, KeepAlive, Sse},
+    response::{Html, IntoResponse, Response},
+    routing::{delete, get, patch, post, put},
+    Extension, Form, Json, Router,
 };
-use chrono::{DateTime, Utc};
-use once_cell::sync::OnceCell;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use futures::{SinkExt, Stream, StreamExt};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::sync::atomic::{AtomicU16, Ordering};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::{oneshot, Mutex};
@@ -46,34 +74,120 @@ pub enum ServerError {
     StartupError(String),
     #[error("数据库错误：{0}")]
     DatabaseError(String),
-    #[error("效请求：{0}")]
+    #[error("无效请求：{0}")]
     BadRequest(String),
     #[error("未找到资源：{0}")]
     NotFound(String),
+    #[error("未授权：{0}")]
+    Unauthorized(String),
+    #[error("无权访问：{0}")]
+    Forbidden(String),
+    #[error("数据校验失败：{0}")]
+    Validation(#[from] ValidationError),
+    #[error("数据已被修改：{0}")]
+    Conflict(String),
 }
 
 impl IntoResponse for ServerError {
+    // v1 的 HTTP 状态码总是 200，错误码在 ApiResponse 中；ErrorDetail 供 /v2 改写为 problem+json
     fn into_response(self) -> axum::response::Response {
-        let (code, message) = match self {
-            ServerError::NotFound(msg) => (404, msg),
-            ServerError::DatabaseError(msg) => (500, msg),
-            ServerError::StartupError(msg) => (500, msg),
-            ServerError::BadRequest(msg) => (400, msg),
+        let (status, error_code, message) = match self {
+            ServerError::NotFound(msg) => (StatusCode::NOT_FOUND, problem::NOT_FOUND, msg),
+            ServerError::DatabaseError(msg) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                problem::STORAGE_ERROR,
+                msg,
+            ),
+            ServerError::StartupError(msg) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                problem::INTERNAL_ERROR,
+                msg,
+            ),
+            ServerError::BadRequest(msg) => {
+                (StatusCode::BAD_REQUEST, problem::INVALID_REQUEST, msg)
+            }
+            ServerError::Unauthorized(msg) => {
+                (StatusCode::UNAUTHORIZED, problem::UNAUTHORIZED, msg)
+            }
+            ServerError::Forbidden(msg) => (StatusCode::FORBIDDEN, problem::FORBIDDEN, msg),
+            ServerError::Conflict(msg) => (StatusCode::CONFLICT, problem::CONFLICT, msg),
+            // v1 在 data 中返回每个字段的错误
+            ServerError::Validation(e) => {
+                let mut response = Json(ApiResponse {
+                    code: 400,
+                    msg: e.to_string(),
+                    data: Some(e.field_errors(Lang::Zh)),
+                })
+                .into_response();
+                response.extensions_mut().insert(ErrorDetail {
+                    status: StatusCode::UNPROCESSABLE_ENTITY,
+                    code: problem::VALIDATION_FAILED,
+                    detail: e.to_string(),
+                    validation: Some(e),
+                });
+                return response;
+            }
         };
 
-        Json(ApiResponse::<()>::error(code, &message)).into_response()
+        let mut response =
+            Json(ApiResponse::<()>::error(status.as_u16() as i32, &message)).into_response();
+        response.extensions_mut().insert(ErrorDetail {
+            status,
+            code: error_code,
+            detail: message,
+            validation: None,
+        });
+        response
     }
 }
 
 pub struct AppState {
     shutdown_tx: Option<oneshot::Sender<()>>,
-    db: Arc<SafeConnection>,
+    server_task: Option<tauri::async_runtime::JoinHandle<()>>,
+    storage: Repositories,
 }
 
-#[derive(Debug, Deserialize)]
+// start/end 可以是带时区的 RFC 3339 时间，也可以是不带时区的时间或日期，
+// 后两者按 tz（IANA 时区名，默认系统本地时区）解释；只给日期时 end 包含当天
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct TimeRangeQuery {
-    start: DateTime<Utc>,
-    end: DateTime<Utc>,
+    start: String,
+    end: String,
+    tz: Option<String>,
+}
+
+fn parse_tz_param(tz: Option<&str>) -> Result<Option<Tz>, ServerError> {
+    match tz.map(str::trim) {
+        Some(name) if !name.is_empty() => recurrence::parse_tz(name)
+            .map(Some)
+            .map_err(|e| ServerError::BadRequest(e.to_string())),
+        _ => Ok(None),
+    }
+}
+
+fn parse_range_bound(
+    value: &str,
+    tz: Option<Tz>,
+    is_end: bool,
+) -> Result<DateTime<Utc>, ServerError> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
+        return Ok(recurrence::zoned_to_utc(naive, tz));
+    }
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => {
+            let date = if is_end {
+                date + chrono::Duration::days(1)
+            } else {
+                date
+            };
+            Ok(recurrence::zoned_to_utc(date.and_time(NaiveTime::MIN), tz))
+        }
+        Err(_) => Err(ServerError::BadRequest(format!("Invalid time: {}", value))),
+    }
 }
 
 trait RouteConfig {
@@ -88,6 +202,7 @@ impl RouteConfig for ApiRoutes {
             .route("/matter", post(create_matter))
             .route("/matter/:id", get(get_matter))
             .route("/matter/:id", put(update_matter))
+            .route("/matter/:id", patch(patch_matter))
             .route("/matter/:id", delete(delete_matter))
             .route("/matter/range", get(get_matters_by_range))
             .route("/matter", get(get_all_matters))
@@ -102,6 +217,7 @@ impl RouteConfig for ApiRoutes {
             .route("/repeat-task", post(create_repeat_task))
             .route("/repeat-task/:id", get(get_repeat_task))
             .route("/repeat-task/:id", put(update_repeat_task))
+            .route("/repeat-task/:id", patch(patch_repeat_task))
             .route("/repeat-task/:id", delete(delete_repeat_task))
             .route("/repeat-task", get(get_all_repeat_tasks))
             .route("/repeat-task/active", get(get_active_repeat_tasks))
@@ -109,18 +225,75 @@ impl RouteConfig for ApiRoutes {
                 "/repeat-task/:id/status/:status",
                 put(update_repeat_task_status),
             )
+            .route(
+                "/repeat-task/:id/occurrences",
+                get(get_repeat_task_occurrences),
+            )
+            .route(
+                "/repeat-task/:id/materialized",
+                get(get_materialized_occurrences),
+            )
+            .route("/repeat-task/:id/matter", post(create_repeat_task_matter))
+            .route("/recurrence/preview", post(preview_recurrence))
+            .route("/holidays", get(get_holidays))
+            .route("/digest", get(get_digest))
             .route("/todo", post(create_todo))
             .route("/todo/:id", get(get_todo))
             .route("/todo/:id", put(update_todo))
+            .route("/todo/:id", patch(patch_todo))
             .route("/todo/:id", delete(delete_todo))
             .route("/todo", get(get_all_todos))
+            .route("/todo/reorder", put(reorder_todos))
+            .route("/matter/:id/comments", get(get_matter_comments))
+            .route("/matter/:id/comments", post(create_matter_comment))
+            .route("/comment/:id", get(get_matter_comment))
+            .route("/comment/:id", put(update_matter_comment))
+            .route("/comment/:id", patch(patch_matter_comment))
+            .route("/comment/:id", delete(delete_matter_comment))
+            .route("/matter/:id/reminders", get(get_matter_reminders))
+            .route("/matter/:id/reminders", post(create_matter_reminder))
+            .route("/repeat-task/:id/reminders", get(get_repeat_task_reminders))
+            .route(
+                "/repeat-task/:id/reminders",
+                post(create_repeat_task_reminder),
+            )
+            .route("/reminder/:id", get(get_matter_reminder))
+            .route("/reminder/:id", put(update_matter_reminder))
+            .route("/reminder/:id", patch(patch_matter_reminder))
+            .route("/reminder/:id", delete(delete_matter_reminder))
+            .route("/matter/:id/attachments", get(get_matter_attachments))
+            .route(
+                "/matter/:id/attachments",
+                post(upload_matter_attachment).layer(DefaultBodyLimit::max(MAX_ATTACHMENT_SIZE)),
+            )
+            .route("/todo/:id/attachments", get(get_todo_attachments))
+            .route(
+                "/todo/:id/attachments",
+                post(upload_todo_attachment).layer(DefaultBodyLimit::max(MAX_ATTACHMENT_SIZE)),
+            )
+            .route("/attachment/:id", get(get_attachment))
+            .route("/attachment/:id", delete(delete_attachment))
+            .route("/attachment/:id/content", get(download_attachment))
             .route("/notification", post(create_notification))
             .route("/notification/:id", get(get_notification))
             .route("/notification/:id", put(update_notification))
+            .route("/notification/:id", patch(patch_notification))
             .route("/notification/:id", delete(delete_notification))
             // .route("/notification", get(get_all_notifications))
             .route("/notification/unread", get(get_unread_notifications))
             .route("/notification/:id/read", put(mark_notification_as_read))
+            .route("/notification/:id/snooze", put(snooze_notification))
+            .route(
+                "/notification/:id/acknowledge",
+                put(acknowledge_notification),
+            )
+            .route("/notification/:id/dismiss", put(dismiss_notification))
+            .route(
+                "/notification/:id/deliveries",
+                get(get_notification_deliveries),
+            )
+            .route("/notification-channels", get(get_notification_channels))
+            .route("/notification-channels", put(set_notification_channels))
             // make special type notification as read
             .route(
                 "/notification/read/:type",
@@ -130,46 +303,167 @@ impl RouteConfig for ApiRoutes {
                 "/notification/read-all",
                 put(mark_all_notifications_as_read),
             )
+            .route("/webhooks", get(get_all_webhooks))
+            .route("/webhooks", post(create_webhook))
+            .route("/webhooks/:id", get(get_webhook))
+            .route("/webhooks/:id", put(update_webhook))
+            .route("/webhooks/:id", patch(patch_webhook))
+            .route("/webhooks/:id", delete(delete_webhook))
+            .route("/webhooks/:id/deliveries", get(get_webhook_deliveries))
+            .route("/webhooks/:id/test", post(test_webhook))
+            .route("/incoming-hooks", get(get_all_incoming_hooks))
+            .route("/incoming-hooks", post(create_incoming_hook))
+            .route("/incoming-hooks/:id", get(get_incoming_hook))
+            .route("/incoming-hooks/:id", put(update_incoming_hook))
+            .route("/incoming-hooks/:id", patch(patch_incoming_hook))
+            .route("/incoming-hooks/:id", delete(delete_incoming_hook))
+            .route("/events", get(stream_events))
+            .route("/events/ws", get(events_websocket))
+            .route_layer(middleware::from_fn_with_state(state.clone(), require_token))
+            // 文档不需要令牌
+            .route("/openapi.json", get(get_openapi))
+            .route("/docs", get(get_api_docs))
+            // 路径中的令牌即凭证
+            .route("/hooks/:token", post(receive_hook))
             .with_state(state)
     }
 }
 
+// 所有接口都需要有效的令牌，只读令牌只能访问 GET/HEAD
+async fn require_token(
+    State(state): State<Arc<Mutex<AppState>>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ServerError> {
+    let storage = state.lock().await.storage.clone();
+    let token = auth::request_token(request.headers(), request.uri());
+    auth::authorize(&storage, request.method(), token).map_err(|e| match e {
+        AuthError::MissingToken | AuthError::InvalidToken => {
+            ServerError::Unauthorized(e.to_string())
+        }
+        AuthError::ReadOnly => ServerError::Forbidden(e.to_string()),
+        AuthError::Storage(msg) => ServerError::DatabaseError(msg),
+    })?;
+    Ok(next.run(request).await)
+}
+
+async fn get_openapi() -> impl IntoResponse {
+    let base_url = server_info().map(|info| info.base_url);
+    Json(openapi::spec(base_url.as_deref()))
+}
+
+async fn get_api_docs() -> impl IntoResponse {
+    Html(openapi::DOCS_HTML)
+}
+
+// 停止时等待正在处理的请求完成的最长时间
+const SHUTDOWN_DRAIN_SECS: u64 = 10;
+
+// 依次尝试 port 到 port + fallback_ports，返回第一个能监听的端口
+async fn bind(config: &ServerConfig) -> Result<tokio::net::TcpListener, ServerError> {
+    let last_port = config.port.saturating_add(config.fallback_ports);
+    let mut last_error = None;
+    for port in config.port..=last_port {
+        match tokio::net::TcpListener::bind((config.host.as_str(), port)).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) => {
+                log::warn!("Failed to bind {}:{}: {}", config.host, port, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(ServerError::StartupError(format!(
+        "{}:{}-{} 均无法监听：{}",
+        config.host,
+        config.port,
+        last_port,
+        last_error.map(|e| e.to_string()).unwrap_or_default()
+    )))
+}
+
 #[derive(Clone)]
 pub struct HttpServer {
     state: Arc<Mutex<AppState>>,
 }
 
 impl HttpServer {
-    pub fn new(db: Arc<SafeConnection>) -> Self {
+    pub fn new(storage: Repositories) -> Self {
         let state = Arc::new(Mutex::new(AppState {
             shutdown_tx: None,
-            db,
+            server_task: None,
+            storage,
         }));
         Self { state }
     }
 
-    pub async fn start(&self, port: u16) -> Result<(), ServerError> {
-        let app = ApiRoutes.configure(self.state.clone());
-        log::info!("HTTP server starting on port {}", port);
-        // localhost
-        let addr = format!("localhost:{}", port);
-        let listener = tokio::net::TcpListener::bind(&addr)
-            .await
-            .map_err(|e| ServerError::StartupError(e.to_string()))?;
+    // 绑定端口并在后台开始处理请求，返回实际监听的地址
+    pub async fn start(&self, config: &ServerConfig) -> Result<SocketAddr, ServerError> {
+        let mut state = self.state.lock().await;
+        if state.shutdown_tx.is_some() {
+            return Err(ServerError::StartupError(
+                "HTTP server already running".into(),
+            ));
+        }
 
+        let listener = bind(config).await?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| ServerError::StartupError(e.to_string()))?;
         log::info!("HTTP server listening on {}", addr);
 
-        axum::serve(listener, app)
-            .await
-            .map_err(|e| ServerError::StartupError(e.to_string()))?;
+        let cors = Arc::new(CorsPolicy::new(&config.allowed_origins));
+        // /v2 与 v1 共用路由，响应格式由 problem::v2_responses 改写
+        let routes = ApiRoutes.configure(self.state.clone());
+        let app = routes
+            .clone()
+            .nest(problem::V2_PREFIX, routes)
+            .layer(middleware::from_fn_with_state(cors, cors::handle))
+            .layer(middleware::from_fn(problem::v2_responses));
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let task = tauri::async_runtime::spawn(async move {
+            let result = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_rx.await;
+                })
+                .await;
+            if let Err(e) = result {
+                log::error!("HTTP server error: {}", e);
+            }
+            log::info!("HTTP server stopped");
+        });
+        state.shutdown_tx = Some(shutdown_tx);
+        state.server_task = Some(task);
 
-        Ok(())
+        Ok(addr)
+    }
+
+    // 替换当前使用的存储，正在处理的请求完成后生效
+    pub async fn set_storage(&self, storage: Repositories) {
+        let mut state = self.state.lock().await;
+        state.storage = storage;
+    }
+
+    pub async fn storage(&self) -> Repositories {
+        self.state.lock().await.storage.clone()
     }
 
+    // 停止接受新连接，等待正在处理的请求完成后返回
     pub async fn stop(&self) {
-        if let Ok(mut state) = self.state.try_lock() {
-            if let Some(tx) = state.shutdown_tx.take() {
-                let _ = tx.send(());
+        let (shutdown_tx, task) = {
+            let mut state = self.state.lock().await;
+            (state.shutdown_tx.take(), state.server_task.take())
+        };
+        if let Some(tx) = shutdown_tx {
+            let _ = tx.send(());
+        }
+        // 事件推送是长连接，不主动结束会拖到超时
+        events::close_streams();
+        if let Some(mut task) = task {
+            // 超时后不再等待仍未结束的连接
+            let drain = std::time::Duration::from_secs(SHUTDOWN_DRAIN_SECS);
+            if tokio::time::timeout(drain, &mut task).await.is_err() {
+                log::warn!("HTTP server did not drain in {:?}, aborting", drain);
+                task.abort();
             }
         }
     }
@@ -198,15 +492,50 @@ async fn create_data(
 }
 
 // Matter 相关处理函数
+// 单条数据的响应带上 ETag，客户端修改时通过 If-Match 带回
+fn versioned<T: Serialize + Versioned>(value: T) -> Response {
+    let etag = value.etag();
+    ([(header::ETAG, etag)], Json(ApiResponse::success(value))).into_response()
+}
+
+// 带 If-Match 且与当前版本不一致时拒绝写入，避免覆盖其他窗口的修改
+fn check_version(headers: &HeaderMap, current: &impl Versioned) -> Result<(), ServerError> {
+    if patch::if_match(headers, current) {
+        Ok(())
+    } else {
+        Err(ServerError::Conflict(format!(
+            "Resource has been modified, current ETag is {}",
+            current.etag()
+        )))
+    }
+}
+
+fn parse_patch(body: &Bytes) -> Result<Value, ServerError> {
+    serde_json::from_slice(body).map_err(|e| ServerError::BadRequest(e.to_string()))
+}
+
+fn apply_patch<T: Serialize + serde::de::DeserializeOwned>(
+    current: &T,
+    patch: &Value,
+) -> Result<T, ServerError> {
+    patch::apply(current, patch).map_err(ServerError::BadRequest)
+}
+
 async fn create_matter(
     State(state): State<Arc<Mutex<AppState>>>,
+    v2: Option<Extension<V2Request>>,
     Json(mut matter): Json<Matter>,
 ) -> Result<impl IntoResponse, ServerError> {
+    validation::matter(&mut matter, Mode::Create, api_version(v2))?;
     matter.created_at = Utc::now();
     matter.updated_at = Utc::now();
 
     let state = state.lock().await;
-    Matter::create(&state.db, &matter).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .matters
+        .create(&matter)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::success(matter)))
 }
@@ -216,11 +545,14 @@ async fn get_matter(
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let matter = Matter::get_by_id(&state.db, &id)
+    let matter = state
+        .storage
+        .matters
+        .get_by_id(&id)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?
         .ok_or_else(|| ServerError::NotFound("Matter not found".into()))?;
 
-    Ok(Json(ApiResponse::success(matter)))
+    Ok(versioned(matter))
 }
 
 // get all matters
@@ -228,26 +560,77 @@ async fn get_all_matters(
     State(state): State<Arc<Mutex<AppState>>>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let matters =
-        Matter::get_all(&state.db).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    let matters = state
+        .storage
+        .matters
+        .get_all()
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    let matters = with_comment_summaries(&state.storage, matters)?;
 
     Ok(Json(ApiResponse::success(matters)))
 }
 
+fn api_version(v2: Option<Extension<V2Request>>) -> Api {
+    if v2.is_some() {
+        Api::V2
+    } else {
+        Api::V1
+    }
+}
+
+// PUT 和 PATCH 共用：数据不存在返回 404，If-Match 不一致返回 409，build 由当前数据生成要保存的数据
+fn save_matter(
+    storage: &Repositories,
+    headers: &HeaderMap,
+    api: Api,
+    id: String,
+    build: impl FnOnce(&Matter) -> Result<Matter, ServerError>,
+) -> Result<Response, ServerError> {
+    let current = storage
+        .matters
+        .get_by_id(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Matter not found".into()))?;
+    check_version(headers, &current)?;
+
+    let mut matter = build(&current)?;
+    matter.id = id;
+    matter.created_at = current.created_at;
+    validation::matter(&mut matter, Mode::Update, api)?;
+    matter.updated_at = Utc::now();
+    storage
+        .matters
+        .update(&matter)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(versioned(matter))
+}
+
 async fn update_matter(
     State(state): State<Arc<Mutex<AppState>>>,
     Path(id): Path<String>,
-    Json(mut matter): Json<Matter>,
+    headers: HeaderMap,
+    v2: Option<Extension<V2Request>>,
+    Json(matter): Json<Matter>,
 ) -> Result<impl IntoResponse, ServerError> {
-    matter.id = id;
-    matter.updated_at = Utc::now();
-
     let state = state.lock().await;
-    matter
-        .update(&state.db)
-        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    save_matter(&state.storage, &headers, api_version(v2), id, |_| {
+        Ok(matter)
+    })
+}
 
-    Ok(Json(ApiResponse::success(matter)))
+async fn patch_matter(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    v2: Option<Extension<V2Request>>,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    let patch = parse_patch(&body)?;
+    let state = state.lock().await;
+    save_matter(&state.storage, &headers, api_version(v2), id, |current| {
+        apply_patch(current, &patch)
+    })
 }
 
 async fn delete_matter(
@@ -255,7 +638,28 @@ async fn delete_matter(
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    Matter::delete(&state.db, &id).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .matters
+        .delete(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .comments
+        .delete_by_matter(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .reminders
+        .delete_by_matter(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .matter_reminders
+        .delete_by_owner(reminder::OWNER_MATTER, &id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    attachment::delete_by_owner(&state.storage, OWNER_MATTER, &id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
     Ok(Json(ApiResponse::<()>::success(())))
 }
 
@@ -263,13 +667,335 @@ async fn get_matters_by_range(
     State(state): State<Arc<Mutex<AppState>>>,
     Query(range): Query<TimeRangeQuery>,
 ) -> Result<impl IntoResponse, ServerError> {
+    let tz = parse_tz_param(range.tz.as_deref())?;
+    let start = parse_range_bound(&range.start, tz, false)?;
+    let end = parse_range_bound(&range.end, tz, true)?;
+
     let state = state.lock().await;
-    let matters = Matter::get_by_time_range(&state.db, range.start, range.end)
+    let matters = state
+        .storage
+        .matters
+        .get_by_time_range(start, end)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    let matters = with_comment_summaries(&state.storage, matters)?;
 
     Ok(Json(ApiResponse::success(matters)))
 }
 
+// 列表接口中的 matter 附带评论数和最后一条评论
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct MatterListItem {
+    #[serde(flatten)]
+    matter: Matter,
+    comment_count: i64,
+    last_comment: Option<MatterComment>,
+}
+
+fn with_comment_summaries(
+    storage: &Repositories,
+    matters: Vec<Matter>,
+) -> Result<Vec<MatterListItem>, ServerError> {
+    let mut summaries = storage
+        .comments
+        .get_summaries()
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(matters
+        .into_iter()
+        .map(|matter| {
+            let summary = summaries.remove(&matter.id);
+            MatterListItem {
+                comment_count: summary.as_ref().map_or(0, |s| s.count),
+                last_comment: summary.map(|s| s.last_comment),
+                matter,
+            }
+        })
+        .collect())
+}
+
+// MatterComment 相关处理函数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct CommentRequest {
+    content: String,
+}
+
+async fn get_matter_comments(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let comments = state
+        .storage
+        .comments
+        .get_by_matter(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(comments)))
+}
+
+async fn create_matter_comment(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    Json(payload): Json<CommentRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    if payload.content.trim().is_empty() {
+        return Err(ServerError::BadRequest("Comment content is empty".into()));
+    }
+
+    let state = state.lock().await;
+    state
+        .storage
+        .matters
+        .get_by_id(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Matter not found".into()))?;
+
+    let now = Utc::now();
+    let comment = MatterComment {
+        id: uuid::Uuid::new_v4().to_string(),
+        matter_id: id,
+        content: payload.content,
+        created_at: now,
+        updated_at: now,
+    };
+    state
+        .storage
+        .comments
+        .create(&comment)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(comment)))
+}
+
+fn find_comment(storage: &Repositories, id: &str) -> Result<MatterComment, ServerError> {
+    storage
+        .comments
+        .get_by_id(id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Comment not found".into()))
+}
+
+async fn get_matter_comment(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    Ok(versioned(find_comment(&state.storage, &id)?))
+}
+
+// PUT 和 PATCH 共用，只能修改 content；build 由当前评论生成新的内容
+fn save_comment(
+    storage: &Repositories,
+    headers: &HeaderMap,
+    id: &str,
+    build: impl FnOnce(&MatterComment) -> Result<String, ServerError>,
+) -> Result<Response, ServerError> {
+    let current = find_comment(storage, id)?;
+    check_version(headers, &current)?;
+
+    let content = build(&current)?;
+    if content.trim().is_empty() {
+        return Err(ServerError::BadRequest("Comment content is empty".into()));
+    }
+    let comment = MatterComment {
+        content,
+        updated_at: Utc::now(),
+        ..current
+    };
+    storage
+        .comments
+        .update(&comment)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(versioned(comment))
+}
+
+async fn update_matter_comment(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<CommentRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    save_comment(&state.storage, &headers, &id, |_| Ok(payload.content))
+}
+
+async fn patch_matter_comment(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    let patch = parse_patch(&body)?;
+    let state = state.lock().await;
+    save_comment(&state.storage, &headers, &id, |current| {
+        apply_patch(current, &patch).map(|comment: MatterComment| comment.content)
+    })
+}
+
+async fn delete_matter_comment(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    state
+        .storage
+        .comments
+        .delete(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::<()>::success(())))
+}
+
+async fn list_reminders(
+    state: &AppState,
+    owner_type: &str,
+    owner_id: &str,
+) -> Result<Vec<MatterReminder>, ServerError> {
+    state
+        .storage
+        .matter_reminders
+        .get_by_owner(owner_type, owner_id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))
+}
+
+async fn add_reminder(
+    state: &AppState,
+    owner_type: &str,
+    owner_id: String,
+    mut reminder: MatterReminder,
+) -> Result<MatterReminder, ServerError> {
+    reminder.owner_type = owner_type.to_string();
+    reminder.owner_id = owner_id;
+    reminder::validate(&reminder).map_err(ServerError::BadRequest)?;
+    reminder::create_reminder(&state.storage, reminder)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| {
+            let owner = match owner_type {
+                OWNER_REPEAT_TASK => "RepeatTask",
+                _ => "Matter",
+            };
+            ServerError::NotFound(format!("{} not found", owner))
+        })
+}
+
+async fn get_matter_reminders(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let reminders = list_reminders(&state, reminder::OWNER_MATTER, &id).await?;
+    Ok(Json(ApiResponse::success(reminders)))
+}
+
+async fn create_matter_reminder(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    Json(payload): Json<MatterReminder>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let reminder = add_reminder(&state, reminder::OWNER_MATTER, id, payload).await?;
+    Ok(Json(ApiResponse::success(reminder)))
+}
+
+async fn get_repeat_task_reminders(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let reminders = list_reminders(&state, OWNER_REPEAT_TASK, &id).await?;
+    Ok(Json(ApiResponse::success(reminders)))
+}
+
+async fn create_repeat_task_reminder(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    Json(payload): Json<MatterReminder>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let reminder = add_reminder(&state, OWNER_REPEAT_TASK, id, payload).await?;
+    Ok(Json(ApiResponse::success(reminder)))
+}
+
+fn find_reminder(storage: &Repositories, id: &str) -> Result<MatterReminder, ServerError> {
+    storage
+        .matter_reminders
+        .get_by_id(id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Reminder not found".into()))
+}
+
+async fn get_matter_reminder(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    Ok(versioned(find_reminder(&state.storage, &id)?))
+}
+
+// PUT 和 PATCH 共用，只修改提醒时间，所属对象不变
+fn save_reminder(
+    storage: &Repositories,
+    headers: &HeaderMap,
+    id: &str,
+    build: impl FnOnce(&MatterReminder) -> Result<MatterReminder, ServerError>,
+) -> Result<Response, ServerError> {
+    let current = find_reminder(storage, id)?;
+    check_version(headers, &current)?;
+
+    let payload = build(&current)?;
+    let reminder = MatterReminder {
+        anchor: payload.anchor,
+        offset_minutes: payload.offset_minutes,
+        remind_at: payload.remind_at,
+        ..current
+    };
+    reminder::validate(&reminder).map_err(ServerError::BadRequest)?;
+    storage
+        .matter_reminders
+        .update(&reminder)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(versioned(reminder))
+}
+
+async fn update_matter_reminder(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<MatterReminder>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    save_reminder(&state.storage, &headers, &id, |_| Ok(payload))
+}
+
+async fn patch_matter_reminder(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    let patch = parse_patch(&body)?;
+    let state = state.lock().await;
+    save_reminder(&state.storage, &headers, &id, |current| {
+        apply_patch(current, &patch)
+    })
+}
+
+async fn delete_matter_reminder(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    state
+        .storage
+        .matter_reminders
+        .delete(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::<()>::success(())))
+}
+
 // KVStore 相关处理函数
 async fn set_kv(
     State(state): State<Arc<Mutex<AppState>>>,
@@ -277,7 +1003,11 @@ async fn set_kv(
     value: String,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    KVStore::set(&state.db, &key, &value).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .kv
+        .set(&key, &value)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::<()>::success(())))
 }
@@ -287,8 +1017,11 @@ async fn get_kv(
     Path(key): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let value =
-        KVStore::get(&state.db, &key, "").map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    let value = state
+        .storage
+        .kv
+        .get(&key, "")
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::success(value)))
 }
@@ -298,14 +1031,18 @@ async fn delete_kv(
     Path(key): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    KVStore::delete(&state.db, &key).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .kv
+        .delete(&key)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::<()>::success(())))
 }
 
 // Tag 相关处理函数
-#[derive(Deserialize)]
-struct TagsRequest {
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct TagsRequest {
     names: String,
 }
 
@@ -332,7 +1069,11 @@ async fn create_tag(
 
     // 批量创建标签
     for name in names {
-        Tag::create(&state.db, &name).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+        state
+            .storage
+            .tags
+            .create(&name)
+            .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
     }
 
     Ok(Json(ApiResponse::<()>::success(())))
@@ -342,7 +1083,11 @@ async fn get_all_tags(
     State(state): State<Arc<Mutex<AppState>>>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let tags = Tag::get_all(&state.db).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    let tags = state
+        .storage
+        .tags
+        .get_all()
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::success(tags)))
 }
@@ -369,7 +1114,11 @@ async fn delete_tag(
 
     // 批量删除标签
     for name in names {
-        Tag::delete(&state.db, &name).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+        state
+            .storage
+            .tags
+            .delete(&name)
+            .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
     }
 
     Ok(Json(ApiResponse::<()>::success(())))
@@ -397,7 +1146,10 @@ async fn update_tag_last_used_at(
 
     // 批量更新标签的最后使用时间
     for name in names {
-        Tag::update_last_used_at(&state.db, &name)
+        state
+            .storage
+            .tags
+            .update_last_used_at(&name)
             .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
     }
 
@@ -411,9 +1163,14 @@ async fn create_repeat_task(
 ) -> Result<impl IntoResponse, ServerError> {
     task.created_at = Utc::now();
     task.updated_at = Utc::now();
+    validation::repeat_task(&mut task, None, Mode::Create)?;
 
     let state = state.lock().await;
-    RepeatTask::create(&state.db, &task).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .repeat_tasks
+        .create(&task)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::success(task)))
 }
@@ -423,19 +1180,25 @@ async fn get_repeat_task(
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let task = RepeatTask::get_by_id(&state.db, &id)
+    let task = state
+        .storage
+        .repeat_tasks
+        .get_by_id(&id)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?
         .ok_or_else(|| ServerError::NotFound("RepeatTask not found".into()))?;
 
-    Ok(Json(ApiResponse::success(task)))
+    Ok(versioned(task))
 }
 
 async fn get_all_repeat_tasks(
     State(state): State<Arc<Mutex<AppState>>>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let tasks =
-        RepeatTask::get_all(&state.db).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    let tasks = state
+        .storage
+        .repeat_tasks
+        .get_all()
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::success(tasks)))
 }
@@ -444,33 +1207,84 @@ async fn get_active_repeat_tasks(
     State(state): State<Arc<Mutex<AppState>>>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let tasks = RepeatTask::get_active_tasks(&state.db)
+    let tasks = state
+        .storage
+        .repeat_tasks
+        .get_active_tasks()
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::success(tasks)))
 }
 
-async fn update_repeat_task(
-    State(state): State<Arc<Mutex<AppState>>>,
-    Path(id): Path<String>,
-    Json(mut task): Json<RepeatTask>,
-) -> Result<impl IntoResponse, ServerError> {
+fn save_repeat_task(
+    storage: &Repositories,
+    headers: &HeaderMap,
+    id: String,
+    build: impl FnOnce(&RepeatTask) -> Result<RepeatTask, ServerError>,
+) -> Result<Response, ServerError> {
+    let current = storage
+        .repeat_tasks
+        .get_by_id(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("RepeatTask not found".into()))?;
+    check_version(headers, &current)?;
+
+    let mut task = build(&current)?;
     task.id = id;
+    task.created_at = current.created_at;
+    validation::repeat_task(&mut task, Some(&current), Mode::Update)?;
     task.updated_at = Utc::now();
-
-    let state = state.lock().await;
-    task.update(&state.db)
+    storage
+        .repeat_tasks
+        .update(&task)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
-    Ok(Json(ApiResponse::success(task)))
+    Ok(versioned(task))
 }
 
-async fn delete_repeat_task(
+async fn update_repeat_task(
     State(state): State<Arc<Mutex<AppState>>>,
     Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(task): Json<RepeatTask>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    RepeatTask::delete(&state.db, &id).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    save_repeat_task(&state.storage, &headers, id, |_| Ok(task))
+}
+
+async fn patch_repeat_task(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    let patch = parse_patch(&body)?;
+    let state = state.lock().await;
+    save_repeat_task(&state.storage, &headers, id, |current| {
+        apply_patch(current, &patch)
+    })
+}
+
+async fn delete_repeat_task(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    state
+        .storage
+        .repeat_tasks
+        .delete(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .occurrences
+        .delete_by_task(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .matter_reminders
+        .delete_by_owner(OWNER_REPEAT_TASK, &id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::<()>::success(())))
 }
@@ -479,23 +1293,235 @@ async fn update_repeat_task_status(
     State(state): State<Arc<Mutex<AppState>>>,
     Path((id, status)): Path<(String, i32)>,
 ) -> Result<impl IntoResponse, ServerError> {
+    validation::repeat_task_status(status)?;
+
     let state = state.lock().await;
-    RepeatTask::update_status(&state.db, &id, status)
+    state
+        .storage
+        .repeat_tasks
+        .update_status(&id, status)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::<()>::success(())))
 }
 
+// 单次预览最多返回的发生次数
+const MAX_PREVIEW_OCCURRENCES: usize = 366;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct OccurrenceQuery {
+    count: Option<usize>,
+    after: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct RecurrencePreviewRequest {
+    // recurrence 和 repeat_time 二选一，都提供时使用 recurrence
+    recurrence: Option<String>,
+    repeat_time: Option<String>,
+    count: Option<usize>,
+    after: Option<DateTime<Utc>>,
+    #[serde(default)]
+    skip_holidays: bool,
+    #[serde(default)]
+    makeup_workdays: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct OccurrenceItem {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct RecurrencePreview {
+    recurrence: String,
+    occurrences: Vec<OccurrenceItem>,
+}
+
+fn preview(
+    recurrence: &Recurrence,
+    rule: HolidayRule,
+    count: Option<usize>,
+    after: Option<DateTime<Utc>>,
+) -> RecurrencePreview {
+    let count = count.unwrap_or(10).min(MAX_PREVIEW_OCCURRENCES);
+    let after = recurrence.to_zoned(after.unwrap_or_else(Utc::now));
+    let occurrences = holiday::calendar()
+        .next_occurrences(recurrence, rule, after, count)
+        .iter()
+        .map(|o| OccurrenceItem {
+            start: o.start_utc(),
+            end: o.end_utc(),
+        })
+        .collect();
+    RecurrencePreview {
+        recurrence: recurrence.to_string(),
+        occurrences,
+    }
+}
+
+async fn get_repeat_task_occurrences(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    Query(query): Query<OccurrenceQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let task = state
+        .storage
+        .repeat_tasks
+        .get_by_id(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("RepeatTask not found".into()))?;
+    let recurrence =
+        recurrence::recurrence_of(&task).map_err(|e| ServerError::BadRequest(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(preview(
+        &recurrence,
+        HolidayRule::from(&task),
+        query.count,
+        query.after,
+    ))))
+}
+
+// 已生成的 matter 及其对应的日期
+async fn get_materialized_occurrences(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let occurrences = state
+        .storage
+        .occurrences
+        .get_by_task(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(occurrences)))
+}
+
+// 立即为重复任务生成今天的 matter
+async fn create_repeat_task_matter(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let task = state
+        .storage
+        .repeat_tasks
+        .get_by_id(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("RepeatTask not found".into()))?;
+    let matter = repeat_scheduler::materialize_today(&task, Utc::now())
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+    state
+        .storage
+        .matters
+        .create(&matter)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(matter)))
+}
+
+async fn preview_recurrence(
+    Json(payload): Json<RecurrencePreviewRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let mut rule = HolidayRule {
+        skip_holidays: payload.skip_holidays,
+        makeup_workdays: payload.makeup_workdays,
+    };
+    let recurrence = match (payload.recurrence, payload.repeat_time) {
+        (Some(text), _) => text.parse::<Recurrence>(),
+        (None, Some(repeat_time)) => {
+            Recurrence::from_legacy(&repeat_time, chrono::Local::now().date_naive()).map(
+                |(r, skip_holidays)| {
+                    rule.skip_holidays |= skip_holidays;
+                    r
+                },
+            )
+        }
+        (None, None) => {
+            return Err(ServerError::BadRequest(
+                "recurrence or repeat_time is required".into(),
+            ))
+        }
+    }
+    .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(preview(
+        &recurrence,
+        rule,
+        payload.count,
+        payload.after,
+    ))))
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct HolidayQuery {
+    year: Option<i32>,
+}
+
+// 某一年的节假日和调休上班日，默认今年
+async fn get_holidays(Query(query): Query<HolidayQuery>) -> Result<impl IntoResponse, ServerError> {
+    let year = query.year.unwrap_or_else(|| chrono::Local::now().year());
+    Ok(Json(ApiResponse::success(
+        holiday::calendar().entries_in_year(year),
+    )))
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct DigestQuery {
+    date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct DigestPreview {
+    markdown: String,
+    digest: digest::Digest,
+}
+
+// 某天的每日简报预览，默认今天，不会发送通知
+async fn get_digest(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Query(query): Query<DigestQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let date = match query.date {
+        Some(date) => date,
+        None => {
+            digest::today(&state.storage).map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        }
+    };
+    let digest = digest::build(&state.storage, date)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(DigestPreview {
+        markdown: digest.to_markdown(),
+        digest,
+    })))
+}
+
 // Todo 相关处理函数
 async fn create_todo(
     State(state): State<Arc<Mutex<AppState>>>,
     Json(mut todo): Json<Todo>,
 ) -> Result<impl IntoResponse, ServerError> {
+    validation::todo(&mut todo, Mode::Create)?;
     todo.created_at = Utc::now();
     todo.updated_at = Utc::now();
 
     let state = state.lock().await;
-    Todo::create(&state.db, &todo).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .todos
+        .create(&todo)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    // 重新读取以返回数据库分配的 sort_order
+    let todo = state
+        .storage
+        .todos
+        .get_by_id(&todo.id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .unwrap_or(todo);
 
     Ok(Json(ApiResponse::success(todo)))
 }
@@ -505,35 +1531,106 @@ async fn get_todo(
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let todo = Todo::get_by_id(&state.db, &id)
+    let todo = state
+        .storage
+        .todos
+        .get_by_id(&id)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?
         .ok_or_else(|| ServerError::NotFound("Todo not found".into()))?;
 
-    Ok(Json(ApiResponse::success(todo)))
+    Ok(versioned(todo))
 }
 
 async fn get_all_todos(
     State(state): State<Arc<Mutex<AppState>>>,
+    Query(filter): Query<TodoFilter>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let todos = Todo::get_all(&state.db).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    let todos = state
+        .storage
+        .todos
+        .query(&filter)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::success(todos)))
 }
 
-async fn update_todo(
+#[derive(Deserialize, JsonSchema)]
+pub(crate) struct ReorderRequest {
+    ids: Vec<String>,
+}
+
+async fn reorder_todos(
     State(state): State<Arc<Mutex<AppState>>>,
-    Path(id): Path<String>,
-    Json(mut todo): Json<Todo>,
+    Json(payload): Json<ReorderRequest>,
 ) -> Result<impl IntoResponse, ServerError> {
+    if payload.ids.is_empty() {
+        return Err(ServerError::BadRequest("No todo ids provided".into()));
+    }
+
+    let state = state.lock().await;
+    state
+        .storage
+        .todos
+        .reorder(&payload.ids)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::<()>::success(())))
+}
+
+fn save_todo(
+    storage: &Repositories,
+    headers: &HeaderMap,
+    id: String,
+    build: impl FnOnce(&Todo) -> Result<Todo, ServerError>,
+) -> Result<Response, ServerError> {
+    let current = storage
+        .todos
+        .get_by_id(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Todo not found".into()))?;
+    check_version(headers, &current)?;
+
+    let mut todo = build(&current)?;
     todo.id = id;
+    todo.created_at = current.created_at;
+    validation::todo(&mut todo, Mode::Update)?;
     todo.updated_at = Utc::now();
+    storage
+        .todos
+        .update(&todo)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    // 没有给出 sort_order 时保留原来的位置，重新读取以返回实际保存的数据
+    let todo = storage
+        .todos
+        .get_by_id(&todo.id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .unwrap_or(todo);
 
+    Ok(versioned(todo))
+}
+
+async fn update_todo(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(todo): Json<Todo>,
+) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    todo.update(&state.db)
-        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    save_todo(&state.storage, &headers, id, |_| Ok(todo))
+}
 
-    Ok(Json(ApiResponse::success(todo)))
+async fn patch_todo(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    let patch = parse_patch(&body)?;
+    let state = state.lock().await;
+    save_todo(&state.storage, &headers, id, |current| {
+        apply_patch(current, &patch)
+    })
 }
 
 async fn delete_todo(
@@ -541,66 +1638,317 @@ async fn delete_todo(
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    Todo::delete(&state.db, &id).map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .todos
+        .delete(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    attachment::delete_by_owner(&state.storage, OWNER_TODO, &id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::<()>::success(())))
 }
 
+// Attachment 相关处理函数
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct UploadQuery {
+    file_name: String,
+}
 
-static HTTP_SERVER: OnceCell<HttpServer> = OnceCell::new();
-static SERVER_PORT: AtomicU16 = AtomicU16::new(0);
+async fn get_matter_attachments(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    get_attachments(state, OWNER_MATTER, id).await
+}
 
-pub fn start_http_server(port: u16, db: Arc<SafeConnection>) -> Result<(), String> {
+async fn get_todo_attachments(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    get_attachments(state, OWNER_TODO, id).await
+}
 
-    if let Some(server) = HTTP_SERVER.get() {
-        let current_port = SERVER_PORT.load(Ordering::Relaxed);
-        if current_port == port {
-            return Ok(());
+async fn get_attachments(
+    state: Arc<Mutex<AppState>>,
+    owner_type: &str,
+    owner_id: String,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let attachments = state
+        .storage
+        .attachments
+        .get_by_owner(owner_type, &owner_id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(attachments)))
+}
+
+async fn upload_matter_attachment(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    upload_attachment(state, OWNER_MATTER, id, query, headers, body).await
+}
+
+async fn upload_todo_attachment(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    upload_attachment(state, OWNER_TODO, id, query, headers, body).await
+}
+
+async fn upload_attachment(
+    state: Arc<Mutex<AppState>>,
+    owner_type: &str,
+    owner_id: String,
+    query: UploadQuery,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    let file_name = query.file_name.trim();
+    if file_name.is_empty() {
+        return Err(ServerError::BadRequest("file_name is required".into()));
+    }
+    if body.is_empty() {
+        return Err(ServerError::BadRequest(
+            "Attachment content is empty".into(),
+        ));
+    }
+    if body.len() > MAX_ATTACHMENT_SIZE {
+        return Err(ServerError::BadRequest(format!(
+            "Attachment exceeds the {} bytes limit",
+            MAX_ATTACHMENT_SIZE
+        )));
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream");
+
+    // 检查所属对象和保存在同一次加锁内完成，避免中途对象被删除而留下孤立的附件
+    let state = state.lock().await;
+    let exists = attachment::owner_exists(&state.storage, owner_type, &owner_id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    if !exists {
+        let owner = if owner_type == OWNER_MATTER {
+            "Matter"
         } else {
-            return Err(format!(
-                "HTTP server already running on port {}",
-                current_port
-            ));
-        }
+            "Todo"
+        };
+        return Err(ServerError::NotFound(format!("{} not found", owner)));
     }
+    let attachment = attachment::save(
+        &state.storage,
+        owner_type,
+        &owner_id,
+        file_name,
+        content_type,
+        &body,
+    )
+    .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(attachment)))
+}
 
-    let server = HttpServer::new(db);
-    let server_clone = server.clone();
+async fn get_attachment(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let attachment = state
+        .storage
+        .attachments
+        .get_by_id(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Attachment not found".into()))?;
 
-    SERVER_PORT.store(port, Ordering::Relaxed);
+    Ok(Json(ApiResponse::success(attachment)))
+}
 
-    tauri::async_runtime::spawn(async move {
-        if let Err(e) = server_clone.start(port).await {
-            log::error!("HTTP server failed to start: {}", e);
+async fn download_attachment(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let attachment = state
+        .storage
+        .attachments
+        .get_by_id(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Attachment not found".into()))?;
+    let data = attachment::read(&state.storage, &attachment)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Attachment content missing".into()))?;
+
+    let disposition = format!(
+        "attachment; filename*=UTF-8''{}",
+        percent_encode(&attachment.file_name)
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, attachment.content_type),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        data,
+    ))
+}
+
+async fn delete_attachment(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let attachment = state
+        .storage
+        .attachments
+        .get_by_id(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Attachment not found".into()))?;
+    attachment::delete(&state.storage, &attachment)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::<()>::success(())))
+}
+
+// RFC 5987 编码，保证中文文件名在下载时不乱码
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
         }
-    });
+    }
+    encoded
+}
+
+// 当前的服务器实例；停止后保留以便后台任务继续使用存储，重新启动时换成新实例
+static HTTP_SERVER: std::sync::RwLock<Option<HttpServer>> = std::sync::RwLock::new(None);
+static SERVER_INFO: std::sync::Mutex<Option<ServerInfo>> = std::sync::Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInfo {
+    pub host: String,
+    // 实际监听的端口，配置的端口被占用时可能是备用端口
+    pub port: u16,
+    pub base_url: String,
+}
+
+pub fn server_info() -> Option<ServerInfo> {
+    SERVER_INFO.lock().unwrap().clone()
+}
+
+fn current_server() -> Option<HttpServer> {
+    HTTP_SERVER.read().unwrap().clone()
+}
+
+// 用新实例替换当前实例并开始监听
+async fn start_server(
+    storage: Repositories,
+    config: &ServerConfig,
+) -> Result<ServerInfo, ServerError> {
+    let server = HttpServer::new(storage);
+    let addr = server.start(config).await?;
+    let host = if config.host.contains(':') {
+        format!("[{}]", config.host)
+    } else {
+        config.host.clone()
+    };
+    let info = ServerInfo {
+        base_url: format!("http://{}:{}", host, addr.port()),
+        host: config.host.clone(),
+        port: addr.port(),
+    };
+    *HTTP_SERVER.write().unwrap() = Some(server);
+    *SERVER_INFO.lock().unwrap() = Some(info.clone());
+    Ok(info)
+}
+
+pub async fn start_http_server(
+    config: &ServerConfig,
+    storage: Repositories,
+) -> Result<ServerInfo, ServerError> {
+    if let Some(info) = server_info() {
+        return Err(ServerError::StartupError(format!(
+            "HTTP server already running on port {}",
+            info.port
+        )));
+    }
+    start_server(storage, config).await
+}
+
+// 按新配置重新监听，存储保持不变
+pub async fn restart_http_server(config: &ServerConfig) -> Result<ServerInfo, ServerError> {
+    let server = current_server()
+        .ok_or_else(|| ServerError::StartupError("HTTP server not initialized".into()))?;
+    server.stop().await;
+    *SERVER_INFO.lock().unwrap() = None;
+    start_server(server.storage().await, config).await
+}
 
-    match HTTP_SERVER.set(server) {
-        Ok(_) => Ok(()),
-        Err(_) => Err("Failed to store HTTP server instance".into()),
+// 后台任务通过这里取得当前 profile 的存储
+pub async fn current_storage() -> Option<Repositories> {
+    let server = current_server()?;
+    Some(server.storage().await)
+}
+
+// 切换 profile 时调用，无需重启服务器
+pub async fn replace_http_server_storage(storage: Repositories) -> Result<(), ServerError> {
+    match current_server() {
+        Some(server) => {
+            server.set_storage(storage).await;
+            Ok(())
+        }
+        None => Err(ServerError::StartupError("HTTP server not running".into())),
     }
 }
 
-pub fn stop_http_server() -> Result<(), ServerError> {
-    if let Some(server) = HTTP_SERVER.get() {
-        tauri::async_runtime::block_on(async {
+// 停止监听并等待正在处理的请求完成，存储仍可供后台任务使用
+pub async fn stop_http_server() -> Result<(), ServerError> {
+    match current_server() {
+        Some(server) => {
             server.stop().await;
-        });
-        SERVER_PORT.store(0, Ordering::Relaxed);
-        Ok(())
-    } else {
-        Err(ServerError::StartupError("HTTP server not running".into()))
+            *SERVER_INFO.lock().unwrap() = None;
+            Ok(())
+        }
+        None => Err(ServerError::StartupError("HTTP server not running".into())),
     }
 }
 
+// 退出时调用：停止服务器后关闭数据库，之后不能再启动
+pub async fn shutdown_http_server() -> Result<(), ServerError> {
+    stop_http_server().await?;
+    let Some(server) = HTTP_SERVER.write().unwrap().take() else {
+        return Ok(());
+    };
+    server
+        .storage()
+        .await
+        .close()
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))
+}
+
 async fn create_notification(
     State(state): State<Arc<Mutex<AppState>>>,
     Json(mut notification): Json<NotificationRecord>,
 ) -> Result<impl IntoResponse, ServerError> {
     notification.created_at = Utc::now();
+    validation::notification(&mut notification, Mode::Create)?;
 
     let state = state.lock().await;
-    NotificationRecord::create(&state.db, &notification)
+    state
+        .storage
+        .notifications
+        .create(&notification)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::success(notification)))
@@ -611,11 +1959,14 @@ async fn get_notification(
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let notification = NotificationRecord::get_by_id(&state.db, &id)
+    let notification = state
+        .storage
+        .notifications
+        .get_by_id(&id)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?
         .ok_or_else(|| ServerError::NotFound("Notification not found".into()))?;
 
-    Ok(Json(ApiResponse::success(notification)))
+    Ok(versioned(notification))
 }
 
 // async fn get_all_notifications(State(state): State<Arc<Mutex<AppState>>>) -> Result<
@@ -623,7 +1974,7 @@ async fn get_notification(
 //     ServerError
 // > {
 //     let state = state.lock().await;
-//     let notifications = NotificationRecord::get_all(&state.db).map_err(|e|
+//     let notifications = state.storage.notifications.get_all().map_err(|e|
 //         ServerError::DatabaseError(e.to_string())
 //     )?;
 
@@ -634,55 +1985,453 @@ async fn get_unread_notifications(
     State(state): State<Arc<Mutex<AppState>>>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    let notifications = NotificationRecord::get_unread(&state.db)
+    let notifications = state
+        .storage
+        .notifications
+        .get_unread()
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::success(notifications)))
 }
 
+fn save_notification(
+    storage: &Repositories,
+    headers: &HeaderMap,
+    id: String,
+    build: impl FnOnce(&NotificationRecord) -> Result<NotificationRecord, ServerError>,
+) -> Result<Response, ServerError> {
+    let current = storage
+        .notifications
+        .get_by_id(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Notification not found".into()))?;
+    check_version(headers, &current)?;
+
+    let mut notification = build(&current)?;
+    notification.id = id;
+    notification.created_at = current.created_at;
+    validation::notification(&mut notification, Mode::Update)?;
+    storage
+        .notifications
+        .update(&notification)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    // update 不修改已读、确认等状态字段，重新读取使 ETag 与 GET 一致
+    let notification = storage
+        .notifications
+        .get_by_id(&notification.id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .unwrap_or(notification);
+
+    Ok(versioned(notification))
+}
+
 async fn update_notification(
     State(state): State<Arc<Mutex<AppState>>>,
     Path(id): Path<String>,
-    Json(mut notification): Json<NotificationRecord>,
+    headers: HeaderMap,
+    Json(notification): Json<NotificationRecord>,
 ) -> Result<impl IntoResponse, ServerError> {
-    notification.id = id;
+    let state = state.lock().await;
+    save_notification(&state.storage, &headers, id, |_| Ok(notification))
+}
 
+async fn patch_notification(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    let patch = parse_patch(&body)?;
     let state = state.lock().await;
-    notification
-        .update(&state.db)
+    save_notification(&state.storage, &headers, id, |current| {
+        apply_patch(current, &patch)
+    })
+}
+
+async fn delete_notification(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    state
+        .storage
+        .notifications
+        .delete(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    state
+        .storage
+        .deliveries
+        .delete_by_notification(&id)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
-    Ok(Json(ApiResponse::success(notification)))
+    Ok(Json(ApiResponse::<()>::success(())))
 }
 
-async fn delete_notification(
+async fn get_notification_deliveries(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let deliveries = state
+        .storage
+        .deliveries
+        .get_by_notification(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(deliveries)))
+}
+
+async fn get_notification_channels(
+    State(state): State<Arc<Mutex<AppState>>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let channels = notification_channel::load_channels(&state.storage)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(channels)))
+}
+
+async fn set_notification_channels(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Json(channels): Json<Vec<ChannelConfig>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    notification_channel::save_channels(&state.storage, &channels)
+        .map_err(ServerError::BadRequest)?;
+
+    Ok(Json(ApiResponse::success(channels)))
+}
+
+fn hook_error(e: HookError) -> ServerError {
+    match e {
+        HookError::Config(msg) | HookError::Payload(msg) => ServerError::BadRequest(msg),
+        HookError::NotFound(_) => ServerError::NotFound("Incoming hook not found".into()),
+        HookError::InvalidToken => ServerError::Unauthorized(e.to_string()),
+        HookError::Disabled => ServerError::Forbidden(e.to_string()),
+        HookError::Storage(e) => ServerError::DatabaseError(e.to_string()),
+    }
+}
+
+async fn get_all_incoming_hooks(
+    State(state): State<Arc<Mutex<AppState>>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let hooks = state
+        .storage
+        .incoming_hooks
+        .get_all()
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(hooks)))
+}
+
+// 返回的令牌只有这一次
+async fn create_incoming_hook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Json(hook): Json<IncomingHook>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let created = incoming_hook::create(&state.storage, hook).map_err(hook_error)?;
+
+    Ok(Json(ApiResponse::success(created)))
+}
+
+fn find_incoming_hook(storage: &Repositories, id: &str) -> Result<IncomingHook, ServerError> {
+    storage
+        .incoming_hooks
+        .get_by_id(id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Incoming hook not found".into()))
+}
+
+async fn get_incoming_hook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    Ok(versioned(find_incoming_hook(&state.storage, &id)?))
+}
+
+// PUT 和 PATCH 共用，令牌保持不变
+fn save_incoming_hook(
+    storage: &Repositories,
+    headers: &HeaderMap,
+    id: &str,
+    build: impl FnOnce(&IncomingHook) -> Result<IncomingHook, ServerError>,
+) -> Result<Response, ServerError> {
+    let current = find_incoming_hook(storage, id)?;
+    check_version(headers, &current)?;
+
+    let hook = incoming_hook::update(storage, id, build(&current)?).map_err(hook_error)?;
+    Ok(versioned(hook))
+}
+
+async fn update_incoming_hook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(hook): Json<IncomingHook>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    save_incoming_hook(&state.storage, &headers, &id, |_| Ok(hook))
+}
+
+async fn patch_incoming_hook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    let patch = parse_patch(&body)?;
+    let state = state.lock().await;
+    save_incoming_hook(&state.storage, &headers, &id, |current| {
+        apply_patch(current, &patch)
+    })
+}
+
+async fn delete_incoming_hook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    state
+        .storage
+        .incoming_hooks
+        .delete(&id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::<()>::success(())))
+}
+
+// 请求体可以是 JSON，也可以是 application/x-www-form-urlencoded 表单
+async fn receive_hook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(token): Path<String>,
+    request: Request,
+) -> Result<impl IntoResponse, ServerError> {
+    let is_form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+    let payload = if is_form {
+        let Form(fields) = Form::<Vec<(String, String)>>::from_request(request, &())
+            .await
+            .map_err(|e| ServerError::BadRequest(e.body_text()))?;
+        incoming_hook::form_payload(fields)
+    } else {
+        let body = Bytes::from_request(request, &())
+            .await
+            .map_err(|e| ServerError::BadRequest(e.body_text()))?;
+        serde_json::from_slice(&body).map_err(|e| ServerError::BadRequest(e.to_string()))?
+    };
+
+    let state = state.lock().await;
+    let item = incoming_hook::receive(&state.storage, &token, &payload).map_err(hook_error)?;
+
+    Ok(Json(ApiResponse::success(item)))
+}
+
+fn webhook_error(e: WebhookError) -> ServerError {
+    match e {
+        WebhookError::Config(msg) => ServerError::BadRequest(msg),
+        WebhookError::NotFound(_) => ServerError::NotFound("Webhook not found".into()),
+        e => ServerError::DatabaseError(e.to_string()),
+    }
+}
+
+async fn get_all_webhooks(
+    State(state): State<Arc<Mutex<AppState>>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let webhooks = state
+        .storage
+        .webhooks
+        .get_all()
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(webhooks)))
+}
+
+// 返回的 secret 只有这一次
+async fn create_webhook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Json(webhook): Json<Webhook>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let created = webhook::create(&state.storage, webhook).map_err(webhook_error)?;
+
+    Ok(Json(ApiResponse::success(created)))
+}
+
+fn find_webhook(storage: &Repositories, id: &str) -> Result<Webhook, ServerError> {
+    storage
+        .webhooks
+        .get_by_id(id)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound("Webhook not found".into()))
+}
+
+async fn get_webhook(
     State(state): State<Arc<Mutex<AppState>>>,
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    NotificationRecord::delete(&state.db, &id)
+    Ok(versioned(find_webhook(&state.storage, &id)?))
+}
+
+// PUT 和 PATCH 共用，secret 为空时保留原来的密钥
+fn save_webhook(
+    storage: &Repositories,
+    headers: &HeaderMap,
+    id: &str,
+    build: impl FnOnce(&Webhook) -> Result<Webhook, ServerError>,
+) -> Result<Response, ServerError> {
+    let current = find_webhook(storage, id)?;
+    check_version(headers, &current)?;
+
+    let webhook = webhook::update(storage, id, build(&current)?).map_err(webhook_error)?;
+    Ok(versioned(webhook))
+}
+
+async fn update_webhook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(webhook): Json<Webhook>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    save_webhook(&state.storage, &headers, &id, |_| Ok(webhook))
+}
+
+// 响应中没有 secret，合并后为空，因此 PATCH 不会改动密钥，除非请求中给出新的 secret
+async fn patch_webhook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ServerError> {
+    let patch = parse_patch(&body)?;
+    let state = state.lock().await;
+    save_webhook(&state.storage, &headers, &id, |current| {
+        apply_patch(current, &patch)
+    })
+}
+
+async fn delete_webhook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    state
+        .storage
+        .webhooks
+        .delete(&id)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::<()>::success(())))
 }
 
+async fn get_webhook_deliveries(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let deliveries = state
+        .storage
+        .webhook_deliveries
+        .get_by_webhook(&id, webhook::DELIVERY_LOG_LIMIT)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(deliveries)))
+}
+
+// 发送失败也返回成功，结果见投递记录的 status 和 last_error
+async fn test_webhook(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    // 发送请求期间不持有锁
+    let storage = state.lock().await.storage.clone();
+    let delivery = webhook::test(&storage, &id).await.map_err(webhook_error)?;
+
+    Ok(Json(ApiResponse::success(delivery)))
+}
+
 async fn mark_notification_as_read(
     State(state): State<Arc<Mutex<AppState>>>,
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    NotificationRecord::mark_as_read(&state.db, &id)
+    state
+        .storage
+        .notifications
+        .mark_as_read(&id)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::<()>::success(())))
 }
 
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub(crate) struct SnoozeRequest {
+    // minutes 和 until 二选一，都不提供时默认 10 分钟
+    minutes: Option<i64>,
+    until: Option<DateTime<Utc>>,
+}
+
+fn notification_found(
+    record: Option<NotificationRecord>,
+) -> Result<NotificationRecord, ServerError> {
+    record.ok_or_else(|| ServerError::NotFound("Notification not found".into()))
+}
+
+async fn snooze_notification(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+    payload: Option<Json<SnoozeRequest>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Json(payload) = payload.unwrap_or_default();
+    let until = reminder::snooze_until(payload.minutes, payload.until, Utc::now())
+        .map_err(ServerError::BadRequest)?;
+
+    let state = state.lock().await;
+    let record = reminder::snooze(&state.storage, &id, until)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(notification_found(record)?)))
+}
+
+async fn acknowledge_notification(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let record = reminder::acknowledge(&state.storage, &id, Utc::now())
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(notification_found(record)?)))
+}
+
+async fn dismiss_notification(
+    State(state): State<Arc<Mutex<AppState>>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let state = state.lock().await;
+    let record = reminder::dismiss(&state.storage, &id, Utc::now())
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    Ok(Json(ApiResponse::success(notification_found(record)?)))
+}
+
 async fn mark_notification_as_read_by_type(
     State(state): State<Arc<Mutex<AppState>>>,
     Path(type_): Path<i32>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    NotificationRecord::mark_as_read_by_type(&state.db, type_)
+    state
+        .storage
+        .notifications
+        .mark_as_read_by_type(type_)
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
     Ok(Json(ApiResponse::<()>::success(())))
 }
@@ -692,13 +2441,99 @@ async fn mark_all_notifications_as_read(
     State(state): State<Arc<Mutex<AppState>>>,
 ) -> Result<impl IntoResponse, ServerError> {
     let state = state.lock().await;
-    NotificationRecord::mark_all_as_read(&state.db)
+    state
+        .storage
+        .notifications
+        .mark_all_as_read()
         .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
 
     Ok(Json(ApiResponse::<()>::success(())))
 }
 
-#[derive(Debug, Deserialize)]
+// 变更事件推送
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(crate) struct EventQuery {
+    // 逗号分隔的实体名，例如 matter,todo
+    entity: Option<String>,
+    // 逗号分隔的变更类型：created / updated / deleted / completed
+    #[serde(rename = "type")]
+    type_: Option<String>,
+    // 断线前收到的最后一个事件 id，SSE 也可以用 Last-Event-ID 头
+    last_event_id: Option<u64>,
+}
+
+fn event_subscription(
+    query: &EventQuery,
+    headers: &HeaderMap,
+) -> impl Stream<Item = StreamItem> + Send + 'static {
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .or(query.last_event_id);
+    let filter = EventFilter::parse(query.entity.as_deref(), query.type_.as_deref());
+    events::subscribe(filter, last_id)
+}
+
+// reset 表示有事件丢失，客户端需要重新拉取数据
+fn reset_message() -> String {
+    json!({ "type": "reset" }).to_string()
+}
+
+fn sse_event(item: StreamItem) -> Event {
+    match item {
+        StreamItem::Change(change) => Event::default()
+            .id(change.id.to_string())
+            .event("change")
+            .json_data(&change)
+            .unwrap_or_else(|_| Event::default().event("reset").data(reset_message())),
+        StreamItem::Reset => Event::default().event("reset").data(reset_message()),
+    }
+}
+
+async fn stream_events(Query(query): Query<EventQuery>, headers: HeaderMap) -> impl IntoResponse {
+    let stream =
+        event_subscription(&query, &headers).map(|item| Ok::<_, Infallible>(sse_event(item)));
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+// 与 SSE 相同的事件，每条消息是一个 JSON 文本；客户端发来的消息被忽略
+async fn events_websocket(
+    Query(query): Query<EventQuery>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    // 升级前订阅，握手期间的事件不会丢失
+    let subscription = event_subscription(&query, &headers);
+    ws.on_upgrade(move |socket| forward_events(socket, subscription))
+}
+
+async fn forward_events(socket: WebSocket, subscription: impl Stream<Item = StreamItem> + Send) {
+    let (mut sender, mut receiver) = socket.split();
+    let client_closed = async move {
+        while let Some(Ok(message)) = receiver.next().await {
+            if let Message::Close(_) = message {
+                break;
+            }
+        }
+    };
+    let mut subscription = Box::pin(subscription.take_until(client_closed));
+    while let Some(item) = subscription.next().await {
+        let text = match item {
+            StreamItem::Change(change) => match serde_json::to_string(&change) {
+                Ok(text) => text,
+                Err(_) => reset_message(),
+            },
+            StreamItem::Reset => reset_message(),
+        };
+        if sender.send(Message::Text(text)).await.is_err() {
+            return;
+        }
+    }
+    let _ = sender.close().await;
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct QueryFieldParams {
     field: String,
     value: String,
@@ -738,9 +2573,13 @@ async fn query_matter_by_field(
     }
 
     let state = state.lock().await;
-    let matters =
-        Matter::query_by_field(&state.db, &params.field, &params.value, params.exact_match)
-            .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+    let matters = state
+        .storage
+        .matters
+        .query_by_field(&params.field, &params.value, params.exact_match)
+        .map_err(|e| ServerError::DatabaseError(e.to_string()))?;
+
+    let matters = with_comment_summaries(&state.storage, matters)?;
 
     Ok(Json(ApiResponse::success(matters)))
 }
@@ -0,0 +1,550 @@
+// 入站 webhook：本地的外部工具（CI、表单、脚本等）向 POST /hooks/<token> 推送 JSON 或表单数据，
+// 按 hook 配置的 mapping 生成 matter、todo 或通知，调用方不需要了解完整的 API。
+// mapping 的值是模板，{{a.b.0}} 取请求体中对应路径的值；整个模板只有一个占位符时保留原始类型。
+// 令牌只以 SHA-256 哈希保存，明文只在创建时返回一次
+
+use crate::auth::hash_token;
+use crate::database::{IncomingHook, Matter, NotificationRecord, NotificationStatus, Todo};
+use crate::reminder;
+use crate::repository::{Repositories, StorageError};
+use crate::validation::{self, Api, Mode, ValidationError};
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::command;
+use thiserror::Error;
+
+pub const TARGET_MATTER: &str = "matter";
+pub const TARGET_TODO: &str = "todo";
+pub const TARGET_NOTIFICATION: &str = "notification";
+
+const TOKEN_PREFIX: &str = "hook_";
+// 列表中显示的明文长度（含 hook_ 前缀）
+const DISPLAY_PREFIX_LEN: usize = 12;
+// 没有给出结束时间的 matter 默认持续一小时
+const DEFAULT_MATTER_HOURS: i64 = 1;
+
+#[derive(Clone, Copy)]
+enum FieldKind {
+    Text,
+    Integer,
+    Time,
+}
+
+use FieldKind::{Integer, Text, Time};
+
+// 各目标允许通过 mapping 设置的字段
+const MATTER_FIELDS: &[(&str, FieldKind)] = &[
+    ("title", Text),
+    ("description", Text),
+    ("tags", Text),
+    ("start_time", Time),
+    ("end_time", Time),
+    ("priority", Integer),
+];
+const TODO_FIELDS: &[(&str, FieldKind)] = &[
+    ("title", Text),
+    ("status", Text),
+    ("notes", Text),
+    ("tags", Text),
+    ("start_time", Time),
+    ("due_date", Time),
+    ("priority", Integer),
+];
+const NOTIFICATION_FIELDS: &[(&str, FieldKind)] = &[
+    ("title", Text),
+    ("content", Text),
+    ("type_", Integer),
+    ("action_url", Text),
+    ("expire_at", Time),
+];
+
+#[derive(Error, Debug)]
+pub enum HookError {
+    #[error("配置错误：{0}")]
+    Config(String),
+    #[error("入站 webhook 不存在：{0}")]
+    NotFound(String),
+    #[error("令牌无效")]
+    InvalidToken,
+    #[error("入站 webhook 已停用")]
+    Disabled,
+    #[error("请求体无效：{0}")]
+    Payload(String),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+pub type Result<T> = std::result::Result<T, HookError>;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CreatedHook {
+    // 明文令牌，只在创建时返回
+    pub token: String,
+    // 推送地址的路径部分
+    pub path: String,
+    #[serde(flatten)]
+    pub hook: IncomingHook,
+}
+
+// 推送后创建的数据
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(tag = "target", content = "item", rename_all = "snake_case")]
+pub enum HookItem {
+    Matter(Matter),
+    Todo(Todo),
+    Notification(NotificationRecord),
+}
+
+fn target_fields(target: &str) -> Option<&'static [(&'static str, FieldKind)]> {
+    match target {
+        TARGET_MATTER => Some(MATTER_FIELDS),
+        TARGET_TODO => Some(TODO_FIELDS),
+        TARGET_NOTIFICATION => Some(NOTIFICATION_FIELDS),
+        _ => None,
+    }
+}
+
+fn generate_token() -> String {
+    format!(
+        "{}{}{}",
+        TOKEN_PREFIX,
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+fn validate(hook: &mut IncomingHook) -> Result<()> {
+    hook.name = hook.name.trim().to_string();
+    if hook.name.is_empty() {
+        return Err(HookError::Config("名称不能为空".to_string()));
+    }
+    hook.target = hook.target.trim().to_string();
+    let fields = target_fields(&hook.target)
+        .ok_or_else(|| HookError::Config(format!("无效的目标：{}", hook.target)))?;
+    if let Some(field) = hook
+        .mapping
+        .keys()
+        .find(|key| !fields.iter().any(|(name, _)| name == key))
+    {
+        return Err(HookError::Config(format!(
+            "{} 不支持字段 {}",
+            hook.target, field
+        )));
+    }
+    Ok(())
+}
+
+pub fn create(storage: &Repositories, mut hook: IncomingHook) -> Result<CreatedHook> {
+    validate(&mut hook)?;
+    let token = generate_token();
+    let now = Utc::now();
+    hook.id = uuid::Uuid::new_v4().to_string();
+    hook.token_hash = hash_token(&token);
+    hook.prefix = token[..DISPLAY_PREFIX_LEN].to_string();
+    hook.created_at = now;
+    hook.updated_at = now;
+    hook.last_used_at = None;
+    storage.incoming_hooks.create(&hook)?;
+    Ok(CreatedHook {
+        path: format!("/hooks/{}", token),
+        token,
+        hook,
+    })
+}
+
+// 令牌保持不变
+pub fn update(storage: &Repositories, id: &str, mut hook: IncomingHook) -> Result<IncomingHook> {
+    let existing = storage
+        .incoming_hooks
+        .get_by_id(id)?
+        .ok_or_else(|| HookError::NotFound(id.to_string()))?;
+    validate(&mut hook)?;
+    let hook = IncomingHook {
+        id: existing.id,
+        token_hash: existing.token_hash,
+        prefix: existing.prefix,
+        created_at: existing.created_at,
+        updated_at: Utc::now(),
+        last_used_at: existing.last_used_at,
+        ..hook
+    };
+    storage.incoming_hooks.update(&hook)?;
+    Ok(hook)
+}
+
+// 按 a.b.0 这样的路径取值，数字段用于数组下标
+fn lookup<'a>(payload: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(payload, |value, segment| match value {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+// 数组按逗号连接，与 tags 的保存格式一致
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        Value::Array(items) => items.iter().map(to_text).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+fn render(template: &str, payload: &Value) -> Option<Value> {
+    let trimmed = template.trim();
+    if let Some(path) = trimmed
+        .strip_prefix("{{")
+        .and_then(|rest| rest.strip_suffix("}}"))
+        .filter(|path| !path.contains("{{") && !path.contains("}}"))
+    {
+        return lookup(payload, path.trim()).cloned();
+    }
+
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        let path = rest[start + 2..start + end].trim();
+        if let Some(value) = lookup(payload, path) {
+            output.push_str(&to_text(value));
+        }
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    Some(Value::String(output))
+}
+
+// 把映射出的值转换成字段需要的类型，空值视为未提供
+fn coerce(field: &str, kind: FieldKind, value: Value) -> Result<Option<Value>> {
+    let invalid = || HookError::Payload(format!("字段 {} 的值无效", field));
+    if value.is_null() || value.as_str().is_some_and(|s| s.trim().is_empty()) {
+        return Ok(None);
+    }
+    let value = match kind {
+        Text => Value::String(to_text(&value).trim().to_string()),
+        Integer => {
+            let number = match &value {
+                Value::Number(n) => n.as_i64(),
+                Value::String(s) => s.trim().parse::<i64>().ok(),
+                _ => None,
+            };
+            let number = number
+                .and_then(|n| i32::try_from(n).ok())
+                .ok_or_else(invalid)?;
+            Value::from(number)
+        }
+        Time => {
+            let time = match &value {
+                Value::String(s) => DateTime::parse_from_rfc3339(s.trim())
+                    .ok()
+                    .map(|t| t.with_timezone(&Utc)),
+                // Unix 时间戳（秒）
+                Value::Number(n) => n.as_i64().and_then(|n| DateTime::from_timestamp(n, 0)),
+                _ => None,
+            };
+            serde_json::to_value(time.ok_or_else(invalid)?).map_err(|_| invalid())?
+        }
+    };
+    Ok(Some(value))
+}
+
+// 按 mapping 取出目标字段；mapping 为空时使用请求体中的同名字段
+fn map_fields(hook: &IncomingHook, payload: &Value) -> Result<Map<String, Value>> {
+    let fields = target_fields(&hook.target)
+        .ok_or_else(|| HookError::Config(format!("无效的目标：{}", hook.target)))?;
+    let mut object = Map::new();
+    for &(field, kind) in fields {
+        let value = if hook.mapping.is_empty() {
+            payload.get(field).cloned()
+        } else {
+            hook.mapping
+                .get(field)
+                .and_then(|template| render(template, payload))
+        };
+        if let Some(value) = value.map(|v| coerce(field, kind, v)).transpose()?.flatten() {
+            object.insert(field.to_string(), value);
+        }
+    }
+    if !object.contains_key("title") {
+        return Err(HookError::Payload("缺少 title".to_string()));
+    }
+    Ok(object)
+}
+
+fn build<T: serde::de::DeserializeOwned>(object: Map<String, Value>) -> Result<T> {
+    serde_json::from_value(Value::Object(object)).map_err(|e| HookError::Payload(e.to_string()))
+}
+
+fn checked(result: std::result::Result<(), ValidationError>) -> Result<()> {
+    result.map_err(|e| HookError::Payload(e.to_string()))
+}
+
+// 按 hook 的配置从请求体生成数据，不写入存储
+pub fn map_payload(hook: &IncomingHook, payload: &Value, now: DateTime<Utc>) -> Result<HookItem> {
+    let mut object = map_fields(hook, payload)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    object.insert("id".to_string(), Value::String(id));
+    match hook.target.as_str() {
+        TARGET_MATTER => {
+            let mut matter: Matter = build(object.clone())?;
+            if !object.contains_key("start_time") {
+                matter.start_time = now;
+            }
+            if !object.contains_key("end_time") {
+                matter.end_time = matter
+                    .start_time
+                    .checked_add_signed(Duration::hours(DEFAULT_MATTER_HOURS))
+                    .ok_or_else(|| HookError::Payload("start_time 超出范围".to_string()))?;
+            }
+            matter.created_at = now;
+            matter.updated_at = now;
+            checked(validation::matter(&mut matter, Mode::Create, Api::V1))?;
+            Ok(HookItem::Matter(matter))
+        }
+        TARGET_TODO => {
+            object
+                .entry("status")
+                .or_insert_with(|| Value::String("todo".to_string()));
+            let mut todo: Todo = build(object)?;
+            todo.created_at = now;
+            todo.updated_at = now;
+            checked(validation::todo(&mut todo, Mode::Create))?;
+            Ok(HookItem::Todo(todo))
+        }
+        _ => {
+            object
+                .entry("content")
+                .or_insert_with(|| Value::String(String::new()));
+            object.entry("type_").or_insert_with(|| Value::from(0));
+            object.insert(
+                "status".to_string(),
+                Value::from(NotificationStatus::Unread as i32),
+            );
+            object.insert("created_at".to_string(), serde_json::json!(now));
+            let mut notification: NotificationRecord = build(object)?;
+            checked(validation::notification(&mut notification, Mode::Create))?;
+            Ok(HookItem::Notification(notification))
+        }
+    }
+}
+
+// 处理 POST /hooks/<token> 推送的数据
+pub fn receive(storage: &Repositories, token: &str, payload: &Value) -> Result<HookItem> {
+    let hook = storage
+        .incoming_hooks
+        .get_by_hash(&hash_token(token))?
+        .ok_or(HookError::InvalidToken)?;
+    if !hook.enabled {
+        return Err(HookError::Disabled);
+    }
+    let now = Utc::now();
+    let item = match map_payload(&hook, payload, now)? {
+        HookItem::Matter(matter) => {
+            storage.matters.create(&matter)?;
+            HookItem::Matter(matter)
+        }
+        HookItem::Todo(todo) => {
+            storage.todos.create(&todo)?;
+            // 重新读取以返回分配的 sort_order
+            HookItem::Todo(storage.todos.get_by_id(&todo.id)?.unwrap_or(todo))
+        }
+        HookItem::Notification(notification) => {
+            storage.notifications.create(&notification)?;
+            HookItem::Notification(notification)
+        }
+    };
+    if let Err(e) = storage.incoming_hooks.touch(&hook.id, now) {
+        log::warn!("Failed to update incoming hook usage: {}", e);
+    }
+    log::info!("Incoming hook {} created a {}", hook.name, hook.target);
+    Ok(item)
+}
+
+// 表单提交的字段转换成 JSON 对象，同名字段保留最后一个
+pub fn form_payload(fields: Vec<(String, String)>) -> Value {
+    Value::Object(
+        fields
+            .into_iter()
+            .map(|(key, value)| (key, Value::String(value)))
+            .collect(),
+    )
+}
+
+#[command]
+pub async fn list_incoming_hooks() -> std::result::Result<Vec<IncomingHook>, String> {
+    let storage = reminder::command_storage().await?;
+    storage.incoming_hooks.get_all().map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn create_incoming_hook(hook: IncomingHook) -> std::result::Result<CreatedHook, String> {
+    let storage = reminder::command_storage().await?;
+    create(&storage, hook).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn update_incoming_hook(
+    id: String,
+    hook: IncomingHook,
+) -> std::result::Result<IncomingHook, String> {
+    let storage = reminder::command_storage().await?;
+    update(&storage, &id, hook).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn delete_incoming_hook(id: String) -> std::result::Result<(), String> {
+    let storage = reminder::command_storage().await?;
+    storage
+        .incoming_hooks
+        .delete(&id)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn hook(target: &str, mapping: Value) -> IncomingHook {
+        serde_json::from_value(json!({
+            "name": "test",
+            "target": target,
+            "mapping": mapping,
+        }))
+        .unwrap()
+    }
+
+    fn payload() -> Value {
+        json!({
+            "count": 3,
+            "repository": { "name": "fates" },
+            "labels": ["bug", "ci"],
+            "commits": [{ "id": "a1" }, { "id": "b2" }],
+        })
+    }
+
+    #[test]
+    fn lookup_follows_object_keys_and_array_indexes() {
+        let payload = payload();
+        assert_eq!(lookup(&payload, "repository.name"), Some(&json!("fates")));
+        assert_eq!(lookup(&payload, "commits.1.id"), Some(&json!("b2")));
+        assert_eq!(lookup(&payload, "commits.2.id"), None);
+        assert_eq!(lookup(&payload, "commits.first"), None);
+        assert_eq!(lookup(&payload, "count.value"), None);
+    }
+
+    #[test]
+    fn single_placeholder_keeps_type() {
+        let payload = payload();
+        assert_eq!(render("{{count}}", &payload), Some(json!(3)));
+        assert_eq!(
+            render(" {{ labels }} ", &payload),
+            Some(json!(["bug", "ci"]))
+        );
+        // 取不到值时视为未提供
+        assert_eq!(render("{{missing}}", &payload), None);
+    }
+
+    #[test]
+    fn mixed_template_renders_text() {
+        let payload = payload();
+        assert_eq!(
+            render("构建失败：{{repository.name}} #{{count}}", &payload),
+            Some(json!("构建失败：fates #3"))
+        );
+        assert_eq!(
+            render("{{labels}} / {{commits.0.id}}", &payload),
+            Some(json!("bug,ci / a1"))
+        );
+        assert_eq!(render("[{{missing}}]", &payload), Some(json!("[]")));
+        // 没有闭合的占位符原样保留
+        assert_eq!(render("a {{b", &payload), Some(json!("a {{b")));
+    }
+
+    #[test]
+    fn coerces_times_and_integers() {
+        let expected = json!("2024-05-01T00:00:00Z");
+        let time = |value: Value| coerce("start_time", Time, value).unwrap();
+        assert_eq!(
+            time(json!("2024-05-01T08:00:00+08:00")),
+            Some(expected.clone())
+        );
+        assert_eq!(time(json!(1714521600)), Some(expected));
+        assert_eq!(time(json!(" ")), None);
+        assert!(matches!(
+            coerce("start_time", Time, json!("2024-05-01")),
+            Err(HookError::Payload(_))
+        ));
+
+        assert_eq!(
+            coerce("priority", Integer, json!(" 2 ")).unwrap(),
+            Some(json!(2))
+        );
+        assert!(coerce("priority", Integer, json!("high")).is_err());
+        assert!(coerce("priority", Integer, json!(1_i64 << 40)).is_err());
+        assert_eq!(
+            coerce("tags", Text, json!(["a", "b"])).unwrap(),
+            Some(json!("a,b"))
+        );
+    }
+
+    #[test]
+    fn maps_matter_with_default_times() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 8, 0, 0).unwrap();
+        let hook = hook(
+            TARGET_MATTER,
+            json!({
+                "title": "构建失败：{{repository.name}}",
+                "tags": "{{labels}}",
+                "priority": "{{count}}",
+            }),
+        );
+        let HookItem::Matter(matter) = map_payload(&hook, &payload(), now).unwrap() else {
+            panic!("expected a matter");
+        };
+        assert_eq!(matter.title, "构建失败：fates");
+        assert_eq!(matter.tags.as_deref(), Some("bug,ci"));
+        // 3 超出范围，校验时截断
+        assert_eq!(matter.priority, validation::PRIORITY_MAX);
+        assert_eq!(matter.start_time, now);
+        assert_eq!(matter.end_time, now + Duration::hours(DEFAULT_MATTER_HOURS));
+        assert_eq!(matter.created_at, now);
+    }
+
+    #[test]
+    fn maps_same_named_fields_without_mapping() {
+        let hook = hook(TARGET_TODO, json!({}));
+        let payload = json!({ "title": "回复邮件", "notes": "今天", "ignored": true });
+        let HookItem::Todo(todo) = map_payload(&hook, &payload, Utc::now()).unwrap() else {
+            panic!("expected a todo");
+        };
+        assert_eq!(todo.title, "回复邮件");
+        assert_eq!(todo.notes.as_deref(), Some("今天"));
+        assert_eq!(todo.status, "todo");
+    }
+
+    #[test]
+    fn rejects_missing_title_and_out_of_range_time() {
+        let hook = hook(TARGET_NOTIFICATION, json!({ "title": "{{missing}}" }));
+        assert!(matches!(
+            map_payload(&hook, &payload(), Utc::now()),
+            Err(HookError::Payload(_))
+        ));
+
+        let hook = self::hook(TARGET_MATTER, json!({}));
+        let payload = json!({ "title": "t", "start_time": DateTime::<Utc>::MAX_UTC.timestamp() });
+        assert!(matches!(
+            map_payload(&hook, &payload, Utc::now()),
+            Err(HookError::Payload(_))
+        ));
+    }
+}
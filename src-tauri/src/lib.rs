@@ -1,14 +1,35 @@
 // Learn more about Tauri commands at https://v2.tauri.app/develop/calling-rust/
 
+mod attachment;
+mod auth;
 mod autostart;
+mod cors;
 mod database;
+mod digest;
+mod events;
+mod holiday;
+mod incoming_hook;
 mod http_server;
 mod models;
+mod notification_channel;
+mod openapi;
+mod patch;
+mod problem;
+mod profile;
+mod recurrence;
+mod reminder;
+mod repeat_scheduler;
+mod repository;
+mod server_config;
 mod utils;
+mod validation;
+mod webhook;
 mod tray;
 mod calendar;
 
 use crate::http_server::start_http_server;
+use crate::server_config::ServerConfig;
+use crate::profile::ProfileManager;
 use tauri::Manager;
 use tauri_plugin_autostart::MacosLauncher;
 use tauri_plugin_autostart::ManagerExt;
@@ -84,13 +105,70 @@ pub fn run() {
             calendar::request_calendar_access,
             calendar::get_calendar_permission_status,
             calendar::open_calendar_setting,
+            profile::list_profiles,
+            profile::create_profile,
+            profile::switch_profile,
+            profile::delete_profile,
+            reminder::list_matter_reminders,
+            reminder::create_matter_reminder,
+            reminder::update_matter_reminder,
+            reminder::delete_matter_reminder,
+            reminder::snooze_notification,
+            reminder::acknowledge_notification,
+            reminder::dismiss_notification,
+            holiday::import_holiday_calendar,
+            notification_channel::get_notification_channels,
+            notification_channel::set_notification_channels,
+            notification_channel::test_notification_channel,
+            digest::get_digest,
+            digest::send_digest,
+            server_config::get_http_server_info,
+            server_config::get_http_server_config,
+            server_config::set_http_server_config,
+            server_config::restart_http_server,
+            server_config::stop_http_server,
+            auth::get_app_api_token,
+            auth::list_api_tokens,
+            auth::create_api_token,
+            auth::delete_api_token,
+            webhook::list_webhooks,
+            webhook::create_webhook,
+            webhook::update_webhook,
+            webhook::delete_webhook,
+            webhook::get_webhook_deliveries,
+            webhook::test_webhook,
+            incoming_hook::list_incoming_hooks,
+            incoming_hook::create_incoming_hook,
+            incoming_hook::update_incoming_hook,
+            incoming_hook::delete_incoming_hook,
         ])
         .setup(|app| {
             try_register_tray_icon(app).unwrap();
-            let db = database::initialize_database(&app.handle()).unwrap();
-            if let Err(e) = start_http_server(8523, db.clone()) {
-                log::error!("Failed to start HTTP server: {}", e);
+            // FATES_STORAGE=memory 时使用内存存储，不会读写 fates.db
+            let in_memory = std::env::var("FATES_STORAGE").as_deref() == Ok("memory");
+            if in_memory {
+                log::warn!("Using in-memory storage, data will not be persisted");
             }
+            let app_dir = utils::get_app_data_dir(app.handle().clone()).unwrap();
+            holiday::init(app_dir.join("holidays"));
+            let server_config = ServerConfig::load(&app_dir);
+            let profiles = ProfileManager::new(app_dir, in_memory);
+            log::info!("Active profile: {}", profiles.active());
+            let storage = profiles.open_active().unwrap();
+            match attachment::sweep_orphans(&storage) {
+                Ok(removed) if removed > 0 => log::info!("Removed {} orphaned attachments", removed),
+                Ok(_) => {}
+                Err(e) => log::error!("Failed to clean up attachments: {}", e),
+            }
+            app.manage(profiles);
+            match tauri::async_runtime::block_on(start_http_server(&server_config, storage)) {
+                Ok(info) => log::info!("HTTP server available at {}", info.base_url),
+                Err(e) => log::error!("Failed to start HTTP server: {}", e),
+            }
+            repeat_scheduler::start(app.handle().clone());
+            reminder::start(app.handle().clone());
+            digest::start(app.handle().clone());
+            webhook::start();
             Ok(())
         })
         .on_window_event(handle_window_event)
@@ -119,7 +197,7 @@ fn handle_run_event(_app_handle: &tauri::AppHandle, event: tauri::RunEvent) {
         tauri::RunEvent::Exit => {
 
             log::warn!("Exit");
-            if let Err(e) = http_server::stop_http_server() {
+            if let Err(e) = tauri::async_runtime::block_on(http_server::shutdown_http_server()) {
                 log::error!("Failed to stop HTTP server: {}", e);
             }
         }
@@ -0,0 +1,1048 @@
+// 通知投递渠道：系统通知、HTTP webhook（通用 JSON 和 ntfy）以及 SMTP 邮件
+// 渠道配置保存在 notification_channel 表中，每个渠道可以只接收部分通知类型；
+// 密码、令牌和请求头与配置分开保存，读取配置时不会返回。
+// 每条通知在每个渠道上有一条投递记录，失败后按指数退避重试，汇总状态写回 NotificationRecord
+
+use crate::database::{
+    DeliveryStatus, NotificationChannelRecord, NotificationDelivery, NotificationRecord,
+};
+use crate::reminder;
+use crate::repository::{Repositories, Result};
+use chrono::{DateTime, Duration, Utc};
+use futures::future::BoxFuture;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::{command, AppHandle};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_notification::NotificationExt;
+use thiserror::Error;
+
+const NATIVE_CHANNEL_ID: &str = "native";
+
+// 最多尝试次数，之后标记为失败
+pub(crate) const MAX_ATTEMPTS: i32 = 5;
+// 第 n 次失败后等待 30s * 2^(n-1)，最长 1 小时
+const BACKOFF_BASE_SECS: i64 = 30;
+const BACKOFF_MAX_SECS: i64 = 60 * 60;
+const REQUEST_TIMEOUT_SECS: u64 = 15;
+
+#[derive(Error, Debug)]
+pub enum ChannelError {
+    #[error("渠道配置无效：{0}")]
+    Config(String),
+    #[error("HTTP 请求失败：{0}")]
+    Http(String),
+    #[error("HTTP 状态码 {0}")]
+    Status(u16),
+    #[error("邮件发送失败：{0}")]
+    Smtp(String),
+    #[error("系统通知失败：{0}")]
+    Native(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    // 明文连接，只适合本机或内网的测试服务器
+    None,
+    #[default]
+    Starttls,
+    Tls,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChannelKind {
+    Native,
+    // POST 通知的 JSON
+    Webhook {
+        url: String,
+        // 可能带有认证信息，和其他密钥一样只写不读
+        #[serde(default, skip_serializing)]
+        headers: Option<HashMap<String, String>>,
+    },
+    // url 为 ntfy 服务器地址加主题，例如 https://ntfy.sh/my-topic
+    Ntfy {
+        url: String,
+        #[serde(default, skip_serializing)]
+        token: Option<String>,
+        #[serde(default)]
+        priority: Option<u8>,
+    },
+    Smtp {
+        host: String,
+        #[serde(default)]
+        port: Option<u16>,
+        #[serde(default)]
+        security: SmtpSecurity,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default, skip_serializing)]
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChannelConfig {
+    pub id: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    // 接收的通知类型，为空表示全部
+    #[serde(default)]
+    pub types: Vec<i32>,
+    #[serde(flatten)]
+    pub kind: ChannelKind,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// 不随配置返回的字段，保存在 secrets 列
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ChannelSecrets {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    headers: Option<HashMap<String, String>>,
+}
+
+impl ChannelKind {
+    // 空字符串表示清除
+    fn secrets(&self) -> ChannelSecrets {
+        let non_empty = |s: &Option<String>| s.clone().filter(|s| !s.is_empty());
+        match self {
+            ChannelKind::Native => ChannelSecrets::default(),
+            ChannelKind::Webhook { headers, .. } => ChannelSecrets {
+                headers: headers.clone().filter(|h| !h.is_empty()),
+                ..Default::default()
+            },
+            ChannelKind::Ntfy { token, .. } => ChannelSecrets {
+                token: non_empty(token),
+                ..Default::default()
+            },
+            ChannelKind::Smtp { password, .. } => ChannelSecrets {
+                password: non_empty(password),
+                ..Default::default()
+            },
+        }
+    }
+
+    // 没有提供的密钥使用已保存的值
+    fn fill_secrets(&mut self, secrets: ChannelSecrets) {
+        match self {
+            ChannelKind::Native => {}
+            ChannelKind::Webhook { headers, .. } => {
+                if headers.is_none() {
+                    *headers = secrets.headers;
+                }
+            }
+            ChannelKind::Ntfy { token, .. } => {
+                if token.is_none() {
+                    *token = secrets.token;
+                }
+            }
+            ChannelKind::Smtp { password, .. } => {
+                if password.is_none() {
+                    *password = secrets.password;
+                }
+            }
+        }
+    }
+}
+
+impl ChannelConfig {
+    fn accepts(&self, type_: i32) -> bool {
+        self.enabled && (self.types.is_empty() || self.types.contains(&type_))
+    }
+}
+
+pub trait NotificationChannel: Send + Sync {
+    fn send<'a>(
+        &'a self,
+        record: &'a NotificationRecord,
+    ) -> BoxFuture<'a, std::result::Result<(), ChannelError>>;
+}
+
+pub struct NativeChannel {
+    app: AppHandle,
+}
+
+impl NotificationChannel for NativeChannel {
+    fn send<'a>(
+        &'a self,
+        record: &'a NotificationRecord,
+    ) -> BoxFuture<'a, std::result::Result<(), ChannelError>> {
+        Box::pin(async move {
+            self.app
+                .notification()
+                .builder()
+                .title(&record.title)
+                .body(&record.content)
+                .show()
+                .map_err(|e| ChannelError::Native(e.to_string()))
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    id: &'a str,
+    title: &'a str,
+    content: &'a str,
+    #[serde(rename = "type")]
+    type_: i32,
+    related_task_id: Option<&'a str>,
+    created_at: DateTime<Utc>,
+}
+
+async fn post_json(
+    request: reqwest::RequestBuilder,
+    body: &impl Serialize,
+) -> std::result::Result<(), ChannelError> {
+    let body = serde_json::to_vec(body).map_err(|e| ChannelError::Http(e.to_string()))?;
+    let response = request
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| ChannelError::Http(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(ChannelError::Status(response.status().as_u16()));
+    }
+    Ok(())
+}
+
+pub struct WebhookChannel {
+    client: reqwest::Client,
+    url: String,
+    headers: HashMap<String, String>,
+}
+
+impl NotificationChannel for WebhookChannel {
+    fn send<'a>(
+        &'a self,
+        record: &'a NotificationRecord,
+    ) -> BoxFuture<'a, std::result::Result<(), ChannelError>> {
+        Box::pin(async move {
+            let mut request = self.client.post(&self.url);
+            for (name, value) in &self.headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+            let payload = WebhookPayload {
+                id: &record.id,
+                title: &record.title,
+                content: &record.content,
+                type_: record.type_,
+                related_task_id: record.related_task_id.as_deref(),
+                created_at: record.created_at,
+            };
+            post_json(request, &payload).await
+        })
+    }
+}
+
+// 使用 ntfy 的 JSON 发布接口，标题和内容不受 HTTP 头只能是 ASCII 的限制
+#[derive(Serialize)]
+struct NtfyPayload<'a> {
+    topic: &'a str,
+    title: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<u8>,
+}
+
+pub struct NtfyChannel {
+    client: reqwest::Client,
+    server: String,
+    topic: String,
+    token: Option<String>,
+    priority: Option<u8>,
+}
+
+impl NotificationChannel for NtfyChannel {
+    fn send<'a>(
+        &'a self,
+        record: &'a NotificationRecord,
+    ) -> BoxFuture<'a, std::result::Result<(), ChannelError>> {
+        Box::pin(async move {
+            let mut request = self.client.post(&self.server);
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token);
+            }
+            let payload = NtfyPayload {
+                topic: &self.topic,
+                title: &record.title,
+                message: &record.content,
+                priority: self.priority,
+            };
+            post_json(request, &payload).await
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct SmtpChannel {
+    host: String,
+    port: Option<u16>,
+    security: SmtpSecurity,
+    credentials: Option<(String, String)>,
+    from: String,
+    to: Vec<String>,
+}
+
+impl SmtpChannel {
+    // lettre 的 SmtpTransport 是阻塞的，在 spawn_blocking 中调用
+    fn send_blocking(
+        &self,
+        subject: String,
+        body: String,
+    ) -> std::result::Result<(), ChannelError> {
+        use lettre::message::{header::ContentType, Mailbox};
+        use lettre::transport::smtp::authentication::Credentials;
+        use lettre::{Message, SmtpTransport, Transport};
+
+        let smtp_err = |e: &dyn std::fmt::Display| ChannelError::Smtp(e.to_string());
+        let from: Mailbox = self.from.parse().map_err(|e| smtp_err(&e))?;
+        let mut message = Message::builder().from(from).subject(subject);
+        for to in &self.to {
+            message = message.to(to.parse::<Mailbox>().map_err(|e| smtp_err(&e))?);
+        }
+        let message = message
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .map_err(|e| smtp_err(&e))?;
+
+        let mut builder = match self.security {
+            SmtpSecurity::None => SmtpTransport::builder_dangerous(&self.host),
+            SmtpSecurity::Starttls => {
+                SmtpTransport::starttls_relay(&self.host).map_err(|e| smtp_err(&e))?
+            }
+            SmtpSecurity::Tls => SmtpTransport::relay(&self.host).map_err(|e| smtp_err(&e))?,
+        };
+        if let Some(port) = self.port {
+            builder = builder.port(port);
+        }
+        if let Some((username, password)) = &self.credentials {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        let transport = builder
+            .timeout(Some(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS)))
+            .build();
+        transport.send(&message).map_err(|e| smtp_err(&e))?;
+        Ok(())
+    }
+}
+
+impl NotificationChannel for SmtpChannel {
+    fn send<'a>(
+        &'a self,
+        record: &'a NotificationRecord,
+    ) -> BoxFuture<'a, std::result::Result<(), ChannelError>> {
+        Box::pin(async move {
+            let channel = self.clone();
+            let (subject, body) = (record.title.clone(), record.content.clone());
+            tauri::async_runtime::spawn_blocking(move || channel.send_blocking(subject, body))
+                .await
+                .map_err(|e| ChannelError::Smtp(e.to_string()))?
+        })
+    }
+}
+
+fn is_http_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+// 校验并补全渠道配置
+pub fn validate(configs: &[ChannelConfig]) -> std::result::Result<(), ChannelError> {
+    let mut ids = HashSet::new();
+    for config in configs {
+        let id = config.id.trim();
+        if id.is_empty() || id.contains(':') {
+            return Err(ChannelError::Config(format!(
+                "无效的渠道 id：{}",
+                config.id
+            )));
+        }
+        if !ids.insert(id) {
+            return Err(ChannelError::Config(format!("渠道 id 重复：{}", id)));
+        }
+        match &config.kind {
+            ChannelKind::Native => {}
+            ChannelKind::Webhook { url, .. } if !is_http_url(url) => {
+                return Err(ChannelError::Config(format!(
+                    "无效的 webhook 地址：{}",
+                    url
+                )));
+            }
+            ChannelKind::Ntfy { url, .. } => {
+                split_ntfy_url(url)?;
+            }
+            ChannelKind::Smtp { host, from, to, .. } => {
+                if host.trim().is_empty() || from.trim().is_empty() || to.is_empty() {
+                    return Err(ChannelError::Config(
+                        "SMTP 渠道需要 host、from 和 to".to_string(),
+                    ));
+                }
+            }
+            ChannelKind::Webhook { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+// https://ntfy.sh/my-topic -> ("https://ntfy.sh/", "my-topic")
+fn split_ntfy_url(url: &str) -> std::result::Result<(String, String), ChannelError> {
+    let invalid = || ChannelError::Config(format!("无效的 ntfy 地址：{}", url));
+    if !is_http_url(url) {
+        return Err(invalid());
+    }
+    let trimmed = url.trim_end_matches('/');
+    let (server, topic) = trimmed.rsplit_once('/').ok_or_else(invalid)?;
+    if topic.is_empty() || server.ends_with('/') {
+        return Err(invalid());
+    }
+    Ok((format!("{}/", server), topic.to_string()))
+}
+
+fn http_client() -> std::result::Result<reqwest::Client, ChannelError> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| ChannelError::Http(e.to_string()))
+}
+
+pub fn build_channel(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    config: &ChannelConfig,
+) -> std::result::Result<Box<dyn NotificationChannel>, ChannelError> {
+    match &config.kind {
+        ChannelKind::Native => Ok(Box::new(NativeChannel { app: app.clone() })),
+        kind => remote_channel(client, kind),
+    }
+}
+
+// 除系统通知外的渠道只依赖 HTTP 客户端或 SMTP 服务器
+fn remote_channel(
+    client: &reqwest::Client,
+    kind: &ChannelKind,
+) -> std::result::Result<Box<dyn NotificationChannel>, ChannelError> {
+    Ok(match kind {
+        ChannelKind::Native => {
+            return Err(ChannelError::Native("系统通知需要应用句柄".to_string()))
+        }
+        ChannelKind::Webhook { url, headers } => Box::new(WebhookChannel {
+            client: client.clone(),
+            url: url.clone(),
+            headers: headers.clone().unwrap_or_default(),
+        }),
+        ChannelKind::Ntfy {
+            url,
+            token,
+            priority,
+        } => {
+            let (server, topic) = split_ntfy_url(url)?;
+            Box::new(NtfyChannel {
+                client: client.clone(),
+                server,
+                topic,
+                token: token.clone(),
+                priority: *priority,
+            })
+        }
+        ChannelKind::Smtp {
+            host,
+            port,
+            security,
+            username,
+            password,
+            from,
+            to,
+        } => Box::new(SmtpChannel {
+            host: host.clone(),
+            port: *port,
+            security: *security,
+            credentials: username
+                .clone()
+                .map(|u| (u, password.clone().unwrap_or_default())),
+            from: from.clone(),
+            to: to.clone(),
+        }),
+    })
+}
+
+// 没有配置时只使用系统通知，与之前的行为一致；要停用系统通知需保存 enabled 为 false 的渠道
+pub fn load_channels(storage: &Repositories) -> Result<Vec<ChannelConfig>> {
+    let records = storage.channels.get_all()?;
+    if records.is_empty() {
+        return Ok(vec![ChannelConfig {
+            id: NATIVE_CHANNEL_ID.to_string(),
+            enabled: true,
+            types: Vec::new(),
+            kind: ChannelKind::Native,
+        }]);
+    }
+    let mut configs = Vec::new();
+    for record in records {
+        let mut config: ChannelConfig = match serde_json::from_str(&record.config) {
+            Ok(config) => config,
+            Err(e) => {
+                log::error!("Invalid notification channel {}: {}", record.id, e);
+                continue;
+            }
+        };
+        config.kind.fill_secrets(parse_secrets(&record));
+        configs.push(config);
+    }
+    Ok(configs)
+}
+
+fn parse_secrets(record: &NotificationChannelRecord) -> ChannelSecrets {
+    serde_json::from_str(&record.secrets).unwrap_or_default()
+}
+
+fn stored_secrets(storage: &Repositories) -> Result<HashMap<String, ChannelSecrets>> {
+    Ok(storage
+        .channels
+        .get_all()?
+        .iter()
+        .map(|record| (record.id.clone(), parse_secrets(record)))
+        .collect())
+}
+
+// 同一 id 的渠道没有提供密钥时沿用已保存的值
+pub fn save_channels(
+    storage: &Repositories,
+    configs: &[ChannelConfig],
+) -> std::result::Result<(), String> {
+    validate(configs).map_err(|e| e.to_string())?;
+    let mut stored = stored_secrets(storage).map_err(|e| e.to_string())?;
+    let mut records = Vec::with_capacity(configs.len());
+    for config in configs {
+        let mut kind = config.kind.clone();
+        if let Some(secrets) = stored.remove(&config.id) {
+            kind.fill_secrets(secrets);
+        }
+        records.push(NotificationChannelRecord {
+            id: config.id.clone(),
+            config: serde_json::to_string(config).map_err(|e| e.to_string())?,
+            secrets: serde_json::to_string(&kind.secrets()).map_err(|e| e.to_string())?,
+        });
+    }
+    storage
+        .channels
+        .replace_all(&records)
+        .map_err(|e| e.to_string())
+}
+
+// 为通知创建各渠道的投递记录，由 dispatch 实际发送
+pub fn enqueue(
+    storage: &Repositories,
+    record: &NotificationRecord,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let mut queued = false;
+    for config in load_channels(storage)? {
+        if !config.accepts(record.type_) {
+            continue;
+        }
+        storage.deliveries.upsert(&NotificationDelivery {
+            id: format!("{}:{}", record.id, config.id),
+            notification_id: record.id.clone(),
+            channel_id: config.id.clone(),
+            status: DeliveryStatus::Pending as i32,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            delivered_at: None,
+            created_at: now,
+        })?;
+        queued = true;
+    }
+    let status = queued.then_some(DeliveryStatus::Pending as i32);
+    storage
+        .notifications
+        .update_delivery_status(&record.id, status)
+}
+
+pub(crate) fn backoff(attempts: i32) -> Duration {
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    Duration::seconds((BACKOFF_BASE_SECS * 2i64.pow(exponent)).min(BACKOFF_MAX_SECS))
+}
+
+// 通知的汇总投递状态
+fn summarize(deliveries: &[NotificationDelivery]) -> Option<i32> {
+    let count = |status: DeliveryStatus| {
+        deliveries
+            .iter()
+            .filter(|d| d.status == status as i32)
+            .count()
+    };
+    let status = if deliveries.is_empty() {
+        return None;
+    } else if count(DeliveryStatus::Pending) > 0 {
+        DeliveryStatus::Pending
+    } else if count(DeliveryStatus::Delivered) == deliveries.len() {
+        DeliveryStatus::Delivered
+    } else if count(DeliveryStatus::Delivered) == 0 {
+        DeliveryStatus::Failed
+    } else {
+        DeliveryStatus::Partial
+    };
+    Some(status as i32)
+}
+
+// 发送所有到期的投递，失败的按退避时间重试
+pub async fn dispatch(app: &AppHandle, storage: &Repositories, now: DateTime<Utc>) -> Result<()> {
+    let client = http_client();
+    deliver_due(storage, now, |config| match &client {
+        Ok(client) => build_channel(app, client, config),
+        Err(e) => Err(ChannelError::Http(e.to_string())),
+    })
+    .await
+}
+
+// build 根据配置创建渠道，与 AppHandle 无关的部分可以单独测试
+async fn deliver_due(
+    storage: &Repositories,
+    now: DateTime<Utc>,
+    build: impl Fn(&ChannelConfig) -> std::result::Result<Box<dyn NotificationChannel>, ChannelError>,
+) -> Result<()> {
+    let due = storage.deliveries.get_due(now)?;
+    if due.is_empty() {
+        return Ok(());
+    }
+    let configs: HashMap<String, ChannelConfig> = load_channels(storage)?
+        .into_iter()
+        .map(|c| (c.id.clone(), c))
+        .collect();
+    let mut touched = HashSet::new();
+
+    for mut delivery in due {
+        let Some(record) = storage.notifications.get_by_id(&delivery.notification_id)? else {
+            storage
+                .deliveries
+                .delete_by_notification(&delivery.notification_id)?;
+            continue;
+        };
+        touched.insert(record.id.clone());
+
+        let result = match configs.get(&delivery.channel_id) {
+            Some(config) if config.enabled => match build(config) {
+                Ok(channel) => channel.send(&record).await,
+                Err(e) => Err(e),
+            },
+            _ => Err(ChannelError::Config(format!(
+                "渠道 {} 不存在或已停用",
+                delivery.channel_id
+            ))),
+        };
+
+        delivery.attempts += 1;
+        match result {
+            Ok(()) => {
+                delivery.status = DeliveryStatus::Delivered as i32;
+                delivery.delivered_at = Some(now);
+                delivery.last_error = None;
+            }
+            Err(e) => {
+                log::warn!(
+                    "Failed to deliver notification {} via {}: {}",
+                    record.id,
+                    delivery.channel_id,
+                    e
+                );
+                let retryable = !matches!(e, ChannelError::Config(_));
+                delivery.last_error = Some(e.to_string());
+                if retryable && delivery.attempts < MAX_ATTEMPTS {
+                    delivery.next_attempt_at = now + backoff(delivery.attempts);
+                } else {
+                    delivery.status = DeliveryStatus::Failed as i32;
+                }
+            }
+        }
+        storage.deliveries.update(&delivery)?;
+    }
+
+    for id in touched {
+        let deliveries = storage.deliveries.get_by_notification(&id)?;
+        storage
+            .notifications
+            .update_delivery_status(&id, summarize(&deliveries))?;
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn get_notification_channels() -> std::result::Result<Vec<ChannelConfig>, String> {
+    let storage = reminder::command_storage().await?;
+    load_channels(&storage).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn set_notification_channels(
+    channels: Vec<ChannelConfig>,
+) -> std::result::Result<(), String> {
+    let storage = reminder::command_storage().await?;
+    save_channels(&storage, &channels)
+}
+
+// 立即通过指定渠道发送一条测试通知，不写入通知记录；没有提供的密钥使用该渠道已保存的值
+#[command]
+pub async fn test_notification_channel(
+    app: AppHandle,
+    mut channel: ChannelConfig,
+) -> std::result::Result<(), String> {
+    validate(std::slice::from_ref(&channel)).map_err(|e| e.to_string())?;
+    let storage = reminder::command_storage().await?;
+    let mut stored = stored_secrets(&storage).map_err(|e| e.to_string())?;
+    if let Some(secrets) = stored.remove(&channel.id) {
+        channel.kind.fill_secrets(secrets);
+    }
+    let client = http_client().map_err(|e| e.to_string())?;
+    let channel = build_channel(&app, &client, &channel).map_err(|e| e.to_string())?;
+    let record = reminder::new_record(
+        "Fates 测试通知".to_string(),
+        "收到这条消息说明通知渠道配置正确".to_string(),
+        0,
+        None,
+    );
+    channel.send(&record).await.map_err(|e| e.to_string())
+}
+
+// webhook、ntfy 和 SMTP 渠道都发送到本机的替身服务器
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reminder::new_record;
+    use axum::body::Bytes;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode, Uri};
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    #[derive(Clone)]
+    struct StandIn {
+        // 前几个请求返回 500
+        failures: usize,
+        requests: Arc<Mutex<Vec<(String, HeaderMap, Value)>>>,
+    }
+
+    impl StandIn {
+        fn requests(&self) -> Vec<(String, HeaderMap, Value)> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    async fn record(
+        State(stand_in): State<StandIn>,
+        uri: Uri,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> StatusCode {
+        let mut requests = stand_in.requests.lock().unwrap();
+        let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+        requests.push((uri.path().to_string(), headers, body));
+        if requests.len() <= stand_in.failures {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    async fn http_stand_in(failures: usize) -> (String, StandIn) {
+        let stand_in = StandIn {
+            failures,
+            requests: Arc::default(),
+        };
+        let app = axum::Router::new()
+            .fallback(record)
+            .with_state(stand_in.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}", addr), stand_in)
+    }
+
+    // 只实现发送一封邮件需要的命令，返回端口和收到的 DATA
+    async fn smtp_stand_in() -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let messages: Arc<Mutex<Vec<String>>> = Arc::default();
+        let received = messages.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+            let mut data: Option<String> = None;
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Some(message) = data.as_mut() {
+                    if line == "." {
+                        received.lock().unwrap().push(data.take().unwrap());
+                        write.write_all(b"250 OK\r\n").await.unwrap();
+                    } else {
+                        message.push_str(&line);
+                        message.push('\n');
+                    }
+                    continue;
+                }
+                let command = line.to_ascii_uppercase();
+                let reply: &[u8] = if command.starts_with("DATA") {
+                    data = Some(String::new());
+                    b"354 End data with <CR><LF>.<CR><LF>\r\n"
+                } else if command.starts_with("QUIT") {
+                    b"221 Bye\r\n"
+                } else {
+                    b"250 OK\r\n"
+                };
+                write.write_all(reply).await.unwrap();
+                if command.starts_with("QUIT") {
+                    break;
+                }
+            }
+        });
+        (port, messages)
+    }
+
+    fn channel(id: &str, types: Vec<i32>, kind: ChannelKind) -> ChannelConfig {
+        ChannelConfig {
+            id: id.to_string(),
+            enabled: true,
+            types,
+            kind,
+        }
+    }
+
+    fn webhook(url: String) -> ChannelKind {
+        ChannelKind::Webhook {
+            url,
+            headers: Some(HashMap::from([("X-Fates".to_string(), "test".to_string())])),
+        }
+    }
+
+    fn send_remote(
+        kind: &ChannelKind,
+        record: &NotificationRecord,
+    ) -> BoxFuture<'static, std::result::Result<(), ChannelError>> {
+        let channel = remote_channel(&http_client().unwrap(), kind).unwrap();
+        let record = record.clone();
+        Box::pin(async move { channel.send(&record).await })
+    }
+
+    #[tokio::test]
+    async fn webhook_posts_json() {
+        let (url, stand_in) = http_stand_in(0).await;
+        let record = new_record("标题".into(), "内容".into(), 2, Some("matter-1".into()));
+        send_remote(&webhook(format!("{}/hook", url)), &record)
+            .await
+            .unwrap();
+
+        let requests = stand_in.requests();
+        assert_eq!(requests.len(), 1);
+        let (path, headers, body) = &requests[0];
+        assert_eq!(path, "/hook");
+        assert_eq!(headers["x-fates"], "test");
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(body["id"], record.id.as_str());
+        assert_eq!(body["title"], "标题");
+        assert_eq!(body["content"], "内容");
+        assert_eq!(body["type"], 2);
+        assert_eq!(body["related_task_id"], "matter-1");
+    }
+
+    #[tokio::test]
+    async fn webhook_error_status() {
+        let (url, _) = http_stand_in(1).await;
+        let record = new_record("title".into(), "content".into(), 0, None);
+        let result = send_remote(&webhook(url), &record).await;
+        assert!(matches!(result, Err(ChannelError::Status(500))));
+    }
+
+    #[tokio::test]
+    async fn ntfy_publishes_json_to_server_root() {
+        let (url, stand_in) = http_stand_in(0).await;
+        let kind = ChannelKind::Ntfy {
+            url: format!("{}/my-topic", url),
+            token: Some("secret".into()),
+            priority: Some(4),
+        };
+        let record = new_record("标题".into(), "内容".into(), 0, None);
+        send_remote(&kind, &record).await.unwrap();
+
+        let requests = stand_in.requests();
+        let (path, headers, body) = &requests[0];
+        assert_eq!(path, "/");
+        assert_eq!(headers["authorization"], "Bearer secret");
+        assert_eq!(body["topic"], "my-topic");
+        assert_eq!(body["title"], "标题");
+        assert_eq!(body["message"], "内容");
+        assert_eq!(body["priority"], 4);
+    }
+
+    #[tokio::test]
+    async fn smtp_sends_mail() {
+        let (port, messages) = smtp_stand_in().await;
+        let kind = ChannelKind::Smtp {
+            host: "127.0.0.1".into(),
+            port: Some(port),
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            from: "Fates <fates@example.com>".into(),
+            to: vec!["user@example.com".into()],
+        };
+        let record = new_record("Meeting".into(), "Starts in 10 minutes".into(), 0, None);
+        send_remote(&kind, &record).await.unwrap();
+
+        let messages = messages.lock().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("Subject: Meeting"));
+        assert!(messages[0].contains("To: user@example.com"));
+        assert!(messages[0].contains("Starts in 10 minutes"));
+    }
+
+    #[tokio::test]
+    async fn dispatch_retries_with_backoff() {
+        let (url, stand_in) = http_stand_in(1).await;
+        let storage = Repositories::in_memory();
+        let configs = [
+            channel("hook", Vec::new(), webhook(url)),
+            // 不接收该类型，不会创建投递记录
+            channel("other", vec![9], ChannelKind::Native),
+        ];
+        save_channels(&storage, &configs).unwrap();
+
+        let record = new_record("title".into(), "content".into(), 1, None);
+        storage.notifications.create(&record).unwrap();
+        let now = Utc::now();
+        enqueue(&storage, &record, now).unwrap();
+        let client = http_client().unwrap();
+        let build = |config: &ChannelConfig| remote_channel(&client, &config.kind);
+
+        // 第一次返回 500，等待 30 秒后重试
+        deliver_due(&storage, now, build).await.unwrap();
+        let deliveries = storage.deliveries.get_by_notification(&record.id).unwrap();
+        assert_eq!(deliveries.len(), 1);
+        let delivery = &deliveries[0];
+        assert_eq!(delivery.status, DeliveryStatus::Pending as i32);
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.next_attempt_at, now + Duration::seconds(30));
+        assert!(delivery.last_error.is_some());
+
+        // 还没到重试时间
+        deliver_due(&storage, now + Duration::seconds(10), build)
+            .await
+            .unwrap();
+        assert_eq!(stand_in.requests().len(), 1);
+
+        let later = now + Duration::seconds(30);
+        deliver_due(&storage, later, build).await.unwrap();
+        let delivery = &storage.deliveries.get_by_notification(&record.id).unwrap()[0];
+        assert_eq!(delivery.status, DeliveryStatus::Delivered as i32);
+        assert_eq!(delivery.delivered_at, Some(later));
+        let record = storage
+            .notifications
+            .get_by_id(&record.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            record.delivery_status,
+            Some(DeliveryStatus::Delivered as i32)
+        );
+    }
+
+    #[tokio::test]
+    async fn dispatch_fails_removed_channel_without_retry() {
+        let (url, _) = http_stand_in(0).await;
+        let storage = Repositories::in_memory();
+        save_channels(&storage, &[channel("hook", Vec::new(), webhook(url))]).unwrap();
+        let record = new_record("title".into(), "content".into(), 0, None);
+        storage.notifications.create(&record).unwrap();
+        let now = Utc::now();
+        enqueue(&storage, &record, now).unwrap();
+        save_channels(&storage, &[]).unwrap();
+
+        let client = http_client().unwrap();
+        deliver_due(&storage, now, |config| {
+            remote_channel(&client, &config.kind)
+        })
+        .await
+        .unwrap();
+        let delivery = &storage.deliveries.get_by_notification(&record.id).unwrap()[0];
+        assert_eq!(delivery.status, DeliveryStatus::Failed as i32);
+        let record = storage
+            .notifications
+            .get_by_id(&record.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.delivery_status, Some(DeliveryStatus::Failed as i32));
+    }
+
+    #[test]
+    fn secrets_are_kept_but_not_returned() {
+        let storage = Repositories::in_memory();
+        let ntfy = |token: Option<&str>| ChannelKind::Ntfy {
+            url: "https://ntfy.sh/topic".into(),
+            token: token.map(str::to_string),
+            priority: None,
+        };
+        let token = |storage: &Repositories| match &load_channels(storage).unwrap()[0].kind {
+            ChannelKind::Ntfy { token, .. } => token.clone(),
+            kind => panic!("unexpected channel {:?}", kind),
+        };
+        save_channels(
+            &storage,
+            &[channel("ntfy", Vec::new(), ntfy(Some("s3cret")))],
+        )
+        .unwrap();
+        assert_eq!(token(&storage).as_deref(), Some("s3cret"));
+
+        let json = serde_json::to_string(&load_channels(&storage).unwrap()).unwrap();
+        assert!(!json.contains("s3cret"));
+        let record = &storage.channels.get_all().unwrap()[0];
+        assert!(!record.config.contains("s3cret"));
+
+        // 保存读取到的配置时沿用已保存的令牌
+        let returned: Vec<ChannelConfig> = serde_json::from_str(&json).unwrap();
+        save_channels(&storage, &returned).unwrap();
+        assert_eq!(token(&storage).as_deref(), Some("s3cret"));
+
+        // 空字符串清除令牌
+        save_channels(&storage, &[channel("ntfy", Vec::new(), ntfy(Some("")))]).unwrap();
+        assert_eq!(token(&storage), None);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_an_hour() {
+        assert_eq!(backoff(1), Duration::seconds(30));
+        assert_eq!(backoff(2), Duration::seconds(60));
+        assert_eq!(backoff(4), Duration::seconds(240));
+        assert_eq!(backoff(20), Duration::hours(1));
+    }
+
+    #[test]
+    fn validates_configs() {
+        let (server, topic) = split_ntfy_url("https://ntfy.sh/my-topic/").unwrap();
+        assert_eq!(
+            (server.as_str(), topic.as_str()),
+            ("https://ntfy.sh/", "my-topic")
+        );
+        assert!(split_ntfy_url("https://ntfy.sh/").is_err());
+        assert!(split_ntfy_url("ftp://ntfy.sh/topic").is_err());
+
+        let native = || channel("native", Vec::new(), ChannelKind::Native);
+        assert!(validate(&[native()]).is_ok());
+        assert!(validate(&[native(), native()]).is_err());
+        assert!(validate(&[channel("a:b", Vec::new(), ChannelKind::Native)]).is_err());
+        assert!(validate(&[channel("hook", Vec::new(), webhook("localhost".into()))]).is_err());
+    }
+}
@@ -0,0 +1,864 @@
+// HTTP API 的 OpenAPI 3 文档，由下面的接口表和请求/响应类型的 JsonSchema 生成，
+// /openapi.json 返回文档，/docs 是内嵌的交互式文档页面。
+// 在 ApiRoutes 中新增或修改路由时需要同步修改 operations()，测试会检查两者是否一致
+
+use crate::database::{
+    Attachment, IncomingHook, Matter, MatterComment, MatterReminder, NotificationDelivery,
+    NotificationRecord, RepeatOccurrence, RepeatTask, Tag, Todo, TodoFilter, Webhook,
+    WebhookDelivery,
+};
+use crate::events::ChangeEvent;
+use crate::holiday::HolidayEntry;
+use crate::http_server::{
+    CommentRequest, DigestPreview, DigestQuery, EventQuery, HolidayQuery, MatterListItem,
+    OccurrenceQuery, QueryFieldParams, RecurrencePreview, RecurrencePreviewRequest, ReorderRequest,
+    SnoozeRequest, TagsRequest, TimeRangeQuery, UploadQuery,
+};
+use crate::incoming_hook::{CreatedHook, HookItem};
+use crate::notification_channel::ChannelConfig;
+use crate::patch::MERGE_PATCH_CONTENT_TYPE;
+use crate::problem::Problem;
+use crate::webhook::CreatedWebhook;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+pub const DOCS_HTML: &str = include_str!("../resources/api-docs.html");
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Value;
+type QueryFn = fn(&mut SchemaGenerator) -> Vec<Value>;
+
+// 请求体或响应数据的格式
+#[derive(Clone, Copy)]
+enum Body {
+    Empty,
+    Json(SchemaFn),
+    // JSON merge patch，只包含要修改的字段
+    MergePatch,
+    // 原样保存的文本，例如 kv 的值
+    Text,
+    // 附件内容
+    Binary,
+    // 变更事件的 SSE 流
+    Events,
+    // 升级为 WebSocket 连接
+    WebSocket,
+}
+
+struct Operation {
+    method: &'static str,
+    path: &'static str,
+    id: &'static str,
+    summary: &'static str,
+    query: Option<QueryFn>,
+    request: Body,
+    response: Body,
+    // 不需要 Authorization 头
+    public: bool,
+}
+
+impl Operation {
+    fn query(mut self, query: QueryFn) -> Self {
+        self.query = Some(query);
+        self
+    }
+
+    fn request(mut self, body: Body) -> Self {
+        self.request = body;
+        self
+    }
+
+    fn response(mut self, body: Body) -> Self {
+        self.response = body;
+        self
+    }
+
+    fn public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+}
+
+fn op(
+    method: &'static str,
+    path: &'static str,
+    id: &'static str,
+    summary: &'static str,
+) -> Operation {
+    Operation {
+        method,
+        path,
+        id,
+        summary,
+        query: None,
+        request: Body::Empty,
+        response: Body::Empty,
+        public: false,
+    }
+}
+
+fn schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Value {
+    serde_json::to_value(gen.subschema_for::<T>()).unwrap_or_default()
+}
+
+// 查询参数结构体的每个字段展开成一个 query 参数
+fn query<T: JsonSchema>(gen: &mut SchemaGenerator) -> Vec<Value> {
+    let root = gen.root_schema_for::<T>().schema;
+    let Some(object) = root.object else {
+        return Vec::new();
+    };
+    object
+        .properties
+        .iter()
+        .map(|(name, schema)| {
+            json!({
+                "name": name,
+                "in": "query",
+                "required": object.required.contains(name),
+                "schema": schema,
+            })
+        })
+        .collect()
+}
+
+fn json<T: JsonSchema>() -> Body {
+    Body::Json(schema::<T>)
+}
+
+// 与 ApiRoutes 中的路由一一对应
+fn operations() -> Vec<Operation> {
+    vec![
+        op("POST", "/matter", "create_matter", "创建 matter")
+            .request(json::<Matter>())
+            .response(json::<Matter>()),
+        op("GET", "/matter", "get_all_matters", "获取全部 matter")
+            .response(json::<Vec<MatterListItem>>()),
+        op("GET", "/matter/:id", "get_matter", "获取 matter").response(json::<Matter>()),
+        op("PUT", "/matter/:id", "update_matter", "更新 matter")
+            .request(json::<Matter>())
+            .response(json::<Matter>()),
+        op(
+            "PATCH",
+            "/matter/:id",
+            "patch_matter",
+            "修改 matter 的部分字段",
+        )
+        .request(Body::MergePatch)
+        .response(json::<Matter>()),
+        op("DELETE", "/matter/:id", "delete_matter", "删除 matter"),
+        op(
+            "GET",
+            "/matter/range",
+            "get_matters_by_range",
+            "按时间范围获取 matter",
+        )
+        .query(query::<TimeRangeQuery>)
+        .response(json::<Vec<MatterListItem>>()),
+        op(
+            "GET",
+            "/matter/query",
+            "query_matter_by_field",
+            "按字段查询 matter",
+        )
+        .query(query::<QueryFieldParams>)
+        .response(json::<Vec<MatterListItem>>()),
+        op("GET", "/kv/:key", "get_kv", "读取设置").response(json::<String>()),
+        op("PUT", "/kv/:key", "set_kv", "写入设置，请求体为原始文本").request(Body::Text),
+        op("DELETE", "/kv/:key", "delete_kv", "删除设置"),
+        op("POST", "/tags", "create_tag", "创建标签，names 以逗号分隔")
+            .request(json::<TagsRequest>()),
+        op("GET", "/tags", "get_all_tags", "获取全部标签").response(json::<Vec<Tag>>()),
+        op("DELETE", "/tags/:name", "delete_tag", "删除标签"),
+        op(
+            "PUT",
+            "/tags/update/:name",
+            "update_tag_last_used_at",
+            "更新标签最后使用时间",
+        ),
+        op("POST", "/repeat-task", "create_repeat_task", "创建重复任务")
+            .request(json::<RepeatTask>())
+            .response(json::<RepeatTask>()),
+        op(
+            "GET",
+            "/repeat-task",
+            "get_all_repeat_tasks",
+            "获取全部重复任务",
+        )
+        .response(json::<Vec<RepeatTask>>()),
+        op(
+            "GET",
+            "/repeat-task/active",
+            "get_active_repeat_tasks",
+            "获取启用的重复任务",
+        )
+        .response(json::<Vec<RepeatTask>>()),
+        op("GET", "/repeat-task/:id", "get_repeat_task", "获取重复任务")
+            .response(json::<RepeatTask>()),
+        op(
+            "PUT",
+            "/repeat-task/:id",
+            "update_repeat_task",
+            "更新重复任务",
+        )
+        .request(json::<RepeatTask>())
+        .response(json::<RepeatTask>()),
+        op(
+            "PATCH",
+            "/repeat-task/:id",
+            "patch_repeat_task",
+            "修改重复任务的部分字段",
+        )
+        .request(Body::MergePatch)
+        .response(json::<RepeatTask>()),
+        op(
+            "DELETE",
+            "/repeat-task/:id",
+            "delete_repeat_task",
+            "删除重复任务",
+        ),
+        op(
+            "PUT",
+            "/repeat-task/:id/status/:status",
+            "update_repeat_task_status",
+            "修改重复任务状态（1 启用，0 停止，-1 归档）",
+        ),
+        op(
+            "GET",
+            "/repeat-task/:id/occurrences",
+            "get_repeat_task_occurrences",
+            "预览重复任务接下来的发生时间",
+        )
+        .query(query::<OccurrenceQuery>)
+        .response(json::<RecurrencePreview>()),
+        op(
+            "GET",
+            "/repeat-task/:id/materialized",
+            "get_materialized_occurrences",
+            "获取重复任务已生成的 matter 及其日期",
+        )
+        .response(json::<Vec<RepeatOccurrence>>()),
+        op(
+            "POST",
+            "/repeat-task/:id/matter",
+            "create_repeat_task_matter",
+            "立即为重复任务生成今天的 matter",
+        )
+        .response(json::<Matter>()),
+        op(
+            "POST",
+            "/recurrence/preview",
+            "preview_recurrence",
+            "预览重复规则",
+        )
+        .request(json::<RecurrencePreviewRequest>())
+        .response(json::<RecurrencePreview>()),
+        op(
+            "GET",
+            "/holidays",
+            "get_holidays",
+            "获取某年的节假日和调休上班日",
+        )
+        .query(query::<HolidayQuery>)
+        .response(json::<Vec<HolidayEntry>>()),
+        op("GET", "/digest", "get_digest", "预览某天的每日简报")
+            .query(query::<DigestQuery>)
+            .response(json::<DigestPreview>()),
+        op("POST", "/todo", "create_todo", "创建 todo")
+            .request(json::<Todo>())
+            .response(json::<Todo>()),
+        op("GET", "/todo", "get_all_todos", "按条件获取 todo")
+            .query(query::<TodoFilter>)
+            .response(json::<Vec<Todo>>()),
+        op(
+            "PUT",
+            "/todo/reorder",
+            "reorder_todos",
+            "按给定顺序重新排序 todo",
+        )
+        .request(json::<ReorderRequest>()),
+        op("GET", "/todo/:id", "get_todo", "获取 todo").response(json::<Todo>()),
+        op("PUT", "/todo/:id", "update_todo", "更新 todo")
+            .request(json::<Todo>())
+            .response(json::<Todo>()),
+        op("PATCH", "/todo/:id", "patch_todo", "修改 todo 的部分字段")
+            .request(Body::MergePatch)
+            .response(json::<Todo>()),
+        op("DELETE", "/todo/:id", "delete_todo", "删除 todo"),
+        op(
+            "GET",
+            "/matter/:id/comments",
+            "get_matter_comments",
+            "获取 matter 的评论",
+        )
+        .response(json::<Vec<MatterComment>>()),
+        op(
+            "POST",
+            "/matter/:id/comments",
+            "create_matter_comment",
+            "添加评论",
+        )
+        .request(json::<CommentRequest>())
+        .response(json::<MatterComment>()),
+        op("GET", "/comment/:id", "get_matter_comment", "获取评论")
+            .response(json::<MatterComment>()),
+        op("PUT", "/comment/:id", "update_matter_comment", "修改评论")
+            .request(json::<CommentRequest>())
+            .response(json::<MatterComment>()),
+        op(
+            "PATCH",
+            "/comment/:id",
+            "patch_matter_comment",
+            "修改评论内容",
+        )
+        .request(Body::MergePatch)
+        .response(json::<MatterComment>()),
+        op(
+            "DELETE",
+            "/comment/:id",
+            "delete_matter_comment",
+            "删除评论",
+        ),
+        op(
+            "GET",
+            "/matter/:id/reminders",
+            "get_matter_reminders",
+            "获取 matter 的提醒",
+        )
+        .response(json::<Vec<MatterReminder>>()),
+        op(
+            "POST",
+            "/matter/:id/reminders",
+            "create_matter_reminder",
+            "为 matter 添加提醒",
+        )
+        .request(json::<MatterReminder>())
+        .response(json::<MatterReminder>()),
+        op(
+            "GET",
+            "/repeat-task/:id/reminders",
+            "get_repeat_task_reminders",
+            "获取重复任务的提醒",
+        )
+        .response(json::<Vec<MatterReminder>>()),
+        op(
+            "POST",
+            "/repeat-task/:id/reminders",
+            "create_repeat_task_reminder",
+            "为重复任务添加提醒",
+        )
+        .request(json::<MatterReminder>())
+        .response(json::<MatterReminder>()),
+        op("GET", "/reminder/:id", "get_matter_reminder", "获取提醒")
+            .response(json::<MatterReminder>()),
+        op("PUT", "/reminder/:id", "update_matter_reminder", "修改提醒")
+            .request(json::<MatterReminder>())
+            .response(json::<MatterReminder>()),
+        op(
+            "PATCH",
+            "/reminder/:id",
+            "patch_matter_reminder",
+            "修改提醒的部分字段",
+        )
+        .request(Body::MergePatch)
+        .response(json::<MatterReminder>()),
+        op(
+            "DELETE",
+            "/reminder/:id",
+            "delete_matter_reminder",
+            "删除提醒",
+        ),
+        op(
+            "GET",
+            "/matter/:id/attachments",
+            "get_matter_attachments",
+            "获取 matter 的附件",
+        )
+        .response(json::<Vec<Attachment>>()),
+        op(
+            "POST",
+            "/matter/:id/attachments",
+            "upload_matter_attachment",
+            "上传 matter 附件，请求体为文件内容",
+        )
+        .query(query::<UploadQuery>)
+        .request(Body::Binary)
+        .response(json::<Attachment>()),
+        op(
+            "GET",
+            "/todo/:id/attachments",
+            "get_todo_attachments",
+            "获取 todo 的附件",
+        )
+        .response(json::<Vec<Attachment>>()),
+        op(
+            "POST",
+            "/todo/:id/attachments",
+            "upload_todo_attachment",
+            "上传 todo 附件，请求体为文件内容",
+        )
+        .query(query::<UploadQuery>)
+        .request(Body::Binary)
+        .response(json::<Attachment>()),
+        op("GET", "/attachment/:id", "get_attachment", "获取附件信息")
+            .response(json::<Attachment>()),
+        op("DELETE", "/attachment/:id", "delete_attachment", "删除附件"),
+        op(
+            "GET",
+            "/attachment/:id/content",
+            "download_attachment",
+            "下载附件内容",
+        )
+        .response(Body::Binary),
+        op("POST", "/notification", "create_notification", "创建通知")
+            .request(json::<NotificationRecord>())
+            .response(json::<NotificationRecord>()),
+        op(
+            "GET",
+            "/notification/unread",
+            "get_unread_notifications",
+            "获取未读通知",
+        )
+        .response(json::<Vec<NotificationRecord>>()),
+        op(
+            "PUT",
+            "/notification/read-all",
+            "mark_all_notifications_as_read",
+            "全部标记为已读",
+        ),
+        op(
+            "PUT",
+            "/notification/read/:type",
+            "mark_notification_as_read_by_type",
+            "把某一类通知标记为已读",
+        ),
+        op("GET", "/notification/:id", "get_notification", "获取通知")
+            .response(json::<NotificationRecord>()),
+        op(
+            "PUT",
+            "/notification/:id",
+            "update_notification",
+            "更新通知",
+        )
+        .request(json::<NotificationRecord>())
+        .response(json::<NotificationRecord>()),
+        op(
+            "PATCH",
+            "/notification/:id",
+            "patch_notification",
+            "修改通知的部分字段",
+        )
+        .request(Body::MergePatch)
+        .response(json::<NotificationRecord>()),
+        op(
+            "DELETE",
+            "/notification/:id",
+            "delete_notification",
+            "删除通知及其投递记录",
+        ),
+        op(
+            "PUT",
+            "/notification/:id/read",
+            "mark_notification_as_read",
+            "标记为已读",
+        ),
+        op(
+            "PUT",
+            "/notification/:id/snooze",
+            "snooze_notification",
+            "稍后提醒，默认 10 分钟",
+        )
+        .request(json::<SnoozeRequest>())
+        .response(json::<NotificationRecord>()),
+        op(
+            "PUT",
+            "/notification/:id/acknowledge",
+            "acknowledge_notification",
+            "确认通知",
+        )
+        .response(json::<NotificationRecord>()),
+        op(
+            "PUT",
+            "/notification/:id/dismiss",
+            "dismiss_notification",
+            "忽略通知",
+        )
+        .response(json::<NotificationRecord>()),
+        op(
+            "GET",
+            "/notification/:id/deliveries",
+            "get_notification_deliveries",
+            "获取通知在各渠道的投递记录",
+        )
+        .response(json::<Vec<NotificationDelivery>>()),
+        op(
+            "GET",
+            "/notification-channels",
+            "get_notification_channels",
+            "获取通知渠道",
+        )
+        .response(json::<Vec<ChannelConfig>>()),
+        op(
+            "PUT",
+            "/notification-channels",
+            "set_notification_channels",
+            "保存通知渠道，未提供的密钥沿用已保存的值",
+        )
+        .request(json::<Vec<ChannelConfig>>())
+        .response(json::<Vec<ChannelConfig>>()),
+        op("GET", "/webhooks", "get_all_webhooks", "获取全部 webhook")
+            .response(json::<Vec<Webhook>>()),
+        op(
+            "POST",
+            "/webhooks",
+            "create_webhook",
+            "创建 webhook，secret 为空时自动生成，只在创建时返回",
+        )
+        .request(json::<Webhook>())
+        .response(json::<CreatedWebhook>()),
+        op("GET", "/webhooks/:id", "get_webhook", "获取 webhook").response(json::<Webhook>()),
+        op(
+            "PUT",
+            "/webhooks/:id",
+            "update_webhook",
+            "更新 webhook，secret 为空时保留原来的密钥",
+        )
+        .request(json::<Webhook>())
+        .response(json::<Webhook>()),
+        op(
+            "PATCH",
+            "/webhooks/:id",
+            "patch_webhook",
+            "修改 webhook 的部分字段，没有给出 secret 时保留原来的密钥",
+        )
+        .request(Body::MergePatch)
+        .response(json::<Webhook>()),
+        op(
+            "DELETE",
+            "/webhooks/:id",
+            "delete_webhook",
+            "删除 webhook 及其投递记录",
+        ),
+        op(
+            "GET",
+            "/webhooks/:id/deliveries",
+            "get_webhook_deliveries",
+            "获取 webhook 最近的投递记录",
+        )
+        .response(json::<Vec<WebhookDelivery>>()),
+        op(
+            "POST",
+            "/webhooks/:id/test",
+            "test_webhook",
+            "立即发送一条 webhook.test 测试事件",
+        )
+        .response(json::<WebhookDelivery>()),
+        op(
+            "GET",
+            "/incoming-hooks",
+            "get_all_incoming_hooks",
+            "获取全部入站 webhook",
+        )
+        .response(json::<Vec<IncomingHook>>()),
+        op(
+            "POST",
+            "/incoming-hooks",
+            "create_incoming_hook",
+            "创建入站 webhook，令牌只在创建时返回",
+        )
+        .request(json::<IncomingHook>())
+        .response(json::<CreatedHook>()),
+        op(
+            "GET",
+            "/incoming-hooks/:id",
+            "get_incoming_hook",
+            "获取入站 webhook",
+        )
+        .response(json::<IncomingHook>()),
+        op(
+            "PUT",
+            "/incoming-hooks/:id",
+            "update_incoming_hook",
+            "更新入站 webhook，令牌保持不变",
+        )
+        .request(json::<IncomingHook>())
+        .response(json::<IncomingHook>()),
+        op(
+            "PATCH",
+            "/incoming-hooks/:id",
+            "patch_incoming_hook",
+            "修改入站 webhook 的部分字段，令牌保持不变",
+        )
+        .request(Body::MergePatch)
+        .response(json::<IncomingHook>()),
+        op(
+            "DELETE",
+            "/incoming-hooks/:id",
+            "delete_incoming_hook",
+            "删除入站 webhook",
+        ),
+        op(
+            "POST",
+            "/hooks/:token",
+            "receive_hook",
+            "推送数据，按 mapping 创建 matter、todo 或通知；也接受表单",
+        )
+        .public()
+        .request(json::<Value>())
+        .response(json::<HookItem>()),
+        op("GET", "/events", "stream_events", "订阅数据变更事件（SSE）")
+            .query(query::<EventQuery>)
+            .response(Body::Events),
+        op(
+            "GET",
+            "/events/ws",
+            "events_websocket",
+            "订阅数据变更事件（WebSocket）",
+        )
+        .query(query::<EventQuery>)
+        .response(Body::WebSocket),
+    ]
+}
+
+// axum 的 /matter/:id 转成 OpenAPI 的 /matter/{id}
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn path_params(path: &str) -> Vec<Value> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix(':'))
+        .map(|name| {
+            let kind = match name {
+                "status" | "type" => "integer",
+                _ => "string",
+            };
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": kind },
+            })
+        })
+        .collect()
+}
+
+// 路径的第一段作为分组
+fn tag(path: &str) -> &str {
+    path.trim_start_matches('/')
+        .split('/')
+        .next()
+        .unwrap_or_default()
+}
+
+fn content(body: Body, gen: &mut SchemaGenerator) -> Option<Value> {
+    match body {
+        Body::Empty => None,
+        Body::Json(schema) => Some(json!({ "application/json": { "schema": schema(gen) } })),
+        Body::MergePatch => {
+            let schema = json!({ "type": "object", "description": "要修改的字段，null 表示清空" });
+            Some(json!({
+                MERGE_PATCH_CONTENT_TYPE: { "schema": schema },
+                "application/json": { "schema": schema },
+            }))
+        }
+        Body::Text => Some(json!({ "text/plain": { "schema": { "type": "string" } } })),
+        Body::Binary => Some(json!({
+            "application/octet-stream": { "schema": { "type": "string", "format": "binary" } }
+        })),
+        // 每条 change 事件的 data 是一个 ChangeEvent
+        Body::Events => {
+            Some(json!({ "text/event-stream": { "schema": schema::<ChangeEvent>(gen) } }))
+        }
+        Body::WebSocket => None,
+    }
+}
+
+// 除下载附件和事件推送外，所有响应都包在 ApiResponse 中
+fn response(body: Body, gen: &mut SchemaGenerator) -> Value {
+    let data = match body {
+        Body::Binary => {
+            return json!({ "description": "文件内容", "content": content(body, gen) });
+        }
+        Body::Events => {
+            return json!({
+                "description": "change 事件为一条变更，reset 事件表示有事件丢失，需要重新拉取数据",
+                "content": content(body, gen),
+            });
+        }
+        Body::WebSocket => {
+            return json!({
+                "description": "切换为 WebSocket，每条文本消息是一个 ChangeEvent 或 {\"type\":\"reset\"}",
+            });
+        }
+        Body::Json(schema) => schema(gen),
+        Body::Empty | Body::MergePatch | Body::Text => json!({ "nullable": true }),
+    };
+    json!({
+        "description": "code 为 200 时成功，否则 msg 为错误信息",
+        "content": {
+            "application/json": {
+                "schema": {
+                    "type": "object",
+                    "required": ["code", "msg"],
+                    "properties": {
+                        "code": { "type": "integer", "example": 200 },
+                        "msg": { "type": "string", "example": "success" },
+                        "data": data,
+                    },
+                },
+            },
+        },
+    })
+}
+
+fn operation(op: &Operation, gen: &mut SchemaGenerator) -> Value {
+    let mut parameters = path_params(op.path);
+    if let Some(query) = op.query {
+        parameters.extend(query(gen));
+    }
+    if matches!(op.request, Body::MergePatch) {
+        parameters.push(json!({
+            "name": "If-Match",
+            "in": "header",
+            "required": false,
+            "description": "GET 返回的 ETag，数据已被修改时返回 409",
+            "schema": { "type": "string" },
+        }));
+    }
+    let scope = if matches!(op.method, "GET" | "HEAD") {
+        "read"
+    } else {
+        "write"
+    };
+
+    let status = match op.response {
+        Body::WebSocket => "101",
+        _ => "200",
+    };
+
+    let mut value = json!({
+        "operationId": op.id,
+        "summary": op.summary,
+        "description": format!("需要 {} 权限的令牌", scope),
+        "tags": [tag(op.path)],
+        "responses": { status: response(op.response, gen) },
+    });
+    if op.public {
+        value["description"] = json!("不需要令牌");
+        value["security"] = json!([]);
+    }
+    if !parameters.is_empty() {
+        value["parameters"] = Value::Array(parameters);
+    }
+    if let Some(content) = content(op.request, gen) {
+        value["requestBody"] = json!({ "required": true, "content": content });
+    }
+    value
+}
+
+// base_url 为服务器当前的地址，未运行时省略 servers
+pub fn spec(base_url: Option<&str>) -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+    for op in operations() {
+        let item = paths
+            .entry(openapi_path(op.path))
+            .or_insert_with(|| json!({}));
+        item[op.method.to_ascii_lowercase()] = operation(&op, &mut gen);
+    }
+    // /v2 的错误响应
+    schema::<Problem>(&mut gen);
+
+    let mut spec = json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Fates HTTP API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "请求需要带 Authorization: Bearer <token>；read 令牌只能发 GET/HEAD 请求。\
+                除下载附件外，响应的 HTTP 状态码都是 200，结果和错误码在 ApiResponse 的 code 和 msg 中。\
+                所有接口也可以加 /v2 前缀访问：v2 直接返回 data（没有数据时为 204），错误使用真实的状态码和 \
+                application/problem+json（见 Problem），code 为稳定的错误码，title 和字段错误按 Accept-Language 返回中文或英文。\
+                写入数据前会校验字段：v1 返回 code 400，data 为 FieldError 列表；v2 返回 422，字段错误在 errors 中。\
+                v2 还要求 matter 的 end_time 不早于 start_time，v1 允许跨天的 matter。\
+                matter、重复任务、todo、通知、评论、提醒、webhook 和入站 webhook 的 GET/PUT/PATCH 响应带 ETag，PUT/PATCH 带 If-Match 时版本不一致返回 409，\
+                数据不存在返回 404。",
+        },
+        "paths": paths,
+        "components": {
+            "schemas": gen.definitions(),
+            "securitySchemes": {
+                "bearerAuth": { "type": "http", "scheme": "bearer" },
+            },
+        },
+        "security": [{ "bearerAuth": [] }],
+    });
+    if let Some(url) = base_url {
+        spec["servers"] = json!([{ "url": url }]);
+    }
+    spec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    // 从 ApiRoutes 的源码中取出 .route("路径", 方法(...)) 的方法和路径
+    fn api_routes() -> BTreeSet<(String, String)> {
+        let source = include_str!("http_server.rs");
+        let start = source.find("impl RouteConfig for ApiRoutes").unwrap();
+        let end = start + source[start..].find("\n}\n").unwrap();
+        let code: String = source[start..end]
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("//"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        code.split(".route(")
+            .skip(1)
+            .map(|route| {
+                let mut parts = route.splitn(3, '"');
+                parts.next();
+                let path = parts.next().unwrap();
+                let method: String = parts
+                    .next()
+                    .unwrap()
+                    .trim_start_matches(|c: char| c == ',' || c.is_whitespace())
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphabetic())
+                    .collect();
+                (method.to_ascii_uppercase(), path.to_string())
+            })
+            .collect()
+    }
+
+    // 文档本身的接口不出现在文档中
+    const UNDOCUMENTED: [&str; 2] = ["/openapi.json", "/docs"];
+
+    #[test]
+    fn every_route_is_documented() {
+        let mut routes = api_routes();
+        routes.retain(|(_, path)| !UNDOCUMENTED.contains(&path.as_str()));
+        assert!(routes.contains(&("PATCH".into(), "/comment/:id".into())));
+        let documented: BTreeSet<(String, String)> = operations()
+            .iter()
+            .map(|op| (op.method.to_string(), op.path.to_string()))
+            .collect();
+        let missing: Vec<_> = routes.difference(&documented).collect();
+        assert!(missing.is_empty(), "没有文档的路由：{:?}", missing);
+        let extra: Vec<_> = documented.difference(&routes).collect();
+        assert!(extra.is_empty(), "文档中多出的路由：{:?}", extra);
+    }
+
+    #[test]
+    fn operation_ids_are_unique() {
+        let ops = operations();
+        let ids: BTreeSet<&str> = ops.iter().map(|op| op.id).collect();
+        assert_eq!(ids.len(), ops.len());
+    }
+}
@@ -0,0 +1,135 @@
+// PATCH 局部更新和乐观并发控制。
+// 请求体按 RFC 7396 JSON merge patch 合并到当前数据：null 删除（对应字段恢复默认值），对象递归合并，其他值直接替换。
+// GET/PUT/PATCH 的响应带 ETag（由 updated_at 或内容摘要生成），写入时带 If-Match 且与当前版本不一致则拒绝
+
+use crate::database::{
+    IncomingHook, Matter, MatterComment, MatterReminder, NotificationRecord, RepeatTask, Todo,
+    Webhook,
+};
+use axum::http::{header, HeaderMap};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+pub trait Versioned {
+    // 不含引号的版本号
+    fn version(&self) -> String;
+
+    fn etag(&self) -> String {
+        format!("\"{}\"", self.version())
+    }
+}
+
+fn timestamp_version(updated_at: DateTime<Utc>) -> String {
+    updated_at
+        .timestamp_nanos_opt()
+        .unwrap_or_else(|| updated_at.timestamp_micros())
+        .to_string()
+}
+
+impl Versioned for Matter {
+    fn version(&self) -> String {
+        timestamp_version(self.updated_at)
+    }
+}
+
+impl Versioned for RepeatTask {
+    fn version(&self) -> String {
+        timestamp_version(self.updated_at)
+    }
+}
+
+impl Versioned for Todo {
+    fn version(&self) -> String {
+        timestamp_version(self.updated_at)
+    }
+}
+
+impl Versioned for MatterComment {
+    fn version(&self) -> String {
+        timestamp_version(self.updated_at)
+    }
+}
+
+impl Versioned for Webhook {
+    fn version(&self) -> String {
+        timestamp_version(self.updated_at)
+    }
+}
+
+// last_used_at 随推送更新，不影响版本
+impl Versioned for IncomingHook {
+    fn version(&self) -> String {
+        timestamp_version(self.updated_at)
+    }
+}
+
+fn digest_version(value: &impl Serialize) -> String {
+    let json = serde_json::to_vec(value).unwrap_or_default();
+    format!("{:x}", Sha256::digest(json))[..32].to_string()
+}
+
+// 通知没有 updated_at，状态也会被调度器修改，用整条记录的摘要作为版本号
+impl Versioned for NotificationRecord {
+    fn version(&self) -> String {
+        digest_version(self)
+    }
+}
+
+// 提醒没有 updated_at，同样用摘要
+impl Versioned for MatterReminder {
+    fn version(&self) -> String {
+        digest_version(self)
+    }
+}
+
+// 没有 If-Match 时不检查；* 匹配任何已存在的数据。按强比较，W/ 开头的弱 ETag 不匹配
+pub fn if_match(headers: &HeaderMap, current: &impl Versioned) -> bool {
+    let values: Vec<&str> = headers
+        .get_all(header::IF_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .collect();
+    if values.is_empty() {
+        return true;
+    }
+    let etag = current.etag();
+    values.iter().any(|v| *v == "*" || *v == etag)
+}
+
+pub fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let Value::Object(target) = target else {
+        return;
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+// 把 patch 合并到 current 的 JSON 表示上再转换回来；patch 必须是对象
+pub fn apply<T: Serialize + DeserializeOwned>(current: &T, patch: &Value) -> Result<T, String> {
+    if !patch.is_object() {
+        return Err("merge patch must be a JSON object".to_string());
+    }
+    let mut value = serde_json::to_value(current).map_err(|e| e.to_string())?;
+    merge(&mut value, patch);
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
@@ -0,0 +1,307 @@
+// /v2 API：与 v1 共用路由和处理函数，只改写响应。
+// 错误返回真实的 HTTP 状态码和 RFC 7807 application/problem+json，code 是稳定的机器可读错误码，
+// title 和字段错误按 Accept-Language 本地化（zh / en，默认 zh），detail 为具体原因；
+// 成功时直接返回数据，不再包一层 ApiResponse，没有数据时返回 204。v1 保持原样供现有前端使用
+
+use crate::validation::{FieldError, Issue, ValidationError};
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::response::Parts;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+
+pub const V2_PREFIX: &str = "/v2";
+
+const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+const TYPE_PREFIX: &str = "urn:fates:problem:";
+
+// 稳定的错误码，客户端应依据它而不是 title/detail 判断错误类型
+pub const INVALID_REQUEST: &str = "invalid_request";
+pub const UNAUTHORIZED: &str = "unauthorized";
+pub const FORBIDDEN: &str = "forbidden";
+pub const VALIDATION_FAILED: &str = "validation_failed";
+pub const NOT_FOUND: &str = "not_found";
+pub const CONFLICT: &str = "conflict";
+pub const METHOD_NOT_ALLOWED: &str = "method_not_allowed";
+pub const PAYLOAD_TOO_LARGE: &str = "payload_too_large";
+pub const UNSUPPORTED_MEDIA_TYPE: &str = "unsupported_media_type";
+pub const STORAGE_ERROR: &str = "storage_error";
+pub const INTERNAL_ERROR: &str = "internal_error";
+const HTTP_ERROR: &str = "http_error";
+
+// ServerError 转换成响应时附带，供 v2 生成 problem+json
+#[derive(Debug, Clone)]
+pub struct ErrorDetail {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub detail: String,
+    // 数据校验失败时按协商的语言生成 detail 和 errors
+    pub validation: Option<ValidationError>,
+}
+
+// v2_responses 附带在 /v2 下的请求上，处理函数据此启用只对 v2 生效的规则
+#[derive(Debug, Clone, Copy)]
+pub struct V2Request;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: String,
+    // 出错的请求路径
+    pub instance: String,
+    // 数据校验失败时每个字段的错误
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Zh,
+    En,
+}
+
+impl Lang {
+    fn tag(self) -> &'static str {
+        match self {
+            Lang::Zh => "zh-CN",
+            Lang::En => "en",
+        }
+    }
+}
+
+// 按 q 值选出第一个支持的语言
+fn negotiate(headers: &HeaderMap) -> Lang {
+    let Some(value) = headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return Lang::Zh;
+    };
+    let mut ranges: Vec<(&str, f32)> = value
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let range = parts.next()?.trim();
+            let q = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            Some((range, q))
+        })
+        .filter(|(_, q)| *q > 0.0)
+        .collect();
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranges
+        .iter()
+        .find_map(|(range, _)| {
+            let primary = range.split('-').next()?.to_ascii_lowercase();
+            match primary.as_str() {
+                "zh" => Some(Lang::Zh),
+                "en" => Some(Lang::En),
+                _ => None,
+            }
+        })
+        .unwrap_or(Lang::Zh)
+}
+
+fn title(code: &str, lang: Lang) -> &'static str {
+    let (zh, en) = match code {
+        INVALID_REQUEST => ("无效请求", "Invalid request"),
+        VALIDATION_FAILED => ("数据校验失败", "Validation failed"),
+        UNAUTHORIZED => ("未授权", "Unauthorized"),
+        FORBIDDEN => ("无权访问", "Forbidden"),
+        NOT_FOUND => ("未找到资源", "Resource not found"),
+        CONFLICT => ("数据已被修改", "Resource was modified"),
+        METHOD_NOT_ALLOWED => ("不支持该请求方法", "Method not allowed"),
+        PAYLOAD_TOO_LARGE => ("请求体过大", "Payload too large"),
+        UNSUPPORTED_MEDIA_TYPE => ("不支持的内容类型", "Unsupported media type"),
+        STORAGE_ERROR => ("存储错误", "Storage error"),
+        INTERNAL_ERROR => ("服务器内部错误", "Internal server error"),
+        _ => ("请求失败", "Request failed"),
+    };
+    match lang {
+        Lang::Zh => zh,
+        Lang::En => en,
+    }
+}
+
+pub fn field_message(issue: &Issue, lang: Lang) -> String {
+    let (zh, en) = match issue {
+        Issue::Required => ("不能为空".to_string(), "must not be empty".to_string()),
+        Issue::TooLong(max) => (
+            format!("不能超过 {} 个字符", max),
+            format!("must be at most {} characters", max),
+        ),
+        Issue::Before(field) => (
+            format!("不能早于 {}", field),
+            format!("must not be earlier than {}", field),
+        ),
+        Issue::InvalidType(value) => (
+            format!("无效的类型：{}", value),
+            format!("invalid type: {}", value),
+        ),
+        Issue::InvalidStatus(value) => (
+            format!("无效的状态：{}", value),
+            format!("invalid status: {}", value),
+        ),
+        Issue::OneOf(values) => (
+            format!("只能是 {}", values.join(" / ")),
+            format!("must be one of {}", values.join(" / ")),
+        ),
+        Issue::InvalidRecurrence(detail) => (
+            format!("无效的重复规则：{}", detail),
+            format!("invalid recurrence rule: {}", detail),
+        ),
+        Issue::InvalidLegacy(detail) => (
+            format!("无效的 repeat_time：{}", detail),
+            format!("invalid repeat_time: {}", detail),
+        ),
+        Issue::InvalidTimeZone(detail) => (
+            format!("无效的时区：{}", detail),
+            format!("invalid time zone: {}", detail),
+        ),
+    };
+    match lang {
+        Lang::Zh => zh,
+        Lang::En => en,
+    }
+}
+
+// axum 提取器拒绝请求、路由不存在等情况没有 ErrorDetail，按状态码归类
+fn code_for_status(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => INVALID_REQUEST,
+        StatusCode::UNAUTHORIZED => UNAUTHORIZED,
+        StatusCode::FORBIDDEN => FORBIDDEN,
+        StatusCode::NOT_FOUND => NOT_FOUND,
+        StatusCode::CONFLICT => CONFLICT,
+        StatusCode::METHOD_NOT_ALLOWED => METHOD_NOT_ALLOWED,
+        StatusCode::PAYLOAD_TOO_LARGE => PAYLOAD_TOO_LARGE,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => UNSUPPORTED_MEDIA_TYPE,
+        s if s.is_server_error() => INTERNAL_ERROR,
+        _ => HTTP_ERROR,
+    }
+}
+
+// 保留原响应的其他头（例如 CORS），只替换状态码和响应体
+fn problem_response(
+    mut parts: Parts,
+    error: ErrorDetail,
+    lang: Lang,
+    instance: String,
+) -> Response {
+    let (detail, errors) = match &error.validation {
+        Some(e) => (e.summary(lang), e.field_errors(lang)),
+        None => (error.detail, Vec::new()),
+    };
+    let problem = Problem {
+        type_: format!("{}{}", TYPE_PREFIX, error.code),
+        title: title(error.code, lang).to_string(),
+        status: error.status.as_u16(),
+        detail,
+        code: error.code.to_string(),
+        instance,
+        errors,
+    };
+    let body = serde_json::to_vec(&problem).unwrap_or_default();
+    parts.status = error.status;
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(PROBLEM_CONTENT_TYPE),
+    );
+    parts.headers.insert(
+        header::CONTENT_LANGUAGE,
+        HeaderValue::from_static(lang.tag()),
+    );
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+// 成功的 ApiResponse 只保留 data
+async fn unwrap_envelope(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::error!("Failed to read response body: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let envelope = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|v| match v {
+            Value::Object(mut map) if map.contains_key("code") && map.contains_key("msg") => {
+                Some(map.remove("data").unwrap_or(Value::Null))
+            }
+            _ => None,
+        });
+    parts.headers.remove(header::CONTENT_LENGTH);
+    match envelope {
+        Some(Value::Null) => {
+            parts.status = StatusCode::NO_CONTENT;
+            parts.headers.remove(header::CONTENT_TYPE);
+            Response::from_parts(parts, Body::empty())
+        }
+        Some(data) => Response::from_parts(parts, Body::from(data.to_string())),
+        None => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
+async fn error_detail(status: StatusCode, body: Body) -> ErrorDetail {
+    let detail = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).trim().to_string(),
+        Err(_) => String::new(),
+    };
+    ErrorDetail {
+        status,
+        code: code_for_status(status),
+        detail: if detail.is_empty() {
+            status.canonical_reason().unwrap_or_default().to_string()
+        } else {
+            detail
+        },
+        validation: None,
+    }
+}
+
+// 放在最外层，连同 CORS 等中间件返回的错误一起改写 /v2 下的响应
+pub async fn v2_responses(mut request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    if path != V2_PREFIX && !path.starts_with(&format!("{}/", V2_PREFIX)) {
+        return next.run(request).await;
+    }
+    let lang = negotiate(request.headers());
+    request.extensions_mut().insert(V2Request);
+    let response = next.run(request).await;
+
+    let status = response.status();
+    let (mut parts, body) = response.into_parts();
+    if let Some(error) = parts.extensions.remove::<ErrorDetail>() {
+        return problem_response(parts, error, lang, path);
+    }
+    if status.is_client_error() || status.is_server_error() {
+        let error = error_detail(status, body).await;
+        return problem_response(parts, error, lang, path);
+    }
+    let response = Response::from_parts(parts, body);
+    if status == StatusCode::OK && is_json(&response) {
+        return unwrap_envelope(response).await;
+    }
+    response
+}
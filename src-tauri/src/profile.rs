@@ -0,0 +1,268 @@
+// 多 profile 支持：每个 profile 使用独立的数据库文件
+// default 沿用原来的 fates.db，其余位于 <app_data_dir>/profiles/<name>.db
+// 附件目录同理：default 为 attachments/，其余为 profiles/<name>.attachments/
+// HTTP API 的访问令牌、出站 webhook 和入站 hook 属于整个应用，统一保存在 default 的数据库中，
+// 切换 profile 后仍然有效；webhook 收到的是当前 profile 的事件，入站 hook 写入当前 profile
+
+use crate::database::{self, DB_NAME};
+use crate::events;
+use crate::http_server;
+use crate::repository::{self, Repositories};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Emitter, Manager};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const PROFILES_DIR: &str = "profiles";
+const PROFILES_CONFIG: &str = "profiles.json";
+const ATTACHMENTS_DIR: &str = "attachments";
+const PROFILE_CHANGED_EVENT: &str = "profile-changed";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub path: String,
+    pub active: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProfilesConfig {
+    active: Option<String>,
+}
+
+pub struct ProfileManager {
+    app_dir: PathBuf,
+    in_memory: bool,
+    active: Mutex<String>,
+    // default 的存储，第一次打开任意 profile 时打开，之后一直复用
+    shared: Mutex<Option<Repositories>>,
+}
+
+impl ProfileManager {
+    pub fn new(app_dir: PathBuf, in_memory: bool) -> Self {
+        let config = fs::read_to_string(app_dir.join(PROFILES_CONFIG))
+            .ok()
+            .and_then(|content| serde_json::from_str::<ProfilesConfig>(&content).ok())
+            .unwrap_or_default();
+
+        let mut active = config.active.unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+        let manager = Self {
+            app_dir,
+            in_memory,
+            active: Mutex::new(DEFAULT_PROFILE.to_string()),
+            shared: Mutex::new(None),
+        };
+        // 上次使用的 profile 已被删除时回退到 default
+        if !manager.exists(&active) {
+            log::warn!("Profile {} not found, falling back to default", active);
+            active = DEFAULT_PROFILE.to_string();
+        }
+        *manager.active.lock().unwrap() = active;
+        manager
+    }
+
+    pub fn active(&self) -> String {
+        self.active.lock().unwrap().clone()
+    }
+
+    fn db_path(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_PROFILE {
+            self.app_dir.join(DB_NAME)
+        } else {
+            self.app_dir.join(PROFILES_DIR).join(format!("{}.db", name))
+        }
+    }
+
+    fn attachments_dir(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_PROFILE {
+            self.app_dir.join(ATTACHMENTS_DIR)
+        } else {
+            self.app_dir
+                .join(PROFILES_DIR)
+                .join(format!("{}.{}", name, ATTACHMENTS_DIR))
+        }
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        name == DEFAULT_PROFILE || (is_valid_name(name) && self.db_path(name).exists())
+    }
+
+    fn info(&self, name: &str, active: &str) -> ProfileInfo {
+        ProfileInfo {
+            name: name.to_string(),
+            path: self.db_path(name).to_string_lossy().to_string(),
+            active: name == active,
+        }
+    }
+
+    fn save_config(&self, active: &str) -> Result<(), String> {
+        let config = ProfilesConfig {
+            active: Some(active.to_string()),
+        };
+        let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+        fs::write(self.app_dir.join(PROFILES_CONFIG), content).map_err(|e| e.to_string())
+    }
+
+    fn open_storage(&self, name: &str) -> Result<Repositories, String> {
+        if self.in_memory {
+            return Ok(Repositories::in_memory());
+        }
+        let db = database::open_database(&self.db_path(name)).map_err(|e| e.to_string())?;
+        Ok(Repositories::sqlite(db, self.attachments_dir(name)))
+    }
+
+    fn shared(&self) -> Result<Repositories, String> {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(storage) = shared.as_ref() {
+            return Ok(storage.clone());
+        }
+        let storage = self.open_storage(DEFAULT_PROFILE)?;
+        *shared = Some(storage.clone());
+        Ok(storage)
+    }
+
+    // 打开 profile 对应的存储，令牌和 hook 使用 default 中共用的仓库
+    pub fn open(&self, name: &str) -> Result<Repositories, String> {
+        let shared = self.shared()?;
+        if name == DEFAULT_PROFILE {
+            return Ok(shared);
+        }
+        let storage = self.open_storage(name)?;
+        move_integrations(&storage, &shared).map_err(|e| e.to_string())?;
+        Ok(storage.share_integrations(&shared))
+    }
+
+    pub fn open_active(&self) -> Result<Repositories, String> {
+        self.open(&self.active())
+    }
+
+    pub fn list(&self) -> Result<Vec<ProfileInfo>, String> {
+        let active = self.active();
+        let mut names = vec![DEFAULT_PROFILE.to_string()];
+
+        let dir = self.app_dir.join(PROFILES_DIR);
+        if dir.exists() {
+            let mut others: Vec<String> = fs::read_dir(&dir)
+                .map_err(|e| e.to_string())?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "db"))
+                .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+                .filter(|name| name != DEFAULT_PROFILE && is_valid_name(name))
+                .collect();
+            others.sort();
+            names.extend(others);
+        }
+
+        Ok(names.iter().map(|name| self.info(name, &active)).collect())
+    }
+
+    pub fn create(&self, name: &str) -> Result<ProfileInfo, String> {
+        if !is_valid_name(name) {
+            return Err(format!("无效的 profile 名称：{}", name));
+        }
+        if self.exists(name) {
+            return Err(format!("Profile 已存在：{}", name));
+        }
+        fs::create_dir_all(self.app_dir.join(PROFILES_DIR)).map_err(|e| e.to_string())?;
+        // 打开一次即可完成建表
+        database::open_database(&self.db_path(name)).map_err(|e| e.to_string())?;
+        log::info!("Profile created: {}", name);
+        Ok(self.info(name, &self.active()))
+    }
+
+    pub async fn switch(&self, name: &str) -> Result<ProfileInfo, String> {
+        if !self.exists(name) {
+            return Err(format!("Profile 不存在：{}", name));
+        }
+        let storage = self.open(name)?;
+        http_server::replace_http_server_storage(storage)
+            .await
+            .map_err(|e| e.to_string())?;
+        // 已连接的事件流属于之前的 profile
+        events::reset();
+
+        *self.active.lock().unwrap() = name.to_string();
+        self.save_config(name)?;
+        log::info!("Switched to profile: {}", name);
+        Ok(self.info(name, name))
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        if name == DEFAULT_PROFILE {
+            return Err("不能删除默认 profile".into());
+        }
+        if name == self.active() {
+            return Err("不能删除当前正在使用的 profile".into());
+        }
+        if !self.exists(name) {
+            return Err(format!("Profile 不存在：{}", name));
+        }
+        let path = self.db_path(name);
+        fs::remove_file(&path).map_err(|e| e.to_string())?;
+        for suffix in ["-wal", "-shm"] {
+            let mut sidecar = path.clone().into_os_string();
+            sidecar.push(suffix);
+            let _ = fs::remove_file(sidecar);
+        }
+        let attachments_dir = self.attachments_dir(name);
+        if attachments_dir.exists() {
+            fs::remove_dir_all(attachments_dir).map_err(|e| e.to_string())?;
+        }
+        log::info!("Profile deleted: {}", name);
+        Ok(())
+    }
+}
+
+// 之前的版本把令牌和 hook 保存在各自 profile 的数据库中，打开时移到共用的存储
+fn move_integrations(from: &Repositories, to: &Repositories) -> repository::Result<()> {
+    for token in from.api_tokens.get_all()? {
+        to.api_tokens.create(&token)?;
+        from.api_tokens.delete(&token.id)?;
+    }
+    for webhook in from.webhooks.get_all()? {
+        to.webhooks.create(&webhook)?;
+        from.webhooks.delete(&webhook.id)?;
+    }
+    for hook in from.incoming_hooks.get_all()? {
+        to.incoming_hooks.create(&hook)?;
+        from.incoming_hooks.delete(&hook.id)?;
+    }
+    Ok(())
+}
+
+// 只允许字母、数字、下划线和短横线，避免路径穿越
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[command]
+pub async fn list_profiles(app: AppHandle) -> Result<Vec<ProfileInfo>, String> {
+    app.state::<ProfileManager>().list()
+}
+
+#[command]
+pub async fn create_profile(app: AppHandle, name: String) -> Result<ProfileInfo, String> {
+    app.state::<ProfileManager>().create(name.trim())
+}
+
+#[command]
+pub async fn switch_profile(app: AppHandle, name: String) -> Result<ProfileInfo, String> {
+    let info = app.state::<ProfileManager>().switch(name.trim()).await?;
+    if let Err(e) = app.emit(PROFILE_CHANGED_EVENT, &info) {
+        log::error!("Failed to emit profile change event: {}", e);
+    }
+    Ok(info)
+}
+
+#[command]
+pub async fn delete_profile(app: AppHandle, name: String) -> Result<(), String> {
+    app.state::<ProfileManager>().delete(name.trim())
+}
@@ -0,0 +1,1097 @@
+// 重复任务的重复规则，支持 RFC 5545 中的 FREQ、INTERVAL、BYDAY、BYMONTHDAY、BYMONTH、COUNT、UNTIL
+// 以文本形式保存在 repeat_task.recurrence 中，例如：
+//
+//   DTSTART;TZID=Asia/Shanghai:20240101T080000
+//   DURATION:PT2H
+//   RRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR
+//
+// DTSTART 同时决定每次发生的开始时刻。带 TZID 时按该时区的墙上时间展开，
+// 夏令时切换前后开始时刻保持不变；不带 TZID 时为本地时间（floating time），跟随系统时区
+
+use crate::database::RepeatTask;
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+// 旧格式 "127|08:00|10:00" 中第 7 位表示跳过节假日
+pub const LEGACY_SKIP_HOLIDAYS_BIT: u32 = 1 << 7;
+
+// 连续多少个周期都没有产生结果就停止展开，避免 BYMONTHDAY=31;BYMONTH=2 之类的规则死循环
+const MAX_EMPTY_PERIODS: u32 = 1000;
+
+// 单次发生的最长时长，超出的 DURATION 视为无效，也避免计算结束时间时溢出
+const MAX_DURATION_DAYS: i64 = 366;
+
+#[derive(Error, Debug)]
+pub enum RecurrenceError {
+    #[error("无效的重复规则：{0}")]
+    Invalid(String),
+    #[error("无效的 repeat_time：{0}")]
+    InvalidLegacy(String),
+    #[error("无效的时区：{0}")]
+    InvalidTimeZone(String),
+}
+
+fn invalid(msg: impl Into<String>) -> RecurrenceError {
+    RecurrenceError::Invalid(msg.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        }
+    }
+}
+
+// BYDAY 中的一项，nth 为空表示每个该星期几，否则为当月/当年的第 n 个（负数从末尾算）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByDay {
+    pub nth: Option<i32>,
+    pub weekday: Weekday,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Until {
+    Date(NaiveDate),
+    Local(NaiveDateTime),
+    Utc(NaiveDateTime),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RRule {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<Until>,
+    pub by_day: Vec<ByDay>,
+    pub by_month_day: Vec<i32>,
+    pub by_month: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub dtstart: NaiveDateTime,
+    pub duration: Duration,
+    pub rule: RRule,
+    // None 表示系统本地时区
+    pub tz: Option<Tz>,
+}
+
+// start/end 为所在时区的墙上时间
+#[derive(Debug, Clone, Copy)]
+pub struct Occurrence {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub tz: Option<Tz>,
+}
+
+impl Occurrence {
+    pub fn start_utc(&self) -> DateTime<Utc> {
+        zoned_to_utc(self.start, self.tz)
+    }
+
+    // DURATION 是精确时长，跨越夏令时切换时结束的墙上时间会相应变化
+    pub fn end_utc(&self) -> DateTime<Utc> {
+        self.start_utc() + (self.end - self.start)
+    }
+}
+
+pub fn parse_tz(name: &str) -> Result<Tz, RecurrenceError> {
+    name.trim()
+        .parse()
+        .map_err(|_| RecurrenceError::InvalidTimeZone(name.to_string()))
+}
+
+// 夏令时切换导致的不存在时刻取切换后的第一个有效时刻，重复的时刻取第一次
+fn resolve<Z: TimeZone>(zone: &Z, naive: NaiveDateTime) -> DateTime<Utc> {
+    match zone.from_local_datetime(&naive).earliest() {
+        Some(dt) => dt.with_timezone(&Utc),
+        None => resolve(zone, naive + Duration::hours(1)),
+    }
+}
+
+pub fn local_to_utc(naive: NaiveDateTime) -> DateTime<Utc> {
+    resolve(&Local, naive)
+}
+
+pub fn zoned_to_utc(naive: NaiveDateTime, tz: Option<Tz>) -> DateTime<Utc> {
+    match tz {
+        Some(tz) => resolve(&tz, naive),
+        None => local_to_utc(naive),
+    }
+}
+
+// UTC 时间在指定时区（默认本地）的墙上时间
+pub fn utc_to_zoned(dt: DateTime<Utc>, tz: Option<Tz>) -> NaiveDateTime {
+    match tz {
+        Some(tz) => dt.with_timezone(&tz).naive_local(),
+        None => dt.with_timezone(&Local).naive_local(),
+    }
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_weekday(code: &str) -> Option<Weekday> {
+    match code {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn parse_ical_datetime(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()
+}
+
+fn parse_duration(value: &str) -> Result<Duration, RecurrenceError> {
+    let err = || invalid(format!("DURATION {}", value));
+    let rest = value.strip_prefix('P').ok_or_else(err)?;
+    let (date_part, time_part) = match rest.split_once('T') {
+        Some((d, t)) => (d, t),
+        None => (rest, ""),
+    };
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for (part, is_time) in [(date_part, false), (time_part, true)] {
+        for c in part.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let n: i64 = number.parse().map_err(|_| err())?;
+            number.clear();
+            let part = match (c, is_time) {
+                ('W', false) => Duration::try_weeks(n),
+                ('D', false) => Duration::try_days(n),
+                ('H', true) => Duration::try_hours(n),
+                ('M', true) => Duration::try_minutes(n),
+                ('S', true) => Duration::try_seconds(n),
+                _ => return Err(err()),
+            };
+            total = part.and_then(|p| total.checked_add(&p)).ok_or_else(err)?;
+        }
+        if !number.is_empty() {
+            return Err(err());
+        }
+    }
+    if total > Duration::days(MAX_DURATION_DAYS) {
+        return Err(invalid(format!(
+            "DURATION 不能超过 {} 天",
+            MAX_DURATION_DAYS
+        )));
+    }
+    Ok(total)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    if minutes % 60 == 0 {
+        format!("PT{}H", minutes / 60)
+    } else {
+        format!("PT{}M", minutes)
+    }
+}
+
+// 超出 chrono 日期范围的月份返回 0
+fn days_in_month(year: i32, month: u32) -> u32 {
+    (28..=31)
+        .rev()
+        .find(|d| NaiveDate::from_ymd_opt(year, month, *d).is_some())
+        .unwrap_or(0)
+}
+
+fn add_months(year: i32, month: u32, months: i64) -> Option<(i32, u32)> {
+    let index = (year as i64 * 12 + (month as i64 - 1)).checked_add(months)?;
+    Some((
+        i32::try_from(index.div_euclid(12)).ok()?,
+        (index.rem_euclid(12) + 1) as u32,
+    ))
+}
+
+fn add_days(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    date.checked_add_signed(Duration::try_days(days)?)
+}
+
+impl FromStr for RRule {
+    type Err = RecurrenceError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().trim_start_matches("RRULE:");
+        let mut freq = None;
+        let mut rule = RRule {
+            freq: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+        };
+
+        for part in value.split(';').filter(|p| !p.is_empty()) {
+            let (key, val) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("缺少 '=': {}", part)))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match val.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => return Err(invalid(format!("不支持的 FREQ {}", other))),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = val
+                        .parse()
+                        .ok()
+                        .filter(|n| *n >= 1)
+                        .ok_or_else(|| invalid(format!("INTERVAL {}", val)))?
+                }
+                "COUNT" => {
+                    rule.count = Some(val.parse().map_err(|_| invalid(format!("COUNT {}", val)))?)
+                }
+                "UNTIL" => {
+                    rule.until = Some(if let Some(utc) = val.strip_suffix('Z') {
+                        Until::Utc(
+                            parse_ical_datetime(utc)
+                                .ok_or_else(|| invalid(format!("UNTIL {}", val)))?,
+                        )
+                    } else if val.contains('T') {
+                        Until::Local(
+                            parse_ical_datetime(val)
+                                .ok_or_else(|| invalid(format!("UNTIL {}", val)))?,
+                        )
+                    } else {
+                        Until::Date(
+                            NaiveDate::parse_from_str(val, "%Y%m%d")
+                                .map_err(|_| invalid(format!("UNTIL {}", val)))?,
+                        )
+                    })
+                }
+                "BYDAY" => {
+                    for item in val.split(',') {
+                        let item = item.trim().to_ascii_uppercase();
+                        if item.len() < 2 {
+                            return Err(invalid(format!("BYDAY {}", item)));
+                        }
+                        let (nth, code) = item.split_at(item.len() - 2);
+                        let weekday = parse_weekday(code)
+                            .ok_or_else(|| invalid(format!("BYDAY {}", item)))?;
+                        let nth = if nth.is_empty() {
+                            None
+                        } else {
+                            let n: i32 = nth
+                                .trim_start_matches('+')
+                                .parse()
+                                .map_err(|_| invalid(format!("BYDAY {}", item)))?;
+                            if n == 0 || n.abs() > 53 {
+                                return Err(invalid(format!("BYDAY {}", item)));
+                            }
+                            Some(n)
+                        };
+                        rule.by_day.push(ByDay { nth, weekday });
+                    }
+                }
+                "BYMONTHDAY" => {
+                    for item in val.split(',') {
+                        let n: i32 = item
+                            .trim()
+                            .trim_start_matches('+')
+                            .parse()
+                            .map_err(|_| invalid(format!("BYMONTHDAY {}", item)))?;
+                        if n == 0 || n.abs() > 31 {
+                            return Err(invalid(format!("BYMONTHDAY {}", item)));
+                        }
+                        rule.by_month_day.push(n);
+                    }
+                }
+                "BYMONTH" => {
+                    for item in val.split(',') {
+                        let n: u32 = item
+                            .trim()
+                            .parse()
+                            .ok()
+                            .filter(|n| (1..=12).contains(n))
+                            .ok_or_else(|| invalid(format!("BYMONTH {}", item)))?;
+                        rule.by_month.push(n);
+                    }
+                }
+                // 周从周一开始，其余取值暂不支持
+                "WKST" if val.eq_ignore_ascii_case("MO") => {}
+                other => return Err(invalid(format!("不支持的规则 {}", other))),
+            }
+        }
+
+        rule.freq = freq.ok_or_else(|| invalid("缺少 FREQ"))?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(invalid("COUNT 和 UNTIL 不能同时出现"));
+        }
+        if matches!(rule.freq, Frequency::Daily | Frequency::Weekly)
+            && rule.by_day.iter().any(|d| d.nth.is_some())
+        {
+            return Err(invalid("DAILY/WEEKLY 的 BYDAY 不能带序号"));
+        }
+        if rule.freq == Frequency::Weekly && !rule.by_month_day.is_empty() {
+            return Err(invalid("WEEKLY 不支持 BYMONTHDAY"));
+        }
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for RRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.freq.as_str())?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        match self.until {
+            Some(Until::Date(d)) => write!(f, ";UNTIL={}", d.format("%Y%m%d"))?,
+            Some(Until::Local(dt)) => write!(f, ";UNTIL={}", dt.format("%Y%m%dT%H%M%S"))?,
+            Some(Until::Utc(dt)) => write!(f, ";UNTIL={}Z", dt.format("%Y%m%dT%H%M%S"))?,
+            None => {}
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|d| match d.nth {
+                    Some(n) => format!("{}{}", n, weekday_code(d.weekday)),
+                    None => weekday_code(d.weekday).to_string(),
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(|d| d.to_string()).collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        if !self.by_month.is_empty() {
+            let months: Vec<String> = self.by_month.iter().map(|m| m.to_string()).collect();
+            write!(f, ";BYMONTH={}", months.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = RecurrenceError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut dtstart = None;
+        let mut tz = None;
+        let mut duration = None;
+        let mut rule = None;
+
+        for line in value.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let (name, val) = line
+                .split_once(':')
+                .ok_or_else(|| invalid(format!("无法识别的行 {}", line)))?;
+            // 目前只支持 DTSTART 的 TZID 参数
+            let mut params = name.split(';');
+            let name = params.next().unwrap_or_default().to_ascii_uppercase();
+            match name.as_str() {
+                "DTSTART" => {
+                    for param in params {
+                        match param.split_once('=') {
+                            Some((key, value)) if key.eq_ignore_ascii_case("TZID") => {
+                                tz = Some(parse_tz(value)?)
+                            }
+                            _ => return Err(invalid(format!("不支持的参数 {}", param))),
+                        }
+                    }
+                    dtstart = Some(
+                        parse_ical_datetime(val)
+                            .ok_or_else(|| invalid(format!("DTSTART {}", val)))?,
+                    )
+                }
+                "DURATION" => duration = Some(parse_duration(val)?),
+                "RRULE" => rule = Some(val.parse::<RRule>()?),
+                other => return Err(invalid(format!("不支持的属性 {}", other))),
+            }
+        }
+
+        let duration = duration.unwrap_or_else(Duration::zero);
+        if duration < Duration::zero() {
+            return Err(invalid("DURATION 不能为负数"));
+        }
+        Ok(Recurrence {
+            dtstart: dtstart.ok_or_else(|| invalid("缺少 DTSTART"))?,
+            duration,
+            rule: rule.ok_or_else(|| invalid("缺少 RRULE"))?,
+            tz,
+        })
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tz {
+            Some(tz) => writeln!(
+                f,
+                "DTSTART;TZID={}:{}",
+                tz.name(),
+                self.dtstart.format("%Y%m%dT%H%M%S")
+            )?,
+            None => writeln!(f, "DTSTART:{}", self.dtstart.format("%Y%m%dT%H%M%S"))?,
+        }
+        writeln!(f, "DURATION:{}", format_duration(self.duration))?;
+        write!(f, "RRULE:{}", self.rule)
+    }
+}
+
+fn parse_legacy_time(value: &str) -> Option<(u32, u32)> {
+    let (h, m) = value.trim().split_once(':')?;
+    let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+    if (h < 24 && m < 60) || (h == 24 && m == 0) {
+        Some((h, m))
+    } else {
+        None
+    }
+}
+
+impl Recurrence {
+    // 从旧格式 "127|08:00|10:00" 转换，返回值第二项表示是否跳过节假日
+    pub fn from_legacy(
+        repeat_time: &str,
+        start_date: NaiveDate,
+    ) -> Result<(Self, bool), RecurrenceError> {
+        let err = || RecurrenceError::InvalidLegacy(repeat_time.to_string());
+        let parts: Vec<&str> = repeat_time.split('|').collect();
+        if parts.len() != 3 {
+            return Err(err());
+        }
+        let bits: u32 = parts[0].trim().parse().map_err(|_| err())?;
+        let (start_h, start_m) = parse_legacy_time(parts[1]).ok_or_else(err)?;
+        let (end_h, end_m) = parse_legacy_time(parts[2]).ok_or_else(err)?;
+        if start_h == 24 {
+            return Err(err());
+        }
+
+        let start_minutes = (start_h * 60 + start_m) as i64;
+        let mut end_minutes = (end_h * 60 + end_m) as i64;
+        // 结束时间早于开始时间视为跨天
+        if end_minutes < start_minutes {
+            end_minutes += 24 * 60;
+        }
+
+        let weekdays = [
+            Weekday::Sun,
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+        ];
+        let by_day: Vec<ByDay> = weekdays
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| bits & (1 << i) != 0)
+            .map(|(_, weekday)| ByDay {
+                nth: None,
+                weekday: *weekday,
+            })
+            .collect();
+
+        let rule = RRule {
+            freq: Frequency::Weekly,
+            interval: 1,
+            // 没有选择任何一天时不会发生
+            count: if by_day.is_empty() { Some(0) } else { None },
+            until: None,
+            by_day,
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+        };
+        let recurrence = Recurrence {
+            dtstart: start_date.and_time(NaiveTime::from_hms_opt(start_h, start_m, 0).unwrap()),
+            duration: Duration::minutes(end_minutes - start_minutes),
+            rule,
+            tz: None,
+        };
+        Ok((recurrence, bits & LEGACY_SKIP_HOLIDAYS_BIT != 0))
+    }
+
+    // 转换回旧格式，只有每周按星期几重复且无结束条件的规则可以表示
+    pub fn to_legacy(&self, skip_holidays: bool) -> Option<String> {
+        let rule = &self.rule;
+        let simple_weekly = rule.freq == Frequency::Weekly
+            && rule.interval == 1
+            && rule.until.is_none()
+            && rule.by_month_day.is_empty()
+            && rule.by_month.is_empty()
+            && rule.by_day.iter().all(|d| d.nth.is_none());
+        if !simple_weekly {
+            return None;
+        }
+
+        let mut bits = match rule.count {
+            Some(0) => 0,
+            Some(_) => return None,
+            None if rule.by_day.is_empty() => 1 << self.dtstart.weekday().num_days_from_sunday(),
+            None => rule
+                .by_day
+                .iter()
+                .fold(0, |acc, d| acc | 1 << d.weekday.num_days_from_sunday()),
+        };
+        if skip_holidays {
+            bits |= LEGACY_SKIP_HOLIDAYS_BIT;
+        }
+        Some(format!("{}|{}", bits, self.legacy_time_window()?))
+    }
+
+    // 旧格式中的 "08:00|10:00" 部分，超过一天的时长无法表示
+    pub fn legacy_time_window(&self) -> Option<String> {
+        if self.duration > Duration::days(1) {
+            return None;
+        }
+        let start = self.dtstart.time();
+        let end_at = self.dtstart + self.duration;
+        let end = if end_at.date() > self.dtstart.date() && end_at.time() == NaiveTime::MIN {
+            "24:00".to_string()
+        } else {
+            end_at.time().format("%H:%M").to_string()
+        };
+        Some(format!("{}|{}", start.format("%H:%M"), end))
+    }
+
+    // 在 start（墙上时间）开始的一次发生
+    pub fn occurrence_at(&self, start: NaiveDateTime) -> Occurrence {
+        Occurrence {
+            start,
+            end: start + self.duration,
+            tz: self.tz,
+        }
+    }
+
+    // UTC 时刻在规则所在时区的墙上时间
+    pub fn to_zoned(&self, dt: DateTime<Utc>) -> NaiveDateTime {
+        utc_to_zoned(dt, self.tz)
+    }
+
+    pub fn occurrences(&self) -> Occurrences<'_> {
+        Occurrences {
+            recurrence: self,
+            period: 0,
+            empty_periods: 0,
+            emitted: 0,
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    // 开始时间不早于 after 的前 limit 次
+    pub fn next_occurrences(&self, after: NaiveDateTime, limit: usize) -> Vec<Occurrence> {
+        self.occurrences()
+            .filter(|o| o.start >= after)
+            .take(limit)
+            .collect()
+    }
+
+    // 与 [from, to) 有交集的所有发生
+    pub fn occurrences_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> Vec<Occurrence> {
+        self.occurrences()
+            .take_while(|o| o.start < to)
+            .filter(|o| o.end > from || o.start >= from)
+            .collect()
+    }
+
+    fn until_reached(&self, start: NaiveDateTime) -> bool {
+        match self.rule.until {
+            Some(Until::Date(d)) => start.date() > d,
+            Some(Until::Local(dt)) => start > dt,
+            Some(Until::Utc(dt)) => zoned_to_utc(start, self.tz).naive_utc() > dt,
+            None => false,
+        }
+    }
+
+    // 第 period 个周期内（按 FREQ/INTERVAL 划分）满足规则的日期，已排序；
+    // 周期超出 chrono 能表示的日期范围时返回 None
+    fn dates_in_period(&self, period: u32) -> Option<Vec<NaiveDate>> {
+        let rule = &self.rule;
+        let start = self.dtstart.date();
+        let step = (period as i64).checked_mul(rule.interval as i64)?;
+
+        let mut dates = match rule.freq {
+            Frequency::Daily => vec![add_days(start, step)?],
+            Frequency::Weekly => {
+                let monday = add_days(start, -(start.weekday().num_days_from_monday() as i64))?;
+                let week_start = add_days(monday, step.checked_mul(7)?)?;
+                let weekdays: Vec<Weekday> = if rule.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    rule.by_day.iter().map(|d| d.weekday).collect()
+                };
+                (0..7)
+                    .filter_map(|i| add_days(week_start, i))
+                    .filter(|d| weekdays.contains(&d.weekday()))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let (year, month) = add_months(start.year(), start.month(), step)?;
+                NaiveDate::from_ymd_opt(year, month, 1)?;
+                self.dates_in_month(year, month, true)
+            }
+            Frequency::Yearly => {
+                let year = start.year().checked_add(i32::try_from(step).ok()?)?;
+                NaiveDate::from_ymd_opt(year, 1, 1)?;
+                self.dates_in_year(year)
+            }
+        };
+
+        // BYxxx 作为限制条件
+        if !rule.by_month.is_empty() {
+            dates.retain(|d| rule.by_month.contains(&d.month()));
+        }
+        if matches!(rule.freq, Frequency::Daily) {
+            if !rule.by_month_day.is_empty() {
+                dates.retain(|d| matches_month_day(*d, &rule.by_month_day));
+            }
+            if !rule.by_day.is_empty() {
+                dates.retain(|d| rule.by_day.iter().any(|b| b.weekday == d.weekday()));
+            }
+        }
+        dates.sort();
+        dates.dedup();
+        Some(dates)
+    }
+
+    // 某个月内满足 BYMONTHDAY/BYDAY 的日期，都为空时 default_day 决定是否取 DTSTART 当天
+    fn dates_in_month(&self, year: i32, month: u32, default_day: bool) -> Vec<NaiveDate> {
+        let rule = &self.rule;
+        let last = days_in_month(year, month);
+        let all_days = (1..=last).filter_map(|d| NaiveDate::from_ymd_opt(year, month, d));
+
+        if !rule.by_month_day.is_empty() {
+            let mut dates: Vec<NaiveDate> = all_days
+                .filter(|d| matches_month_day(*d, &rule.by_month_day))
+                .collect();
+            // 同时有 BYDAY 时 BYDAY 只做限制
+            if !rule.by_day.is_empty() {
+                dates.retain(|d| rule.by_day.iter().any(|b| b.weekday == d.weekday()));
+            }
+            return dates;
+        }
+        if !rule.by_day.is_empty() {
+            let days: Vec<NaiveDate> = all_days.collect();
+            return select_by_day(&days, &rule.by_day);
+        }
+        if default_day {
+            return NaiveDate::from_ymd_opt(year, month, self.dtstart.day())
+                .into_iter()
+                .collect();
+        }
+        Vec::new()
+    }
+
+    fn dates_in_year(&self, year: i32) -> Vec<NaiveDate> {
+        let rule = &self.rule;
+        if !rule.by_month.is_empty() {
+            return rule
+                .by_month
+                .iter()
+                .flat_map(|m| self.dates_in_month(year, *m, true))
+                .collect();
+        }
+        if !rule.by_month_day.is_empty() {
+            return (1..=12)
+                .flat_map(|m| self.dates_in_month(year, m, false))
+                .collect();
+        }
+        if !rule.by_day.is_empty() {
+            // 没有 BYMONTH 时序号按全年计算
+            let days: Vec<NaiveDate> = (1..=366)
+                .filter_map(|d| NaiveDate::from_yo_opt(year, d))
+                .collect();
+            return select_by_day(&days, &rule.by_day);
+        }
+        NaiveDate::from_ymd_opt(year, self.dtstart.month(), self.dtstart.day())
+            .into_iter()
+            .collect()
+    }
+}
+
+fn matches_month_day(date: NaiveDate, month_days: &[i32]) -> bool {
+    let last = days_in_month(date.year(), date.month()) as i32;
+    let day = date.day() as i32;
+    month_days
+        .iter()
+        .any(|&n| if n > 0 { n == day } else { last + n + 1 == day })
+}
+
+// 在一段连续的日期（一个月或一年）中按 BYDAY 选取
+fn select_by_day(days: &[NaiveDate], by_day: &[ByDay]) -> Vec<NaiveDate> {
+    let mut selected = Vec::new();
+    for spec in by_day {
+        let matching: Vec<NaiveDate> = days
+            .iter()
+            .copied()
+            .filter(|d| d.weekday() == spec.weekday)
+            .collect();
+        match spec.nth {
+            None => selected.extend(matching),
+            Some(n) if n > 0 => selected.extend(matching.get(n as usize - 1)),
+            Some(n) => {
+                let index = matching.len() as i32 + n;
+                if index >= 0 {
+                    selected.push(matching[index as usize]);
+                }
+            }
+        }
+    }
+    selected
+}
+
+pub struct Occurrences<'a> {
+    recurrence: &'a Recurrence,
+    period: u32,
+    empty_periods: u32,
+    emitted: u32,
+    buffer: VecDeque<NaiveDate>,
+    done: bool,
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = Occurrence;
+
+    fn next(&mut self) -> Option<Occurrence> {
+        let recurrence = self.recurrence;
+        loop {
+            if self.done {
+                return None;
+            }
+            if let Some(count) = recurrence.rule.count {
+                if self.emitted >= count {
+                    self.done = true;
+                    return None;
+                }
+            }
+
+            if let Some(date) = self.buffer.pop_front() {
+                let start = date.and_time(recurrence.dtstart.time());
+                // 结束时间超出日期范围时同样停止
+                if recurrence.until_reached(start)
+                    || start.checked_add_signed(recurrence.duration).is_none()
+                {
+                    self.done = true;
+                    return None;
+                }
+                self.emitted += 1;
+                return Some(recurrence.occurrence_at(start));
+            }
+
+            let first_date = recurrence.dtstart.date();
+            let Some(dates) = recurrence.dates_in_period(self.period) else {
+                self.done = true;
+                return None;
+            };
+            let dates: Vec<NaiveDate> = dates.into_iter().filter(|d| *d >= first_date).collect();
+            self.period = match self.period.checked_add(1) {
+                Some(period) => period,
+                None => {
+                    self.done = true;
+                    return None;
+                }
+            };
+            if dates.is_empty() {
+                self.empty_periods += 1;
+                if self.empty_periods > MAX_EMPTY_PERIODS {
+                    self.done = true;
+                }
+            } else {
+                self.empty_periods = 0;
+                self.buffer.extend(dates);
+            }
+        }
+    }
+}
+
+// 取出重复任务的规则，没有保存规则的旧数据按 repeat_time 临时转换
+// 任务设置了 tz 时以 tz 为准
+pub fn recurrence_of(task: &RepeatTask) -> Result<Recurrence, RecurrenceError> {
+    let tz = task_tz(task)?;
+    let mut recurrence: Recurrence = match task.recurrence.as_deref().map(str::trim) {
+        Some(text) if !text.is_empty() => text.parse()?,
+        _ => {
+            let start_date = utc_to_zoned(task.created_at, tz).date();
+            Recurrence::from_legacy(&task.repeat_time, start_date)?.0
+        }
+    };
+    if tz.is_some() {
+        recurrence.tz = tz;
+    }
+    Ok(recurrence)
+}
+
+fn task_tz(task: &RepeatTask) -> Result<Option<Tz>, RecurrenceError> {
+    match task.tz.as_deref().map(str::trim) {
+        Some(name) if !name.is_empty() => parse_tz(name).map(Some),
+        _ => Ok(None),
+    }
+}
+
+// 保存前让 recurrence 与 repeat_time 保持一致
+// 旧版前端只会修改 repeat_time（同时原样带回 recurrence），因此以有变化的一方为准；
+// 两者都变化时以 recurrence 为准。无法用旧格式表示的规则，repeat_time 的星期位全部为 0
+pub fn sync_repeat_task(
+    task: &mut RepeatTask,
+    previous: Option<&RepeatTask>,
+) -> Result<(), RecurrenceError> {
+    let has_recurrence = task
+        .recurrence
+        .as_deref()
+        .is_some_and(|r| !r.trim().is_empty());
+    let (recurrence_changed, legacy_changed) = match previous {
+        Some(p) => (
+            task.recurrence != p.recurrence,
+            task.repeat_time != p.repeat_time,
+        ),
+        None => (true, true),
+    };
+
+    let tz = task_tz(task)?;
+    task.tz = tz.map(|tz| tz.name().to_string());
+    if has_recurrence && (recurrence_changed || !legacy_changed) {
+        let mut recurrence: Recurrence = task.recurrence.as_deref().unwrap().parse()?;
+        // 规则里带了 TZID 而任务没有设置 tz 时，以规则为准
+        match tz {
+            Some(tz) => recurrence.tz = Some(tz),
+            None => task.tz = recurrence.tz.map(|tz| tz.name().to_string()),
+        }
+        task.repeat_time = match recurrence.to_legacy(task.skip_holidays) {
+            Some(legacy) => legacy,
+            None => {
+                let bits = if task.skip_holidays {
+                    LEGACY_SKIP_HOLIDAYS_BIT
+                } else {
+                    0
+                };
+                let window = recurrence
+                    .legacy_time_window()
+                    .unwrap_or_else(|| "00:00|24:00".to_string());
+                format!("{}|{}", bits, window)
+            }
+        };
+        task.recurrence = Some(recurrence.to_string());
+    } else {
+        // 修改 repeat_time 时保留原来的起始日期
+        let start_date = previous
+            .and_then(|p| recurrence_of(p).ok())
+            .map(|r| r.dtstart.date())
+            .unwrap_or_else(|| utc_to_zoned(Utc::now(), tz).date());
+        let (mut recurrence, skip_holidays) =
+            Recurrence::from_legacy(&task.repeat_time, start_date)?;
+        recurrence.tz = tz;
+        task.recurrence = Some(recurrence.to_string());
+        task.skip_holidays = skip_holidays;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn starts(recurrence: &Recurrence, limit: usize) -> Vec<String> {
+        recurrence
+            .occurrences()
+            .take(limit)
+            .map(|o| o.start.format("%Y-%m-%d %H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn weekly_by_day() {
+        let r: Recurrence =
+            "DTSTART:20240101T090000\nDURATION:PT1H\nRRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            starts(&r, 5),
+            [
+                "2024-01-01 09:00",
+                "2024-01-03 09:00",
+                "2024-01-05 09:00",
+                "2024-01-08 09:00",
+                "2024-01-10 09:00",
+            ]
+        );
+        let first = r.occurrences().next().unwrap();
+        assert_eq!(first.end, dt("2024-01-01 10:00"));
+    }
+
+    #[test]
+    fn monthly_last_friday_and_month_day() {
+        let r: Recurrence = "DTSTART:20240101T090000\nRRULE:FREQ=MONTHLY;BYDAY=-1FR;COUNT=3"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            starts(&r, 10),
+            ["2024-01-26 09:00", "2024-02-23 09:00", "2024-03-29 09:00"]
+        );
+
+        // 没有 31 号的月份跳过
+        let r: Recurrence = "DTSTART:20240131T090000\nRRULE:FREQ=MONTHLY;BYMONTHDAY=31"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            starts(&r, 3),
+            ["2024-01-31 09:00", "2024-03-31 09:00", "2024-05-31 09:00"]
+        );
+    }
+
+    #[test]
+    fn interval_and_until() {
+        let r: Recurrence = "DTSTART:20240101T090000\nRRULE:FREQ=DAILY;INTERVAL=3;UNTIL=20240110"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            starts(&r, 10),
+            [
+                "2024-01-01 09:00",
+                "2024-01-04 09:00",
+                "2024-01-07 09:00",
+                "2024-01-10 09:00",
+            ]
+        );
+        let between = r.occurrences_between(dt("2024-01-03 00:00"), dt("2024-01-08 00:00"));
+        assert_eq!(between.len(), 2);
+    }
+
+    #[test]
+    fn legacy_round_trip() {
+        let (r, skip) = Recurrence::from_legacy("127|08:00|10:00", date("2024-01-01")).unwrap();
+        assert!(!skip);
+        assert_eq!(r.duration, Duration::hours(2));
+        assert_eq!(r.rule.by_day.len(), 7);
+        assert_eq!(r.to_legacy(false).as_deref(), Some("127|08:00|10:00"));
+
+        // 第 7 位表示跳过节假日，周一到周五
+        let (r, skip) = Recurrence::from_legacy("190|09:00|18:00", date("2024-01-01")).unwrap();
+        assert!(skip);
+        assert_eq!(r.to_legacy(true).as_deref(), Some("190|09:00|18:00"));
+        assert_eq!(starts(&r, 2), ["2024-01-01 09:00", "2024-01-02 09:00"]);
+
+        // 结束早于开始视为跨天
+        let (r, _) = Recurrence::from_legacy("62|22:00|02:00", date("2024-01-01")).unwrap();
+        assert_eq!(r.duration, Duration::hours(4));
+        assert_eq!(r.to_legacy(false).as_deref(), Some("62|22:00|02:00"));
+
+        assert!(Recurrence::from_legacy("127|08:00", date("2024-01-01")).is_err());
+        assert!(Recurrence::from_legacy("127|25:00|10:00", date("2024-01-01")).is_err());
+    }
+
+    #[test]
+    fn dst_transitions() {
+        let r: Recurrence =
+            "DTSTART;TZID=America/New_York:20240308T080000\nDURATION:PT1H\nRRULE:FREQ=DAILY"
+                .parse()
+                .unwrap();
+        let utc: Vec<String> = r
+            .occurrences()
+            .take(4)
+            .map(|o| o.start_utc().format("%Y-%m-%d %H:%M").to_string())
+            .collect();
+        // 2024-03-10 开始夏令时，墙上时间不变，UTC 提前一小时
+        assert_eq!(
+            utc,
+            [
+                "2024-03-08 13:00",
+                "2024-03-09 13:00",
+                "2024-03-10 12:00",
+                "2024-03-11 12:00",
+            ]
+        );
+
+        let tz = Some(parse_tz("America/New_York").unwrap());
+        // 不存在的 02:30 取切换后的时刻
+        assert_eq!(
+            zoned_to_utc(dt("2024-03-10 02:30"), tz),
+            zoned_to_utc(dt("2024-03-10 03:30"), tz)
+        );
+        assert_eq!(
+            zoned_to_utc(dt("2024-03-10 02:30"), tz)
+                .format("%H:%M")
+                .to_string(),
+            "07:30"
+        );
+        // 重复的 01:30 取第一次（夏令时）
+        assert_eq!(
+            zoned_to_utc(dt("2024-11-03 01:30"), tz)
+                .format("%H:%M")
+                .to_string(),
+            "05:30"
+        );
+    }
+
+    #[test]
+    fn rejects_overflowing_values() {
+        for duration in ["P9999999999999W", "PT9223372036854775807S", "P400D"] {
+            let value = format!(
+                "DTSTART:20240101T090000\nDURATION:{}\nRRULE:FREQ=DAILY",
+                duration
+            );
+            assert!(value.parse::<Recurrence>().is_err(), "{}", duration);
+        }
+    }
+
+    #[test]
+    fn huge_interval_ends_without_panic() {
+        for freq in ["DAILY", "WEEKLY", "MONTHLY", "YEARLY"] {
+            let r: Recurrence = format!(
+                "DTSTART:20240101T090000\nDURATION:PT1H\nRRULE:FREQ={};INTERVAL=4294967295",
+                freq
+            )
+            .parse()
+            .unwrap();
+            assert_eq!(starts(&r, 5), ["2024-01-01 09:00"], "{}", freq);
+        }
+    }
+}
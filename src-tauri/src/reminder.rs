@@ -0,0 +1,937 @@
+// 提醒引擎：根据 matter 的开始/结束时间计算提醒，写入 reminder_job 队列，
+// 到点后记录到 notification_records，并通过配置的渠道（默认是系统通知）发出。
+// 队列保存在数据库中，应用重启后继续生效；关闭期间错过的提醒在启动后汇总成一条通知。
+// 通知可以稍后提醒（snooze）；高优先级 matter 的提醒在确认或忽略前会定时重复通知
+
+use crate::database::{
+    Matter, MatterReminder, NotificationRecord, NotificationStatus, ReminderJob, ReminderStatus,
+};
+use crate::http_server;
+use crate::notification_channel;
+use crate::repository::{Repositories, Result};
+use chrono::{DateTime, Duration, Local, Utc};
+use std::collections::{HashMap, HashSet};
+use tauri::{command, AppHandle, Emitter};
+
+pub const KIND_START: &str = "start";
+pub const KIND_END: &str = "end";
+pub const KIND_ABSOLUTE: &str = "at";
+
+pub const OWNER_MATTER: &str = "matter";
+pub const OWNER_REPEAT_TASK: &str = "repeat_task";
+
+// 相对提醒最多提前 7 天
+pub const MAX_OFFSET_MINUTES: i64 = 7 * 24 * 60;
+
+// 与前端 NotificationType 保持一致
+pub const NOTIFICATION_TYPE_TASK_START: i32 = 0;
+pub const NOTIFICATION_TYPE_TASK_END: i32 = 1;
+pub const NOTIFICATION_TYPE_MISSED: i32 = 5;
+pub const NOTIFICATION_TYPE_DIGEST: i32 = 6;
+
+const ENABLED_KEY: &str = "reminder.enabled";
+// 逗号分隔的提前分钟数，例如 "15" 或 "5,30"
+const START_OFFSETS_KEY: &str = "reminder.start_offsets";
+const END_OFFSETS_KEY: &str = "reminder.end_offsets";
+const DEFAULT_OFFSETS: &str = "15";
+
+// 只为这个时间范围内的 matter 生成提醒
+const LOOKAHEAD_HOURS: i64 = 24;
+const LOOKBACK_HOURS: i64 = 24;
+// 超过提醒时间多久仍按正常提醒发出，再晚则视为错过
+const GRACE_MINUTES: i64 = 5;
+// 汇总通知中最多列出的标题数
+const MISSED_SUMMARY_LIMIT: usize = 5;
+
+// 达到这个优先级的 matter 在确认前反复提醒
+const ESCALATION_PRIORITY: i32 = 1;
+// 重复提醒的间隔分钟数，0 表示不重复
+const ESCALATION_INTERVAL_KEY: &str = "reminder.escalation_interval";
+const DEFAULT_ESCALATION_INTERVAL: i64 = 5;
+// 最多重复提醒的次数
+const ESCALATION_MAX_KEY: &str = "reminder.escalation_max";
+const DEFAULT_ESCALATION_MAX: i32 = 6;
+
+const DEFAULT_SNOOZE_MINUTES: i64 = 10;
+const MAX_SNOOZE_MINUTES: i64 = 7 * 24 * 60;
+
+const CHECK_INTERVAL_SECS: u64 = 30;
+const REMINDER_FIRED_EVENT: &str = "reminder-fired";
+
+pub struct Reminder {
+    pub job: ReminderJob,
+    pub matter: Matter,
+}
+
+#[derive(Default)]
+pub struct DueReminders {
+    pub fire: Vec<Reminder>,
+    pub missed: Vec<Reminder>,
+}
+
+fn parse_offsets(value: &str) -> Vec<i64> {
+    let mut offsets: Vec<i64> = value
+        .split(',')
+        .filter_map(|v| v.trim().parse().ok())
+        .filter(|v| (0..=MAX_OFFSET_MINUTES).contains(v))
+        .collect();
+    offsets.sort();
+    offsets.dedup();
+    offsets
+}
+
+// 相对提醒为 <matter>:<start|end>:<分钟>，固定时间提醒为 <matter>:at:<提醒 id>
+pub fn job_id(matter_id: &str, kind: &str, key: impl std::fmt::Display) -> String {
+    format!("{}:{}:{}", matter_id, kind, key)
+}
+
+// 一条提醒规则：相对开始/结束时间提前若干分钟，或者固定时间
+enum Rule {
+    Relative { anchor: String, offset: i64 },
+    Absolute { id: String, at: DateTime<Utc> },
+}
+
+fn default_rules(start_offsets: &[i64], end_offsets: &[i64]) -> Vec<Rule> {
+    let relative = |anchor: &str, offsets: &[i64]| -> Vec<Rule> {
+        offsets
+            .iter()
+            .map(|&offset| Rule::Relative {
+                anchor: anchor.to_string(),
+                offset,
+            })
+            .collect()
+    };
+    let mut rules = relative(KIND_START, start_offsets);
+    rules.extend(relative(KIND_END, end_offsets));
+    rules
+}
+
+// matter 自己设置了提醒时只用这些；否则继承所属重复任务的提醒；都没有时使用全局默认值
+fn rules_for_matter(
+    storage: &Repositories,
+    matter: &Matter,
+    defaults: impl Fn() -> Vec<Rule>,
+) -> Result<Vec<Rule>> {
+    let mut custom = storage
+        .matter_reminders
+        .get_by_owner(OWNER_MATTER, &matter.id)?;
+    if custom.is_empty() && matter.type_ == 1 {
+        if let Some(task_id) = &matter.reserved_2 {
+            custom = storage
+                .matter_reminders
+                .get_by_owner(OWNER_REPEAT_TASK, task_id)?;
+        }
+    }
+    if custom.is_empty() {
+        return Ok(defaults());
+    }
+    Ok(custom
+        .into_iter()
+        .filter_map(|r| match (r.remind_at, r.offset_minutes) {
+            (Some(at), _) => Some(Rule::Absolute { id: r.id, at }),
+            (None, Some(offset)) => Some(Rule::Relative {
+                anchor: r.anchor,
+                offset,
+            }),
+            (None, None) => None,
+        })
+        .collect())
+}
+
+// 单个 matter 应有的提醒
+fn jobs_for_matter(matter: &Matter, rules: &[Rule], now: DateTime<Utc>) -> Vec<ReminderJob> {
+    let mut jobs = Vec::new();
+    for rule in rules {
+        let (id, kind, offset, fire_at) = match rule {
+            Rule::Relative { anchor, offset } => {
+                let at = if anchor == KIND_END {
+                    matter.end_time
+                } else {
+                    matter.start_time
+                };
+                let fire_at = at - Duration::minutes(*offset);
+                // 结束提醒早于开始时间、或提醒时间早于 matter 创建时间的没有意义
+                if (anchor == KIND_END && fire_at < matter.start_time)
+                    || fire_at < matter.created_at
+                {
+                    continue;
+                }
+                (
+                    job_id(&matter.id, anchor, *offset),
+                    anchor.as_str(),
+                    *offset,
+                    fire_at,
+                )
+            }
+            Rule::Absolute { id, at } => (
+                job_id(&matter.id, KIND_ABSOLUTE, id),
+                KIND_ABSOLUTE,
+                (matter.start_time - *at).num_minutes(),
+                *at,
+            ),
+        };
+        jobs.push(ReminderJob {
+            id,
+            matter_id: matter.id.clone(),
+            kind: kind.to_string(),
+            offset_minutes: offset,
+            fire_at,
+            status: ReminderStatus::Pending as i32,
+            fired_at: None,
+            created_at: now,
+        });
+    }
+    jobs
+}
+
+// 根据最近的 matter 更新提醒队列，配置变化后不再需要的提醒会被取消
+pub fn sync_jobs(storage: &Repositories, now: DateTime<Utc>) -> Result<()> {
+    let start_offsets = parse_offsets(&storage.kv.get(START_OFFSETS_KEY, DEFAULT_OFFSETS)?);
+    let end_offsets = parse_offsets(&storage.kv.get(END_OFFSETS_KEY, DEFAULT_OFFSETS)?);
+
+    // 提前量较大的提醒需要更早看到对应的 matter
+    let max_offset = start_offsets
+        .iter()
+        .chain(end_offsets.iter())
+        .copied()
+        .chain(storage.matter_reminders.max_offset()?)
+        .max()
+        .unwrap_or(0);
+    let from = now - Duration::hours(LOOKBACK_HOURS);
+    let to = now + Duration::hours(LOOKAHEAD_HOURS);
+
+    let mut matters: HashMap<String, Matter> = storage
+        .matters
+        .get_by_time_range(from, to + Duration::minutes(max_offset))?
+        .into_iter()
+        .map(|m| (m.id.clone(), m))
+        .collect();
+    // 固定时间的提醒与 matter 本身的时间无关
+    for reminder in storage.matter_reminders.get_absolute_between(from, to)? {
+        if reminder.owner_type != OWNER_MATTER || matters.contains_key(&reminder.owner_id) {
+            continue;
+        }
+        if let Some(matter) = storage.matters.get_by_id(&reminder.owner_id)? {
+            matters.insert(matter.id.clone(), matter);
+        }
+    }
+
+    for matter in matters.values() {
+        let rules = rules_for_matter(storage, matter, || {
+            default_rules(&start_offsets, &end_offsets)
+        })?;
+        let jobs = jobs_for_matter(matter, &rules, now);
+        let wanted: HashSet<&str> = jobs.iter().map(|j| j.id.as_str()).collect();
+        for existing in storage.reminders.get_by_matter(&matter.id)? {
+            if existing.status == ReminderStatus::Pending as i32
+                && !wanted.contains(existing.id.as_str())
+            {
+                storage
+                    .reminders
+                    .update_status(&existing.id, ReminderStatus::Cancelled, None)?;
+            }
+        }
+        for job in &jobs {
+            storage.reminders.upsert(job)?;
+        }
+    }
+    Ok(())
+}
+
+// 取出到期的提醒，区分需要正常发出的和已经错过的
+pub fn collect_due(storage: &Repositories, now: DateTime<Utc>) -> Result<DueReminders> {
+    let mut due = DueReminders::default();
+    for job in storage.reminders.get_due(now)? {
+        let Some(matter) = storage.matters.get_by_id(&job.matter_id)? else {
+            storage
+                .reminders
+                .update_status(&job.id, ReminderStatus::Cancelled, None)?;
+            continue;
+        };
+        let late = now - job.fire_at > Duration::minutes(GRACE_MINUTES);
+        // 开始提醒在 matter 结束后才处理也算错过
+        let expired = job.kind == KIND_START && matter.end_time <= now;
+        let reminder = Reminder { job, matter };
+        if late || expired {
+            due.missed.push(reminder);
+        } else {
+            due.fire.push(reminder);
+        }
+    }
+    Ok(due)
+}
+
+pub(crate) fn new_record(
+    title: String,
+    content: String,
+    type_: i32,
+    related: Option<String>,
+) -> NotificationRecord {
+    NotificationRecord {
+        id: uuid::Uuid::new_v4().to_string(),
+        title,
+        content,
+        type_,
+        status: NotificationStatus::Unread as i32,
+        related_task_id: related,
+        created_at: Utc::now(),
+        read_at: None,
+        expire_at: None,
+        action_url: None,
+        reserved_1: None,
+        reserved_2: None,
+        reserved_3: None,
+        reserved_4: None,
+        reserved_5: None,
+        acknowledged_at: None,
+        next_notify_at: None,
+        escalate: false,
+        escalation_count: 0,
+        delivery_status: None,
+    }
+}
+
+pub fn reminder_record(reminder: &Reminder, now: DateTime<Utc>) -> NotificationRecord {
+    let matter = &reminder.matter;
+    let (title, target, type_) = if reminder.job.kind == KIND_END {
+        ("任务即将结束", matter.end_time, NOTIFICATION_TYPE_TASK_END)
+    } else {
+        (
+            "任务即将开始",
+            matter.start_time,
+            NOTIFICATION_TYPE_TASK_START,
+        )
+    };
+    let minutes = (target - now).num_minutes().max(0);
+    let action = if reminder.job.kind == KIND_END {
+        "结束"
+    } else {
+        "开始"
+    };
+    let content = if reminder.job.kind == KIND_ABSOLUTE && now >= matter.start_time {
+        format!("任务 \"{}\" 正在进行", matter.title)
+    } else if minutes == 0 {
+        format!("任务 \"{}\" 现在{}", matter.title, action)
+    } else {
+        format!(
+            "任务 \"{}\" 将在 {} 分钟后{}",
+            matter.title, minutes, action
+        )
+    };
+    let mut record = new_record(title.to_string(), content, type_, Some(matter.id.clone()));
+    record.escalate = matter.priority >= ESCALATION_PRIORITY;
+    record
+}
+
+pub fn missed_record(missed: &[Reminder]) -> NotificationRecord {
+    let mut lines: Vec<String> = missed
+        .iter()
+        .take(MISSED_SUMMARY_LIMIT)
+        .map(|r| {
+            let at = r.job.fire_at.with_timezone(&Local).format("%m-%d %H:%M");
+            format!("{} {}", at, r.matter.title)
+        })
+        .collect();
+    if missed.len() > MISSED_SUMMARY_LIMIT {
+        lines.push(format!("等 {} 条", missed.len()));
+    }
+    new_record(
+        format!("错过了 {} 条提醒", missed.len()),
+        lines.join("\n"),
+        NOTIFICATION_TYPE_MISSED,
+        None,
+    )
+}
+
+fn emit_fired(app: &AppHandle, record: &NotificationRecord) {
+    if let Err(e) = app.emit(REMINDER_FIRED_EVENT, record) {
+        log::error!("Failed to emit reminder event: {}", e);
+    }
+}
+
+// 写入通知记录并交给各投递渠道，实际发送由 notification_channel::dispatch 完成
+pub(crate) fn deliver(
+    app: &AppHandle,
+    storage: &Repositories,
+    record: &NotificationRecord,
+) -> Result<()> {
+    storage.notifications.create(record)?;
+    notification_channel::enqueue(storage, record, record.created_at)?;
+    emit_fired(app, record);
+    Ok(())
+}
+
+struct Escalation {
+    interval: Duration,
+    max: i32,
+}
+
+fn escalation(storage: &Repositories) -> Result<Option<Escalation>> {
+    let interval = storage
+        .kv
+        .get(
+            ESCALATION_INTERVAL_KEY,
+            &DEFAULT_ESCALATION_INTERVAL.to_string(),
+        )?
+        .trim()
+        .parse()
+        .unwrap_or(DEFAULT_ESCALATION_INTERVAL);
+    let max = storage
+        .kv
+        .get(ESCALATION_MAX_KEY, &DEFAULT_ESCALATION_MAX.to_string())?
+        .trim()
+        .parse()
+        .unwrap_or(DEFAULT_ESCALATION_MAX);
+    if interval <= 0 || max <= 0 {
+        return Ok(None);
+    }
+    Ok(Some(Escalation {
+        interval: Duration::minutes(interval.min(MAX_OFFSET_MINUTES)),
+        max,
+    }))
+}
+
+// 下一次升级提醒的时间，次数用完或 matter 已结束时不再提醒
+fn next_escalation(
+    record: &NotificationRecord,
+    matter: Option<&Matter>,
+    escalation: Option<&Escalation>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let escalation = escalation?;
+    let active = matter.is_some_and(|m| m.end_time > now);
+    (record.escalate && active && record.escalation_count < escalation.max)
+        .then(|| now + escalation.interval)
+}
+
+fn related_matter(storage: &Repositories, record: &NotificationRecord) -> Result<Option<Matter>> {
+    match record.related_task_id.as_deref() {
+        Some(id) => storage.matters.get_by_id(id),
+        None => Ok(None),
+    }
+}
+
+// 稍后提醒到期或需要升级的通知重新弹出，沿用原来的记录
+fn renotify(app: &AppHandle, storage: &Repositories, now: DateTime<Utc>) -> Result<()> {
+    let escalation = escalation(storage)?;
+    for mut record in storage.notifications.get_renotify_due(now)? {
+        let matter = related_matter(storage, &record)?;
+        let snoozed = record.status == NotificationStatus::Snoozed as i32;
+        if !snoozed {
+            if next_escalation(&record, matter.as_ref(), escalation.as_ref(), now).is_none() {
+                record.next_notify_at = None;
+                storage.notifications.update_state(&record)?;
+                continue;
+            }
+            record.escalation_count += 1;
+        }
+        record.status = NotificationStatus::Unread as i32;
+        record.next_notify_at = next_escalation(&record, matter.as_ref(), escalation.as_ref(), now);
+        storage.notifications.update_state(&record)?;
+        notification_channel::enqueue(storage, &record, now)?;
+        emit_fired(app, &record);
+    }
+    Ok(())
+}
+
+fn process(app: &AppHandle, storage: &Repositories, now: DateTime<Utc>) -> Result<()> {
+    if storage.kv.get(ENABLED_KEY, "1")? == "0" {
+        return Ok(());
+    }
+    sync_jobs(storage, now)?;
+    let due = collect_due(storage, now)?;
+
+    let escalation = escalation(storage)?;
+    for reminder in &due.fire {
+        let mut record = reminder_record(reminder, now);
+        record.next_notify_at =
+            next_escalation(&record, Some(&reminder.matter), escalation.as_ref(), now);
+        deliver(app, storage, &record)?;
+        storage
+            .reminders
+            .update_status(&reminder.job.id, ReminderStatus::Fired, Some(now))?;
+    }
+
+    if !due.missed.is_empty() {
+        log::info!("{} reminders missed", due.missed.len());
+        deliver(app, storage, &missed_record(&due.missed))?;
+        for reminder in &due.missed {
+            storage
+                .reminders
+                .update_status(&reminder.job.id, ReminderStatus::Missed, None)?;
+        }
+    }
+
+    renotify(app, storage, now)
+}
+
+async fn run_once(app: &AppHandle) {
+    let Some(storage) = http_server::current_storage().await else {
+        return;
+    };
+    let app_clone = app.clone();
+    let storage_clone = storage.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        process(&app_clone, &storage_clone, Utc::now())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::error!("Failed to process reminders: {}", e),
+        Err(e) => log::error!("Reminder job panicked: {}", e),
+    }
+
+    if let Err(e) = notification_channel::dispatch(app, &storage, Utc::now()).await {
+        log::error!("Failed to dispatch notifications: {}", e);
+    }
+}
+
+// 启动后立即检查一次，补发或汇总关闭期间错过的提醒
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            run_once(&app).await;
+        }
+    });
+}
+
+pub fn validate(reminder: &MatterReminder) -> std::result::Result<(), String> {
+    if reminder.owner_type != OWNER_MATTER && reminder.owner_type != OWNER_REPEAT_TASK {
+        return Err(format!("无效的 owner_type：{}", reminder.owner_type));
+    }
+    if reminder.anchor != KIND_START && reminder.anchor != KIND_END {
+        return Err(format!("无效的 anchor：{}", reminder.anchor));
+    }
+    match (reminder.offset_minutes, reminder.remind_at) {
+        (Some(_), Some(_)) | (None, None) => {
+            Err("offset_minutes 和 remind_at 必须且只能设置一个".into())
+        }
+        (Some(offset), None) if !(0..=MAX_OFFSET_MINUTES).contains(&offset) => Err(format!(
+            "offset_minutes 必须在 0 到 {} 之间",
+            MAX_OFFSET_MINUTES
+        )),
+        (None, Some(_)) if reminder.owner_type == OWNER_REPEAT_TASK => {
+            Err("重复任务只能设置相对提醒".into())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn owner_exists(storage: &Repositories, owner_type: &str, owner_id: &str) -> Result<bool> {
+    match owner_type {
+        OWNER_MATTER => Ok(storage.matters.get_by_id(owner_id)?.is_some()),
+        OWNER_REPEAT_TASK => Ok(storage.repeat_tasks.get_by_id(owner_id)?.is_some()),
+        _ => Ok(false),
+    }
+}
+
+// 校验并保存新的提醒，所属对象不存在时返回 None
+pub fn create_reminder(
+    storage: &Repositories,
+    mut reminder: MatterReminder,
+) -> Result<Option<MatterReminder>> {
+    if !owner_exists(storage, &reminder.owner_type, &reminder.owner_id)? {
+        return Ok(None);
+    }
+    reminder.id = uuid::Uuid::new_v4().to_string();
+    reminder.created_at = Utc::now();
+    storage.matter_reminders.create(&reminder)?;
+    Ok(Some(reminder))
+}
+
+pub(crate) async fn command_storage() -> std::result::Result<Repositories, String> {
+    http_server::current_storage()
+        .await
+        .ok_or_else(|| "存储尚未初始化".to_string())
+}
+
+#[command]
+pub async fn list_matter_reminders(
+    owner_type: String,
+    owner_id: String,
+) -> std::result::Result<Vec<MatterReminder>, String> {
+    let storage = command_storage().await?;
+    storage
+        .matter_reminders
+        .get_by_owner(&owner_type, &owner_id)
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn create_matter_reminder(
+    reminder: MatterReminder,
+) -> std::result::Result<MatterReminder, String> {
+    validate(&reminder)?;
+    let storage = command_storage().await?;
+    create_reminder(&storage, reminder)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "提醒所属的对象不存在".to_string())
+}
+
+#[command]
+pub async fn update_matter_reminder(
+    reminder: MatterReminder,
+) -> std::result::Result<MatterReminder, String> {
+    let storage = command_storage().await?;
+    let existing = storage
+        .matter_reminders
+        .get_by_id(&reminder.id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "提醒不存在".to_string())?;
+    let updated = MatterReminder {
+        anchor: reminder.anchor,
+        offset_minutes: reminder.offset_minutes,
+        remind_at: reminder.remind_at,
+        ..existing
+    };
+    validate(&updated)?;
+    storage
+        .matter_reminders
+        .update(&updated)
+        .map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+#[command]
+pub async fn delete_matter_reminder(id: String) -> std::result::Result<(), String> {
+    let storage = command_storage().await?;
+    storage
+        .matter_reminders
+        .delete(&id)
+        .map_err(|e| e.to_string())
+}
+
+// 稍后提醒的时间：指定时间或若干分钟后，默认 10 分钟，最多 7 天
+pub fn snooze_until(
+    minutes: Option<i64>,
+    until: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> std::result::Result<DateTime<Utc>, String> {
+    let until = match (until, minutes) {
+        (Some(until), _) => until,
+        (None, Some(minutes)) if minutes <= 0 => {
+            return Err("minutes 必须大于 0".to_string());
+        }
+        // 先检查上限，过大的值构造 Duration 时会溢出
+        (None, Some(minutes)) if minutes > MAX_SNOOZE_MINUTES => {
+            return Err("稍后提醒最多 7 天".to_string());
+        }
+        (None, minutes) => now + Duration::minutes(minutes.unwrap_or(DEFAULT_SNOOZE_MINUTES)),
+    };
+    if until <= now {
+        return Err("稍后提醒的时间必须晚于当前时间".to_string());
+    }
+    if until - now > Duration::minutes(MAX_SNOOZE_MINUTES) {
+        return Err("稍后提醒最多 7 天".to_string());
+    }
+    Ok(until)
+}
+
+fn change_state(
+    storage: &Repositories,
+    id: &str,
+    change: impl FnOnce(&mut NotificationRecord),
+) -> Result<Option<NotificationRecord>> {
+    let Some(mut record) = storage.notifications.get_by_id(id)? else {
+        return Ok(None);
+    };
+    change(&mut record);
+    storage.notifications.update_state(&record)?;
+    Ok(Some(record))
+}
+
+pub fn snooze(
+    storage: &Repositories,
+    id: &str,
+    until: DateTime<Utc>,
+) -> Result<Option<NotificationRecord>> {
+    change_state(storage, id, |record| {
+        record.status = NotificationStatus::Snoozed as i32;
+        record.next_notify_at = Some(until);
+    })
+}
+
+// 确认后不再重复提醒
+pub fn acknowledge(
+    storage: &Repositories,
+    id: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<NotificationRecord>> {
+    change_state(storage, id, |record| {
+        record.status = NotificationStatus::Acknowledged as i32;
+        record.acknowledged_at = Some(now);
+        record.read_at.get_or_insert(now);
+        record.next_notify_at = None;
+    })
+}
+
+pub fn dismiss(
+    storage: &Repositories,
+    id: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<NotificationRecord>> {
+    change_state(storage, id, |record| {
+        record.status = NotificationStatus::Dismissed as i32;
+        record.read_at.get_or_insert(now);
+        record.next_notify_at = None;
+    })
+}
+
+fn found(record: Option<NotificationRecord>) -> std::result::Result<NotificationRecord, String> {
+    record.ok_or_else(|| "通知不存在".to_string())
+}
+
+#[command]
+pub async fn snooze_notification(
+    id: String,
+    minutes: Option<i64>,
+    until: Option<DateTime<Utc>>,
+) -> std::result::Result<NotificationRecord, String> {
+    let until = snooze_until(minutes, until, Utc::now())?;
+    let storage = command_storage().await?;
+    found(snooze(&storage, &id, until).map_err(|e| e.to_string())?)
+}
+
+#[command]
+pub async fn acknowledge_notification(
+    id: String,
+) -> std::result::Result<NotificationRecord, String> {
+    let storage = command_storage().await?;
+    found(acknowledge(&storage, &id, Utc::now()).map_err(|e| e.to_string())?)
+}
+
+#[command]
+pub async fn dismiss_notification(id: String) -> std::result::Result<NotificationRecord, String> {
+    let storage = command_storage().await?;
+    found(dismiss(&storage, &id, Utc::now()).map_err(|e| e.to_string())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, hour, minute, 0).unwrap()
+    }
+
+    fn matter(id: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Matter {
+        serde_json::from_value(json!({
+            "id": id,
+            "title": format!("matter {}", id),
+            "start_time": start,
+            "end_time": end,
+            "created_at": at(0, 0),
+            "updated_at": at(0, 0),
+        }))
+        .unwrap()
+    }
+
+    fn job(id: &str, matter_id: &str, kind: &str, fire_at: DateTime<Utc>) -> ReminderJob {
+        ReminderJob {
+            id: id.to_string(),
+            matter_id: matter_id.to_string(),
+            kind: kind.to_string(),
+            offset_minutes: 0,
+            fire_at,
+            status: ReminderStatus::Pending as i32,
+            fired_at: None,
+            created_at: at(0, 0),
+        }
+    }
+
+    fn jobs(storage: &Repositories, matter_id: &str) -> Vec<(String, i32)> {
+        storage
+            .reminders
+            .get_by_matter(matter_id)
+            .unwrap()
+            .into_iter()
+            .map(|j| (j.id, j.status))
+            .collect()
+    }
+
+    fn ids(reminders: &[Reminder]) -> Vec<&str> {
+        reminders.iter().map(|r| r.job.id.as_str()).collect()
+    }
+
+    const PENDING: i32 = ReminderStatus::Pending as i32;
+    const CANCELLED: i32 = ReminderStatus::Cancelled as i32;
+
+    #[test]
+    fn parse_offsets_bounds() {
+        assert_eq!(parse_offsets("30, 5,5,x,,-1"), [5, 30]);
+        assert_eq!(
+            parse_offsets(&format!(
+                "0,{},{}",
+                MAX_OFFSET_MINUTES,
+                MAX_OFFSET_MINUTES + 1
+            )),
+            [0, MAX_OFFSET_MINUTES]
+        );
+        assert!(parse_offsets("").is_empty());
+    }
+
+    #[test]
+    fn sync_jobs_follows_settings() {
+        let s = Repositories::in_memory();
+        s.matters
+            .create(&matter("a", at(13, 0), at(14, 0)))
+            .unwrap();
+        // 超出前瞻范围的 matter 不生成提醒
+        s.matters
+            .create(&matter(
+                "far",
+                at(20, 0) + Duration::days(2),
+                at(21, 0) + Duration::days(2),
+            ))
+            .unwrap();
+
+        sync_jobs(&s, at(12, 0)).unwrap();
+        assert_eq!(
+            jobs(&s, "a"),
+            [
+                ("a:start:15".to_string(), PENDING),
+                ("a:end:15".to_string(), PENDING)
+            ]
+        );
+        assert!(jobs(&s, "far").is_empty());
+
+        // 修改默认提前量后，旧的提醒被取消
+        s.kv.set(START_OFFSETS_KEY, "5").unwrap();
+        s.kv.set(END_OFFSETS_KEY, "").unwrap();
+        sync_jobs(&s, at(12, 0)).unwrap();
+        assert_eq!(
+            jobs(&s, "a"),
+            [
+                ("a:start:15".to_string(), CANCELLED),
+                ("a:start:5".to_string(), PENDING),
+                ("a:end:15".to_string(), CANCELLED),
+            ]
+        );
+
+        // 改回来后已取消的提醒重新生效
+        s.kv.set(START_OFFSETS_KEY, "15").unwrap();
+        sync_jobs(&s, at(12, 0)).unwrap();
+        assert_eq!(
+            jobs(&s, "a"),
+            [
+                ("a:start:15".to_string(), PENDING),
+                ("a:start:5".to_string(), CANCELLED),
+                ("a:end:15".to_string(), CANCELLED),
+            ]
+        );
+    }
+
+    #[test]
+    fn sync_jobs_uses_custom_reminders() {
+        let s = Repositories::in_memory();
+        s.matters
+            .create(&matter("a", at(13, 0), at(14, 0)))
+            .unwrap();
+        sync_jobs(&s, at(12, 0)).unwrap();
+
+        let reminder = |offset: Option<i64>, remind_at: Option<DateTime<Utc>>| MatterReminder {
+            id: String::new(),
+            owner_type: OWNER_MATTER.to_string(),
+            owner_id: "a".to_string(),
+            anchor: KIND_START.to_string(),
+            offset_minutes: offset,
+            remind_at,
+            created_at: at(0, 0),
+        };
+        let relative = create_reminder(&s, reminder(Some(30), None))
+            .unwrap()
+            .unwrap();
+        let absolute = create_reminder(&s, reminder(None, Some(at(12, 40))))
+            .unwrap()
+            .unwrap();
+        assert!(create_reminder(
+            &s,
+            MatterReminder {
+                owner_id: "missing".into(),
+                ..relative.clone()
+            }
+        )
+        .unwrap()
+        .is_none());
+
+        // 自定义提醒替代全局默认值
+        sync_jobs(&s, at(12, 0)).unwrap();
+        let absolute_id = job_id("a", KIND_ABSOLUTE, &absolute.id);
+        assert_eq!(
+            jobs(&s, "a"),
+            [
+                ("a:start:30".to_string(), PENDING),
+                (absolute_id.clone(), PENDING),
+                ("a:start:15".to_string(), CANCELLED),
+                ("a:end:15".to_string(), CANCELLED),
+            ]
+        );
+        let job = s
+            .reminders
+            .get_by_matter("a")
+            .unwrap()
+            .into_iter()
+            .find(|j| j.id == absolute_id)
+            .unwrap();
+        assert_eq!(job.fire_at, at(12, 40));
+        assert_eq!(job.offset_minutes, 20);
+    }
+
+    #[test]
+    fn collect_due_splits_missed() {
+        let s = Repositories::in_memory();
+        s.matters
+            .create(&matter("a", at(12, 10), at(13, 0)))
+            .unwrap();
+        s.matters
+            .create(&matter("b", at(10, 0), at(11, 0)))
+            .unwrap();
+        let now = at(12, 0);
+        s.reminders
+            .upsert(&job("on-time", "a", KIND_START, now))
+            .unwrap();
+        s.reminders
+            .upsert(&job(
+                "grace",
+                "a",
+                KIND_START,
+                now - Duration::minutes(GRACE_MINUTES),
+            ))
+            .unwrap();
+        s.reminders
+            .upsert(&job(
+                "late",
+                "a",
+                KIND_END,
+                now - Duration::minutes(GRACE_MINUTES + 1),
+            ))
+            .unwrap();
+        // 已结束的 matter 的开始提醒也算错过
+        s.reminders
+            .upsert(&job("ended", "b", KIND_START, now - Duration::minutes(1)))
+            .unwrap();
+        s.reminders
+            .upsert(&job("future", "a", KIND_END, now + Duration::minutes(1)))
+            .unwrap();
+        s.reminders
+            .upsert(&job("orphan", "gone", KIND_START, now))
+            .unwrap();
+
+        let due = collect_due(&s, now).unwrap();
+        let mut fire = ids(&due.fire);
+        fire.sort();
+        let mut missed = ids(&due.missed);
+        missed.sort();
+        assert_eq!(fire, ["grace", "on-time"]);
+        assert_eq!(missed, ["ended", "late"]);
+        // matter 已删除的提醒被取消，不再返回
+        assert_eq!(jobs(&s, "gone"), [("orphan".to_string(), CANCELLED)]);
+        assert_eq!(collect_due(&s, now).unwrap().fire.len(), 2);
+    }
+}
@@ -0,0 +1,184 @@
+// 后台定时根据重复任务生成 matter，不依赖前端页面是否存活
+// 每个任务每天最多生成一次，生成记录保存在 repeat_occurrence 表中；
+// 应用关闭期间错过的日期在下次启动时补齐（最多 catch_up_days 天）
+
+use crate::database::{Matter, RepeatOccurrence, RepeatTask};
+use crate::holiday::{self, HolidayRule};
+use crate::http_server;
+use crate::recurrence::{self, local_to_utc, parse_tz, utc_to_zoned, RecurrenceError};
+use crate::repository::{Repositories, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter};
+
+// 向前生成的天数，0 表示只生成到今天
+const HORIZON_DAYS_KEY: &str = "repeat_task.horizon_days";
+// 最多补齐的天数
+const CATCH_UP_DAYS_KEY: &str = "repeat_task.catch_up_days";
+// 上次运行的日期，补齐从这一天开始
+const LAST_RUN_KEY: &str = "repeat_task.last_run";
+
+const DEFAULT_HORIZON_DAYS: i64 = 0;
+const DEFAULT_CATCH_UP_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 60;
+
+const CHECK_INTERVAL_SECS: u64 = 60;
+const MATERIALIZED_EVENT: &str = "repeat-tasks-materialized";
+
+fn get_days(storage: &Repositories, key: &str, default: i64) -> Result<i64> {
+    let value = storage.kv.get(key, &default.to_string())?;
+    Ok(value.trim().parse().unwrap_or(default).clamp(0, MAX_DAYS))
+}
+
+fn priority_color(priority: i32) -> &'static str {
+    match priority {
+        1 => "red",
+        -1 => "green",
+        _ => "blue",
+    }
+}
+
+fn new_matter(task: &RepeatTask, occurrence: &recurrence::Occurrence) -> Matter {
+    let now = Utc::now();
+    Matter {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: task.title.clone(),
+        description: task.description.clone(),
+        tags: task.tags.clone(),
+        start_time: occurrence.start_utc(),
+        end_time: occurrence.end_utc(),
+        priority: task.priority,
+        type_: 1,
+        created_at: now,
+        updated_at: now,
+        reserved_1: Some(priority_color(task.priority).to_string()),
+        reserved_2: Some(task.id.clone()),
+        reserved_3: None,
+        reserved_4: None,
+        reserved_5: None,
+        tz: task.tz.clone(),
+    }
+}
+
+// 生成今天前后窗口内所有尚未生成的 matter，返回新建的 matter
+// 窗口按本地日期计算；设置了时区的任务按该时区的“今天”平移窗口
+pub fn materialize(storage: &Repositories, now: DateTime<Utc>) -> Result<Vec<Matter>> {
+    let today = utc_to_zoned(now, None).date();
+    let horizon = get_days(storage, HORIZON_DAYS_KEY, DEFAULT_HORIZON_DAYS)?;
+    let catch_up = get_days(storage, CATCH_UP_DAYS_KEY, DEFAULT_CATCH_UP_DAYS)?;
+    let last_run = storage.kv.get(LAST_RUN_KEY, "")?.parse::<NaiveDate>().ok();
+
+    // 第一次运行只处理今天，避免升级后补出大量历史 matter
+    let from = match last_run {
+        Some(date) => date.max(today - Duration::days(catch_up)).min(today),
+        None => today,
+    };
+    let to = today + Duration::days(horizon);
+
+    // 旧版前端生成的 matter 没有生成记录，按 reserved_2 + 日期识别
+    // 各任务时区与本地相差不超过一天，查询范围前后各放宽一天
+    let mut existing: HashMap<(String, NaiveDate), String> = HashMap::new();
+    for matter in storage.matters.get_by_time_range(
+        local_to_utc((from - Duration::days(1)).and_hms_opt(0, 0, 0).unwrap()),
+        local_to_utc((to + Duration::days(2)).and_hms_opt(0, 0, 0).unwrap()),
+    )? {
+        if let (1, Some(task_id)) = (matter.type_, matter.reserved_2) {
+            let tz = matter.tz.as_deref().and_then(|tz| parse_tz(tz).ok());
+            let date = utc_to_zoned(matter.start_time, tz).date();
+            existing.insert((task_id, date), matter.id);
+        }
+    }
+
+    let calendar = holiday::calendar();
+    let mut created = Vec::new();
+    for task in storage.repeat_tasks.get_active_tasks()? {
+        let recurrence = match recurrence::recurrence_of(&task) {
+            Ok(recurrence) => recurrence,
+            Err(e) => {
+                log::warn!("Skipping repeat task {}: {}", task.id, e);
+                continue;
+            }
+        };
+        let shift = recurrence.to_zoned(now).date() - today;
+        let (task_from, task_to) = (from + shift, to + shift);
+        let window_start = task_from.and_hms_opt(0, 0, 0).unwrap();
+        let window_end = (task_to + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap();
+        let created_on = recurrence.to_zoned(task.created_at).date();
+
+        for occurrence in calendar.occurrences_between(
+            &recurrence,
+            HolidayRule::from(&task),
+            window_start,
+            window_end,
+        ) {
+            let date = occurrence.start.date();
+            // 不为任务创建之前的日期补生成
+            if date < task_from.max(created_on) || storage.occurrences.exists(&task.id, date)? {
+                continue;
+            }
+
+            let matter_id = match existing.get(&(task.id.clone(), date)) {
+                Some(id) => id.clone(),
+                None => {
+                    let matter = new_matter(&task, &occurrence);
+                    storage.matters.create(&matter)?;
+                    let id = matter.id.clone();
+                    created.push(matter);
+                    id
+                }
+            };
+            storage.occurrences.create(&RepeatOccurrence {
+                task_id: task.id.clone(),
+                occurrence_date: date,
+                matter_id,
+                created_at: Utc::now(),
+            })?;
+        }
+    }
+
+    storage.kv.set(LAST_RUN_KEY, &today.to_string())?;
+    Ok(created)
+}
+
+// 手动为任务生成今天的一次，不检查规则是否包含今天；时间取规则的开始时刻和时长。
+// 生成的 matter 带 reserved_2，定时生成时会识别为当天已生成
+pub fn materialize_today(
+    task: &RepeatTask,
+    now: DateTime<Utc>,
+) -> std::result::Result<Matter, RecurrenceError> {
+    let recurrence = recurrence::recurrence_of(task)?;
+    let today = recurrence.to_zoned(now).date();
+    let occurrence = recurrence.occurrence_at(today.and_time(recurrence.dtstart.time()));
+    Ok(new_matter(task, &occurrence))
+}
+
+async fn run_once(app: &AppHandle) {
+    let Some(storage) = http_server::current_storage().await else {
+        return;
+    };
+    let result =
+        tauri::async_runtime::spawn_blocking(move || materialize(&storage, Utc::now())).await;
+
+    match result {
+        Ok(Ok(created)) if !created.is_empty() => {
+            log::info!("Materialized {} repeat task matters", created.len());
+            if let Err(e) = app.emit(MATERIALIZED_EVENT, &created) {
+                log::error!("Failed to emit repeat task event: {}", e);
+            }
+        }
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => log::error!("Failed to materialize repeat tasks: {}", e),
+        Err(e) => log::error!("Repeat task job panicked: {}", e),
+    }
+}
+
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            run_once(&app).await;
+        }
+    });
+}
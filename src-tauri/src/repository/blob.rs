@@ -0,0 +1,112 @@
+use super::{BlobStore, Result, StorageError};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+// 哈希只允许十六进制字符，防止拼接出目录之外的路径
+fn check_hash(hash: &str) -> Result<()> {
+    if hash.len() < 3 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(StorageError::Backend(format!("无效的内容哈希：{}", hash)));
+    }
+    Ok(())
+}
+
+// 以 <root>/<hash 前两位>/<hash> 的形式保存在磁盘上
+pub struct FsBlobStore {
+    root: PathBuf,
+}
+
+impl FsBlobStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path(&self, hash: &str) -> PathBuf {
+        self.root.join(&hash[..2]).join(hash)
+    }
+}
+
+impl BlobStore for FsBlobStore {
+    fn put(&self, hash: &str, data: &[u8]) -> Result<()> {
+        check_hash(hash)?;
+        let path = self.path(hash);
+        if path.exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(path.parent().unwrap())?;
+        // 先写临时文件再重命名，避免中途失败留下不完整的内容
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        check_hash(hash)?;
+        match fs::read(self.path(hash)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn remove(&self, hash: &str) -> Result<()> {
+        check_hash(hash)?;
+        match fs::remove_file(self.path(hash)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let mut hashes = Vec::new();
+        if !self.root.exists() {
+            return Ok(hashes);
+        }
+        for dir in fs::read_dir(&self.root)? {
+            let dir = dir?.path();
+            if !dir.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(&dir)? {
+                let name = file?.file_name().to_string_lossy().to_string();
+                if check_hash(&name).is_ok() {
+                    hashes.push(name);
+                }
+            }
+        }
+        Ok(hashes)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryBlobStore {
+    blobs: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl BlobStore for MemoryBlobStore {
+    fn put(&self, hash: &str, data: &[u8]) -> Result<()> {
+        self.blobs
+            .write()
+            .unwrap()
+            .entry(hash.to_string())
+            .or_insert_with(|| data.to_vec());
+        Ok(())
+    }
+
+    fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.blobs.read().unwrap().get(hash).cloned())
+    }
+
+    fn remove(&self, hash: &str) -> Result<()> {
+        self.blobs.write().unwrap().remove(hash);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        Ok(self.blobs.read().unwrap().keys().cloned().collect())
+    }
+}
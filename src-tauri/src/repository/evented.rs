@@ -0,0 +1,421 @@
+// 给仓库加上变更事件：写入成功后发布事件，读操作直接转发。
+// 重复任务生成记录、提醒队列、投递记录和访问令牌属于内部数据，不发布事件
+
+use super::{
+    AttachmentRepository, CommentRepository, KVRepository, MatterReminderRepository,
+    MatterRepository, NotificationRepository, RepeatTaskRepository, Result, TagRepository,
+    TodoRepository,
+};
+use crate::database::{
+    Attachment, CommentSummary, Matter, MatterComment, MatterReminder, NotificationRecord,
+    RepeatTask, Tag, Todo, TodoFilter,
+};
+use crate::events::{self, ChangeType};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const TODO_COMPLETED: &str = "completed";
+// 后台任务使用的设置，变更事件只带 key，不带值
+const INTERNAL_KV_PREFIXES: [&str; 4] = ["digest.", "notification.", "repeat_task.", "reminder."];
+
+pub struct Evented<T: ?Sized> {
+    inner: Arc<T>,
+}
+
+impl<T: ?Sized> Evented<T> {
+    pub fn wrap(inner: Arc<T>) -> Arc<Self> {
+        Arc::new(Self { inner })
+    }
+}
+
+fn publish<D: Serialize>(entity: &'static str, type_: ChangeType, id: &str, data: Option<&D>) {
+    let data = data.and_then(|d| serde_json::to_value(d).ok());
+    events::publish(entity, type_, Some(id.to_string()), data);
+}
+
+// 没有单个对象的批量操作，data 为操作参数
+fn publish_bulk(entity: &'static str, type_: ChangeType, data: Value) {
+    events::publish(entity, type_, None, Some(data));
+}
+
+fn deleted(entity: &'static str, id: &str) {
+    publish::<()>(entity, ChangeType::Deleted, id, None);
+}
+
+impl MatterRepository for Evented<dyn MatterRepository> {
+    fn create(&self, matter: &Matter) -> Result<()> {
+        self.inner.create(matter)?;
+        publish("matter", ChangeType::Created, &matter.id, Some(matter));
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Matter>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn get_all(&self) -> Result<Vec<Matter>> {
+        self.inner.get_all()
+    }
+
+    fn get_by_time_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Matter>> {
+        self.inner.get_by_time_range(start, end)
+    }
+
+    fn update(&self, matter: &Matter) -> Result<()> {
+        self.inner.update(matter)?;
+        publish("matter", ChangeType::Updated, &matter.id, Some(matter));
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.inner.delete(id)?;
+        deleted("matter", id);
+        Ok(())
+    }
+
+    fn query_by_field(&self, field: &str, value: &str, exact_match: bool) -> Result<Vec<Matter>> {
+        self.inner.query_by_field(field, value, exact_match)
+    }
+}
+
+impl RepeatTaskRepository for Evented<dyn RepeatTaskRepository> {
+    fn create(&self, task: &RepeatTask) -> Result<()> {
+        self.inner.create(task)?;
+        publish("repeat_task", ChangeType::Created, &task.id, Some(task));
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<RepeatTask>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn get_all(&self) -> Result<Vec<RepeatTask>> {
+        self.inner.get_all()
+    }
+
+    fn get_active_tasks(&self) -> Result<Vec<RepeatTask>> {
+        self.inner.get_active_tasks()
+    }
+
+    fn update(&self, task: &RepeatTask) -> Result<()> {
+        self.inner.update(task)?;
+        publish("repeat_task", ChangeType::Updated, &task.id, Some(task));
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.inner.delete(id)?;
+        deleted("repeat_task", id);
+        Ok(())
+    }
+
+    fn update_status(&self, id: &str, new_status: i32) -> Result<()> {
+        self.inner.update_status(id, new_status)?;
+        let task = self.inner.get_by_id(id)?;
+        publish("repeat_task", ChangeType::Updated, id, task.as_ref());
+        Ok(())
+    }
+}
+
+impl TodoRepository for Evented<dyn TodoRepository> {
+    fn create(&self, todo: &Todo) -> Result<()> {
+        self.inner.create(todo)?;
+        publish("todo", ChangeType::Created, &todo.id, Some(todo));
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Todo>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn query(&self, filter: &TodoFilter) -> Result<Vec<Todo>> {
+        self.inner.query(filter)
+    }
+
+    fn update(&self, todo: &Todo) -> Result<()> {
+        let previous = self.inner.get_by_id(&todo.id)?;
+        self.inner.update(todo)?;
+        publish("todo", ChangeType::Updated, &todo.id, Some(todo));
+        let was_completed = previous.is_some_and(|p| p.status == TODO_COMPLETED);
+        if todo.status == TODO_COMPLETED && !was_completed {
+            publish("todo", ChangeType::Completed, &todo.id, Some(todo));
+        }
+        Ok(())
+    }
+
+    fn reorder(&self, ids: &[String]) -> Result<()> {
+        self.inner.reorder(ids)?;
+        publish_bulk("todo", ChangeType::Updated, json!({ "reorder": ids }));
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.inner.delete(id)?;
+        deleted("todo", id);
+        Ok(())
+    }
+}
+
+impl TagRepository for Evented<dyn TagRepository> {
+    fn create(&self, name: &str) -> Result<()> {
+        self.inner.create(name)?;
+        publish::<Tag>("tag", ChangeType::Created, name, None);
+        Ok(())
+    }
+
+    fn get_all(&self) -> Result<Vec<Tag>> {
+        self.inner.get_all()
+    }
+
+    fn update_last_used_at(&self, name: &str) -> Result<()> {
+        self.inner.update_last_used_at(name)?;
+        publish::<Tag>("tag", ChangeType::Updated, name, None);
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        self.inner.delete(name)?;
+        deleted("tag", name);
+        Ok(())
+    }
+}
+
+impl KVRepository for Evented<dyn KVRepository> {
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        self.inner.set(key, value)?;
+        let internal = INTERNAL_KV_PREFIXES.iter().any(|p| key.starts_with(p));
+        let data = (!internal).then_some(&value);
+        publish("kv", ChangeType::Updated, key, data);
+        Ok(())
+    }
+
+    fn get(&self, key: &str, default: &str) -> Result<String> {
+        self.inner.get(key, default)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        self.inner.delete(key)?;
+        deleted("kv", key);
+        Ok(())
+    }
+}
+
+impl Evented<dyn NotificationRepository> {
+    // 只改了部分字段的操作重新读出整条通知
+    fn notification_updated(&self, id: &str) -> Result<()> {
+        let record = self.inner.get_by_id(id)?;
+        publish("notification", ChangeType::Updated, id, record.as_ref());
+        Ok(())
+    }
+}
+
+impl NotificationRepository for Evented<dyn NotificationRepository> {
+    fn create(&self, notification: &NotificationRecord) -> Result<()> {
+        self.inner.create(notification)?;
+        publish(
+            "notification",
+            ChangeType::Created,
+            &notification.id,
+            Some(notification),
+        );
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<NotificationRecord>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn get_unread(&self) -> Result<Vec<NotificationRecord>> {
+        self.inner.get_unread()
+    }
+
+    fn update(&self, notification: &NotificationRecord) -> Result<()> {
+        self.inner.update(notification)?;
+        self.notification_updated(&notification.id)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.inner.delete(id)?;
+        deleted("notification", id);
+        Ok(())
+    }
+
+    fn mark_as_read(&self, id: &str) -> Result<()> {
+        self.inner.mark_as_read(id)?;
+        self.notification_updated(id)
+    }
+
+    fn mark_as_read_by_type(&self, type_: i32) -> Result<()> {
+        self.inner.mark_as_read_by_type(type_)?;
+        publish_bulk(
+            "notification",
+            ChangeType::Updated,
+            json!({ "read_type": type_ }),
+        );
+        Ok(())
+    }
+
+    fn mark_all_as_read(&self) -> Result<()> {
+        self.inner.mark_all_as_read()?;
+        publish_bulk(
+            "notification",
+            ChangeType::Updated,
+            json!({ "read_all": true }),
+        );
+        Ok(())
+    }
+
+    fn get_renotify_due(&self, now: DateTime<Utc>) -> Result<Vec<NotificationRecord>> {
+        self.inner.get_renotify_due(now)
+    }
+
+    fn update_state(&self, notification: &NotificationRecord) -> Result<()> {
+        self.inner.update_state(notification)?;
+        self.notification_updated(&notification.id)
+    }
+
+    fn update_delivery_status(&self, id: &str, status: Option<i32>) -> Result<()> {
+        self.inner.update_delivery_status(id, status)?;
+        self.notification_updated(id)
+    }
+}
+
+impl CommentRepository for Evented<dyn CommentRepository> {
+    fn create(&self, comment: &MatterComment) -> Result<()> {
+        self.inner.create(comment)?;
+        publish("comment", ChangeType::Created, &comment.id, Some(comment));
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<MatterComment>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn get_by_matter(&self, matter_id: &str) -> Result<Vec<MatterComment>> {
+        self.inner.get_by_matter(matter_id)
+    }
+
+    fn get_summaries(&self) -> Result<HashMap<String, CommentSummary>> {
+        self.inner.get_summaries()
+    }
+
+    fn update(&self, comment: &MatterComment) -> Result<()> {
+        self.inner.update(comment)?;
+        publish("comment", ChangeType::Updated, &comment.id, Some(comment));
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.inner.delete(id)?;
+        deleted("comment", id);
+        Ok(())
+    }
+
+    fn delete_by_matter(&self, matter_id: &str) -> Result<()> {
+        self.inner.delete_by_matter(matter_id)?;
+        publish_bulk(
+            "comment",
+            ChangeType::Deleted,
+            json!({ "matter_id": matter_id }),
+        );
+        Ok(())
+    }
+}
+
+impl MatterReminderRepository for Evented<dyn MatterReminderRepository> {
+    fn create(&self, reminder: &MatterReminder) -> Result<()> {
+        self.inner.create(reminder)?;
+        publish(
+            "reminder",
+            ChangeType::Created,
+            &reminder.id,
+            Some(reminder),
+        );
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<MatterReminder>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn get_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<Vec<MatterReminder>> {
+        self.inner.get_by_owner(owner_type, owner_id)
+    }
+
+    fn get_absolute_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<MatterReminder>> {
+        self.inner.get_absolute_between(start, end)
+    }
+
+    fn max_offset(&self) -> Result<Option<i64>> {
+        self.inner.max_offset()
+    }
+
+    fn update(&self, reminder: &MatterReminder) -> Result<()> {
+        self.inner.update(reminder)?;
+        publish(
+            "reminder",
+            ChangeType::Updated,
+            &reminder.id,
+            Some(reminder),
+        );
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.inner.delete(id)?;
+        deleted("reminder", id);
+        Ok(())
+    }
+
+    fn delete_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<()> {
+        self.inner.delete_by_owner(owner_type, owner_id)?;
+        publish_bulk(
+            "reminder",
+            ChangeType::Deleted,
+            json!({ "owner_type": owner_type, "owner_id": owner_id }),
+        );
+        Ok(())
+    }
+}
+
+impl AttachmentRepository for Evented<dyn AttachmentRepository> {
+    fn create(&self, attachment: &Attachment) -> Result<()> {
+        self.inner.create(attachment)?;
+        publish(
+            "attachment",
+            ChangeType::Created,
+            &attachment.id,
+            Some(attachment),
+        );
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Attachment>> {
+        self.inner.get_by_id(id)
+    }
+
+    fn get_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<Vec<Attachment>> {
+        self.inner.get_by_owner(owner_type, owner_id)
+    }
+
+    fn get_all(&self) -> Result<Vec<Attachment>> {
+        self.inner.get_all()
+    }
+
+    fn count_by_hash(&self, hash: &str) -> Result<i64> {
+        self.inner.count_by_hash(hash)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.inner.delete(id)?;
+        deleted("attachment", id);
+        Ok(())
+    }
+}
@@ -0,0 +1,1298 @@
+use super::{
+    ApiTokenRepository, AttachmentRepository, ChannelRepository, CommentRepository,
+    DeliveryRepository, IncomingHookRepository, KVRepository, MatterReminderRepository,
+    MatterRepository, NotificationRepository, OccurrenceRepository, ReminderRepository,
+    RepeatTaskRepository, Result, TagRepository, TodoRepository, WebhookDeliveryRepository,
+    WebhookRepository,
+};
+use crate::database::{
+    ApiToken, Attachment, CommentSummary, DeliveryStatus, IncomingHook, KVStore, Matter,
+    MatterComment, MatterReminder, NotificationChannelRecord, NotificationDelivery,
+    NotificationRecord, NotificationStatus, ReminderJob, ReminderStatus, RepeatOccurrence,
+    RepeatTask, Tag, Todo, TodoFilter, Webhook, WebhookDelivery,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+// 纯内存实现，进程退出后数据即丢失
+#[derive(Default)]
+pub struct MemoryStorage {
+    matters: RwLock<HashMap<String, Matter>>,
+    repeat_tasks: RwLock<HashMap<String, RepeatTask>>,
+    occurrences: RwLock<HashMap<(String, NaiveDate), RepeatOccurrence>>,
+    todos: RwLock<HashMap<String, Todo>>,
+    tags: RwLock<HashMap<String, Tag>>,
+    kv: RwLock<HashMap<String, KVStore>>,
+    notifications: RwLock<HashMap<String, NotificationRecord>>,
+    deliveries: RwLock<HashMap<String, NotificationDelivery>>,
+    channels: RwLock<Vec<NotificationChannelRecord>>,
+    api_tokens: RwLock<HashMap<String, ApiToken>>,
+    webhooks: RwLock<HashMap<String, Webhook>>,
+    webhook_deliveries: RwLock<HashMap<String, WebhookDelivery>>,
+    incoming_hooks: RwLock<HashMap<String, IncomingHook>>,
+    reminders: RwLock<HashMap<String, ReminderJob>>,
+    matter_reminders: RwLock<HashMap<String, MatterReminder>>,
+    attachments: RwLock<HashMap<String, Attachment>>,
+    comments: RwLock<HashMap<String, MatterComment>>,
+}
+
+// 与 SQLite 中 query_by_field 支持的字段保持一致
+fn matter_field(matter: &Matter, field: &str) -> Option<String> {
+    match field {
+        "id" => Some(matter.id.clone()),
+        "title" => Some(matter.title.clone()),
+        "description" => matter.description.clone(),
+        "tags" => matter.tags.clone(),
+        "priority" => Some(matter.priority.to_string()),
+        "type" => Some(matter.type_.to_string()),
+        "reserved_1" => matter.reserved_1.clone(),
+        "reserved_2" => matter.reserved_2.clone(),
+        "reserved_3" => matter.reserved_3.clone(),
+        "reserved_4" => matter.reserved_4.clone(),
+        "reserved_5" => matter.reserved_5.clone(),
+        _ => None,
+    }
+}
+
+fn todo_matches(todo: &Todo, filter: &TodoFilter) -> bool {
+    if let Some(status) = &filter.status {
+        if &todo.status != status {
+            return false;
+        }
+    }
+    if let Some(tag) = &filter.tag {
+        let tags = todo.tags.as_deref().unwrap_or("");
+        if !tags.split(',').any(|t| t == tag) {
+            return false;
+        }
+    }
+    if let Some(priority) = filter.priority {
+        if todo.priority != priority {
+            return false;
+        }
+    }
+    let due_date = todo.due_date;
+    if filter.due_before.is_some() && !(due_date.is_some() && due_date <= filter.due_before) {
+        return false;
+    }
+    if filter.due_after.is_some() && !(due_date.is_some() && due_date >= filter.due_after) {
+        return false;
+    }
+    let start_time = todo.start_time;
+    if filter.start_before.is_some() && !(start_time.is_some() && start_time <= filter.start_before)
+    {
+        return false;
+    }
+    if filter.start_after.is_some() && !(start_time.is_some() && start_time >= filter.start_after) {
+        return false;
+    }
+    true
+}
+
+impl MatterRepository for MemoryStorage {
+    fn create(&self, matter: &Matter) -> Result<()> {
+        self.matters
+            .write()
+            .unwrap()
+            .insert(matter.id.clone(), matter.clone());
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Matter>> {
+        Ok(self.matters.read().unwrap().get(id).cloned())
+    }
+
+    fn get_all(&self) -> Result<Vec<Matter>> {
+        let mut matters: Vec<Matter> = self.matters.read().unwrap().values().cloned().collect();
+        matters.sort_by_key(|m| m.start_time);
+        Ok(matters)
+    }
+
+    fn get_by_time_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Matter>> {
+        let mut matters: Vec<Matter> = self
+            .matters
+            .read()
+            .unwrap()
+            .values()
+            .filter(|m| {
+                (m.start_time >= start && m.start_time <= end)
+                    || (m.end_time >= start && m.end_time <= end)
+                    || (m.start_time <= start && m.end_time >= end)
+            })
+            .cloned()
+            .collect();
+        matters.sort_by_key(|m| m.start_time);
+        Ok(matters)
+    }
+
+    fn update(&self, matter: &Matter) -> Result<()> {
+        let mut matters = self.matters.write().unwrap();
+        if let Some(existing) = matters.get_mut(&matter.id) {
+            let created_at = existing.created_at;
+            *existing = matter.clone();
+            existing.created_at = created_at;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.matters.write().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn query_by_field(&self, field: &str, value: &str, exact_match: bool) -> Result<Vec<Matter>> {
+        let mut matters: Vec<Matter> = self
+            .matters
+            .read()
+            .unwrap()
+            .values()
+            .filter(|m| match matter_field(m, field) {
+                Some(v) if exact_match => v == value,
+                Some(v) => v.contains(value),
+                None => false,
+            })
+            .cloned()
+            .collect();
+        matters.sort_by_key(|m| m.start_time);
+        Ok(matters)
+    }
+}
+
+impl RepeatTaskRepository for MemoryStorage {
+    fn create(&self, task: &RepeatTask) -> Result<()> {
+        self.repeat_tasks
+            .write()
+            .unwrap()
+            .insert(task.id.clone(), task.clone());
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<RepeatTask>> {
+        Ok(self.repeat_tasks.read().unwrap().get(id).cloned())
+    }
+
+    fn get_all(&self) -> Result<Vec<RepeatTask>> {
+        let mut tasks: Vec<RepeatTask> = self
+            .repeat_tasks
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect();
+        tasks.sort_by_key(|t| Reverse(t.created_at));
+        Ok(tasks)
+    }
+
+    fn get_active_tasks(&self) -> Result<Vec<RepeatTask>> {
+        let mut tasks = RepeatTaskRepository::get_all(self)?;
+        tasks.retain(|t| t.status == 1);
+        Ok(tasks)
+    }
+
+    fn update(&self, task: &RepeatTask) -> Result<()> {
+        let mut tasks = self.repeat_tasks.write().unwrap();
+        if let Some(existing) = tasks.get_mut(&task.id) {
+            let created_at = existing.created_at;
+            *existing = task.clone();
+            existing.created_at = created_at;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.repeat_tasks.write().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn update_status(&self, id: &str, new_status: i32) -> Result<()> {
+        if let Some(task) = self.repeat_tasks.write().unwrap().get_mut(id) {
+            task.status = new_status;
+            task.updated_at = Utc::now();
+        }
+        Ok(())
+    }
+}
+
+impl OccurrenceRepository for MemoryStorage {
+    fn create(&self, occurrence: &RepeatOccurrence) -> Result<bool> {
+        let mut occurrences = self.occurrences.write().unwrap();
+        let key = (occurrence.task_id.clone(), occurrence.occurrence_date);
+        if occurrences.contains_key(&key) {
+            return Ok(false);
+        }
+        occurrences.insert(key, occurrence.clone());
+        Ok(true)
+    }
+
+    fn exists(&self, task_id: &str, date: NaiveDate) -> Result<bool> {
+        Ok(self
+            .occurrences
+            .read()
+            .unwrap()
+            .contains_key(&(task_id.to_string(), date)))
+    }
+
+    fn get_by_task(&self, task_id: &str) -> Result<Vec<RepeatOccurrence>> {
+        let mut occurrences: Vec<RepeatOccurrence> = self
+            .occurrences
+            .read()
+            .unwrap()
+            .values()
+            .filter(|o| o.task_id == task_id)
+            .cloned()
+            .collect();
+        occurrences.sort_by_key(|o| Reverse(o.occurrence_date));
+        Ok(occurrences)
+    }
+
+    fn get_by_matter(&self, matter_id: &str) -> Result<Option<RepeatOccurrence>> {
+        Ok(self
+            .occurrences
+            .read()
+            .unwrap()
+            .values()
+            .find(|o| o.matter_id == matter_id)
+            .cloned())
+    }
+
+    fn delete_by_task(&self, task_id: &str) -> Result<()> {
+        self.occurrences
+            .write()
+            .unwrap()
+            .retain(|(id, _), _| id != task_id);
+        Ok(())
+    }
+}
+
+impl ReminderRepository for MemoryStorage {
+    fn upsert(&self, job: &ReminderJob) -> Result<()> {
+        let mut reminders = self.reminders.write().unwrap();
+        match reminders.get_mut(&job.id) {
+            Some(existing)
+                if existing.fire_at != job.fire_at
+                    || existing.status == ReminderStatus::Cancelled as i32 =>
+            {
+                existing.fire_at = job.fire_at;
+                existing.status = job.status;
+                existing.fired_at = None;
+            }
+            Some(_) => {}
+            None => {
+                reminders.insert(job.id.clone(), job.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn get_due(&self, now: DateTime<Utc>) -> Result<Vec<ReminderJob>> {
+        let mut jobs: Vec<ReminderJob> = self
+            .reminders
+            .read()
+            .unwrap()
+            .values()
+            .filter(|j| j.status == ReminderStatus::Pending as i32 && j.fire_at <= now)
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|j| j.fire_at);
+        Ok(jobs)
+    }
+
+    fn get_by_matter(&self, matter_id: &str) -> Result<Vec<ReminderJob>> {
+        let mut jobs: Vec<ReminderJob> = self
+            .reminders
+            .read()
+            .unwrap()
+            .values()
+            .filter(|j| j.matter_id == matter_id)
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|j| j.fire_at);
+        Ok(jobs)
+    }
+
+    fn update_status(
+        &self,
+        id: &str,
+        status: ReminderStatus,
+        fired_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        if let Some(job) = self.reminders.write().unwrap().get_mut(id) {
+            job.status = status as i32;
+            job.fired_at = fired_at;
+        }
+        Ok(())
+    }
+
+    fn delete_by_matter(&self, matter_id: &str) -> Result<()> {
+        self.reminders
+            .write()
+            .unwrap()
+            .retain(|_, j| j.matter_id != matter_id);
+        Ok(())
+    }
+}
+
+impl MatterReminderRepository for MemoryStorage {
+    fn create(&self, reminder: &MatterReminder) -> Result<()> {
+        self.matter_reminders
+            .write()
+            .unwrap()
+            .insert(reminder.id.clone(), reminder.clone());
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<MatterReminder>> {
+        Ok(self.matter_reminders.read().unwrap().get(id).cloned())
+    }
+
+    fn get_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<Vec<MatterReminder>> {
+        let mut reminders: Vec<MatterReminder> = self
+            .matter_reminders
+            .read()
+            .unwrap()
+            .values()
+            .filter(|r| r.owner_type == owner_type && r.owner_id == owner_id)
+            .cloned()
+            .collect();
+        reminders.sort_by_key(|r| r.created_at);
+        Ok(reminders)
+    }
+
+    fn get_absolute_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<MatterReminder>> {
+        let mut reminders: Vec<MatterReminder> = self
+            .matter_reminders
+            .read()
+            .unwrap()
+            .values()
+            .filter(|r| r.remind_at.is_some_and(|at| at >= start && at <= end))
+            .cloned()
+            .collect();
+        reminders.sort_by_key(|r| r.remind_at);
+        Ok(reminders)
+    }
+
+    fn max_offset(&self) -> Result<Option<i64>> {
+        Ok(self
+            .matter_reminders
+            .read()
+            .unwrap()
+            .values()
+            .filter_map(|r| r.offset_minutes)
+            .max())
+    }
+
+    fn update(&self, reminder: &MatterReminder) -> Result<()> {
+        if let Some(existing) = self.matter_reminders.write().unwrap().get_mut(&reminder.id) {
+            existing.anchor = reminder.anchor.clone();
+            existing.offset_minutes = reminder.offset_minutes;
+            existing.remind_at = reminder.remind_at;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.matter_reminders.write().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn delete_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<()> {
+        self.matter_reminders
+            .write()
+            .unwrap()
+            .retain(|_, r| !(r.owner_type == owner_type && r.owner_id == owner_id));
+        Ok(())
+    }
+}
+
+impl TodoRepository for MemoryStorage {
+    fn create(&self, todo: &Todo) -> Result<()> {
+        let mut todos = self.todos.write().unwrap();
+        let mut todo = todo.clone();
+        if todo.sort_order.is_none() {
+            let max = todos.values().filter_map(|t| t.sort_order).max();
+            todo.sort_order = Some(max.map_or(0, |m| m + 1));
+        }
+        todos.insert(todo.id.clone(), todo);
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Todo>> {
+        Ok(self.todos.read().unwrap().get(id).cloned())
+    }
+
+    fn query(&self, filter: &TodoFilter) -> Result<Vec<Todo>> {
+        let mut todos: Vec<Todo> = self
+            .todos
+            .read()
+            .unwrap()
+            .values()
+            .filter(|t| todo_matches(t, filter))
+            .cloned()
+            .collect();
+        todos.sort_by(|a, b| {
+            a.sort_order
+                .cmp(&b.sort_order)
+                .then(b.created_at.cmp(&a.created_at))
+        });
+        Ok(todos)
+    }
+
+    fn update(&self, todo: &Todo) -> Result<()> {
+        let mut todos = self.todos.write().unwrap();
+        if let Some(existing) = todos.get_mut(&todo.id) {
+            let created_at = existing.created_at;
+            let sort_order = existing.sort_order;
+            *existing = todo.clone();
+            existing.created_at = created_at;
+            existing.sort_order = todo.sort_order.or(sort_order);
+        }
+        Ok(())
+    }
+
+    fn reorder(&self, ids: &[String]) -> Result<()> {
+        let mut todos = self.todos.write().unwrap();
+        let now = Utc::now();
+        for (index, id) in ids.iter().enumerate() {
+            if let Some(todo) = todos.get_mut(id) {
+                todo.sort_order = Some(index as i64);
+                todo.updated_at = now;
+            }
+        }
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.todos.write().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+impl TagRepository for MemoryStorage {
+    fn create(&self, name: &str) -> Result<()> {
+        let now = Utc::now();
+        self.tags
+            .write()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Tag {
+                name: name.to_string(),
+                created_at: now,
+                last_used_at: now,
+            });
+        Ok(())
+    }
+
+    fn get_all(&self) -> Result<Vec<Tag>> {
+        let mut tags: Vec<Tag> = self.tags.read().unwrap().values().cloned().collect();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tags)
+    }
+
+    fn update_last_used_at(&self, name: &str) -> Result<()> {
+        if let Some(tag) = self.tags.write().unwrap().get_mut(name) {
+            tag.last_used_at = Utc::now();
+        }
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        self.tags.write().unwrap().remove(name);
+        Ok(())
+    }
+}
+
+impl KVRepository for MemoryStorage {
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        let now = Utc::now();
+        self.kv
+            .write()
+            .unwrap()
+            .entry(key.to_string())
+            .and_modify(|kv| {
+                kv.value = value.to_string();
+                kv.updated_at = now;
+            })
+            .or_insert_with(|| KVStore {
+                key: key.to_string(),
+                value: value.to_string(),
+                created_at: now,
+                updated_at: now,
+            });
+        Ok(())
+    }
+
+    fn get(&self, key: &str, default: &str) -> Result<String> {
+        Ok(self
+            .kv
+            .read()
+            .unwrap()
+            .get(key)
+            .map(|kv| kv.value.clone())
+            .unwrap_or(default.to_string()))
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        self.kv.write().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+impl NotificationRepository for MemoryStorage {
+    fn create(&self, notification: &NotificationRecord) -> Result<()> {
+        self.notifications
+            .write()
+            .unwrap()
+            .insert(notification.id.clone(), notification.clone());
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<NotificationRecord>> {
+        Ok(self.notifications.read().unwrap().get(id).cloned())
+    }
+
+    fn get_unread(&self) -> Result<Vec<NotificationRecord>> {
+        let mut notifications: Vec<NotificationRecord> = self
+            .notifications
+            .read()
+            .unwrap()
+            .values()
+            .filter(|n| n.status == NotificationStatus::Unread as i32)
+            .cloned()
+            .collect();
+        notifications.sort_by_key(|n| Reverse(n.created_at));
+        Ok(notifications)
+    }
+
+    fn update(&self, notification: &NotificationRecord) -> Result<()> {
+        let mut notifications = self.notifications.write().unwrap();
+        if let Some(existing) = notifications.get_mut(&notification.id) {
+            // 与 SQLite 一致，状态相关字段由 update_state 维护
+            let previous = existing.clone();
+            *existing = notification.clone();
+            existing.created_at = previous.created_at;
+            existing.read_at = previous.read_at;
+            existing.acknowledged_at = previous.acknowledged_at;
+            existing.next_notify_at = previous.next_notify_at;
+            existing.escalate = previous.escalate;
+            existing.escalation_count = previous.escalation_count;
+            existing.delivery_status = previous.delivery_status;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.notifications.write().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn mark_as_read(&self, id: &str) -> Result<()> {
+        if let Some(n) = self.notifications.write().unwrap().get_mut(id) {
+            n.status = NotificationStatus::Read as i32;
+            n.read_at = Some(Utc::now());
+        }
+        Ok(())
+    }
+
+    fn mark_as_read_by_type(&self, type_: i32) -> Result<()> {
+        let now = Utc::now();
+        for n in self.notifications.write().unwrap().values_mut() {
+            if n.type_ == type_ {
+                n.status = NotificationStatus::Read as i32;
+                n.read_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    fn mark_all_as_read(&self) -> Result<()> {
+        let now = Utc::now();
+        for n in self.notifications.write().unwrap().values_mut() {
+            if n.status == NotificationStatus::Unread as i32 {
+                n.status = NotificationStatus::Read as i32;
+                n.read_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    fn get_renotify_due(&self, now: DateTime<Utc>) -> Result<Vec<NotificationRecord>> {
+        let pending = [
+            NotificationStatus::Unread as i32,
+            NotificationStatus::Read as i32,
+            NotificationStatus::Snoozed as i32,
+        ];
+        let mut notifications: Vec<NotificationRecord> = self
+            .notifications
+            .read()
+            .unwrap()
+            .values()
+            .filter(|n| n.next_notify_at.is_some_and(|at| at <= now))
+            .filter(|n| pending.contains(&n.status))
+            .cloned()
+            .collect();
+        notifications.sort_by_key(|n| n.next_notify_at);
+        Ok(notifications)
+    }
+
+    fn update_state(&self, notification: &NotificationRecord) -> Result<()> {
+        if let Some(n) = self
+            .notifications
+            .write()
+            .unwrap()
+            .get_mut(&notification.id)
+        {
+            n.status = notification.status;
+            n.read_at = notification.read_at;
+            n.acknowledged_at = notification.acknowledged_at;
+            n.next_notify_at = notification.next_notify_at;
+            n.escalate = notification.escalate;
+            n.escalation_count = notification.escalation_count;
+        }
+        Ok(())
+    }
+
+    fn update_delivery_status(&self, id: &str, status: Option<i32>) -> Result<()> {
+        if let Some(n) = self.notifications.write().unwrap().get_mut(id) {
+            n.delivery_status = status;
+        }
+        Ok(())
+    }
+}
+
+impl ChannelRepository for MemoryStorage {
+    fn get_all(&self) -> Result<Vec<NotificationChannelRecord>> {
+        Ok(self.channels.read().unwrap().clone())
+    }
+
+    fn replace_all(&self, channels: &[NotificationChannelRecord]) -> Result<()> {
+        *self.channels.write().unwrap() = channels.to_vec();
+        Ok(())
+    }
+}
+
+impl DeliveryRepository for MemoryStorage {
+    fn upsert(&self, delivery: &NotificationDelivery) -> Result<()> {
+        let mut deliveries = self.deliveries.write().unwrap();
+        match deliveries.get_mut(&delivery.id) {
+            Some(existing) => {
+                existing.status = delivery.status;
+                existing.attempts = delivery.attempts;
+                existing.next_attempt_at = delivery.next_attempt_at;
+                existing.last_error = None;
+                existing.delivered_at = None;
+            }
+            None => {
+                deliveries.insert(delivery.id.clone(), delivery.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn get_due(&self, now: DateTime<Utc>) -> Result<Vec<NotificationDelivery>> {
+        let mut deliveries: Vec<NotificationDelivery> = self
+            .deliveries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|d| d.status == DeliveryStatus::Pending as i32 && d.next_attempt_at <= now)
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|d| d.next_attempt_at);
+        Ok(deliveries)
+    }
+
+    fn get_by_notification(&self, notification_id: &str) -> Result<Vec<NotificationDelivery>> {
+        let mut deliveries: Vec<NotificationDelivery> = self
+            .deliveries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|d| d.notification_id == notification_id)
+            .cloned()
+            .collect();
+        deliveries.sort_by(|a, b| a.channel_id.cmp(&b.channel_id));
+        Ok(deliveries)
+    }
+
+    fn update(&self, delivery: &NotificationDelivery) -> Result<()> {
+        if let Some(existing) = self.deliveries.write().unwrap().get_mut(&delivery.id) {
+            *existing = delivery.clone();
+        }
+        Ok(())
+    }
+
+    fn delete_by_notification(&self, notification_id: &str) -> Result<()> {
+        self.deliveries
+            .write()
+            .unwrap()
+            .retain(|_, d| d.notification_id != notification_id);
+        Ok(())
+    }
+}
+
+impl ApiTokenRepository for MemoryStorage {
+    fn create(&self, token: &ApiToken) -> Result<()> {
+        self.api_tokens
+            .write()
+            .unwrap()
+            .insert(token.id.clone(), token.clone());
+        Ok(())
+    }
+
+    fn get_all(&self) -> Result<Vec<ApiToken>> {
+        let mut tokens: Vec<ApiToken> = self.api_tokens.read().unwrap().values().cloned().collect();
+        tokens.sort_by_key(|t| t.created_at);
+        Ok(tokens)
+    }
+
+    fn get_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        Ok(self
+            .api_tokens
+            .read()
+            .unwrap()
+            .values()
+            .find(|t| t.token_hash == token_hash)
+            .cloned())
+    }
+
+    fn touch(&self, id: &str, used_at: DateTime<Utc>) -> Result<()> {
+        if let Some(token) = self.api_tokens.write().unwrap().get_mut(id) {
+            token.last_used_at = Some(used_at);
+        }
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.api_tokens.write().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+impl WebhookRepository for MemoryStorage {
+    fn create(&self, webhook: &Webhook) -> Result<()> {
+        self.webhooks
+            .write()
+            .unwrap()
+            .insert(webhook.id.clone(), webhook.clone());
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Webhook>> {
+        Ok(self.webhooks.read().unwrap().get(id).cloned())
+    }
+
+    fn get_all(&self) -> Result<Vec<Webhook>> {
+        let mut webhooks: Vec<Webhook> = self.webhooks.read().unwrap().values().cloned().collect();
+        webhooks.sort_by_key(|w| w.created_at);
+        Ok(webhooks)
+    }
+
+    fn update(&self, webhook: &Webhook) -> Result<()> {
+        if let Some(existing) = self.webhooks.write().unwrap().get_mut(&webhook.id) {
+            *existing = Webhook {
+                created_at: existing.created_at,
+                ..webhook.clone()
+            };
+        }
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.webhook_deliveries
+            .write()
+            .unwrap()
+            .retain(|_, d| d.webhook_id != id);
+        self.webhooks.write().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+impl WebhookDeliveryRepository for MemoryStorage {
+    fn create(&self, delivery: &WebhookDelivery) -> Result<()> {
+        self.webhook_deliveries
+            .write()
+            .unwrap()
+            .insert(delivery.id.clone(), delivery.clone());
+        Ok(())
+    }
+
+    fn get_due(&self, now: DateTime<Utc>) -> Result<Vec<WebhookDelivery>> {
+        let mut deliveries: Vec<WebhookDelivery> = self
+            .webhook_deliveries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|d| d.status == DeliveryStatus::Pending as i32 && d.next_attempt_at <= now)
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|d| (d.next_attempt_at, d.event_id));
+        Ok(deliveries)
+    }
+
+    fn get_by_webhook(&self, webhook_id: &str, limit: usize) -> Result<Vec<WebhookDelivery>> {
+        let mut deliveries: Vec<WebhookDelivery> = self
+            .webhook_deliveries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|d| d.webhook_id == webhook_id)
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|d| Reverse((d.created_at, d.event_id)));
+        deliveries.truncate(limit);
+        Ok(deliveries)
+    }
+
+    fn update(&self, delivery: &WebhookDelivery) -> Result<()> {
+        if let Some(existing) = self
+            .webhook_deliveries
+            .write()
+            .unwrap()
+            .get_mut(&delivery.id)
+        {
+            *existing = delivery.clone();
+        }
+        Ok(())
+    }
+
+    fn prune(&self, before: DateTime<Utc>) -> Result<usize> {
+        let mut deliveries = self.webhook_deliveries.write().unwrap();
+        let count = deliveries.len();
+        deliveries
+            .retain(|_, d| d.status == DeliveryStatus::Pending as i32 || d.created_at >= before);
+        Ok(count - deliveries.len())
+    }
+}
+
+impl IncomingHookRepository for MemoryStorage {
+    fn create(&self, hook: &IncomingHook) -> Result<()> {
+        self.incoming_hooks
+            .write()
+            .unwrap()
+            .insert(hook.id.clone(), hook.clone());
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<IncomingHook>> {
+        Ok(self.incoming_hooks.read().unwrap().get(id).cloned())
+    }
+
+    fn get_by_hash(&self, token_hash: &str) -> Result<Option<IncomingHook>> {
+        Ok(self
+            .incoming_hooks
+            .read()
+            .unwrap()
+            .values()
+            .find(|h| h.token_hash == token_hash)
+            .cloned())
+    }
+
+    fn get_all(&self) -> Result<Vec<IncomingHook>> {
+        let mut hooks: Vec<IncomingHook> = self
+            .incoming_hooks
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect();
+        hooks.sort_by_key(|h| h.created_at);
+        Ok(hooks)
+    }
+
+    fn update(&self, hook: &IncomingHook) -> Result<()> {
+        if let Some(existing) = self.incoming_hooks.write().unwrap().get_mut(&hook.id) {
+            *existing = IncomingHook {
+                token_hash: existing.token_hash.clone(),
+                prefix: existing.prefix.clone(),
+                created_at: existing.created_at,
+                last_used_at: existing.last_used_at,
+                ..hook.clone()
+            };
+        }
+        Ok(())
+    }
+
+    fn touch(&self, id: &str, used_at: DateTime<Utc>) -> Result<()> {
+        if let Some(hook) = self.incoming_hooks.write().unwrap().get_mut(id) {
+            hook.last_used_at = Some(used_at);
+        }
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.incoming_hooks.write().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+impl AttachmentRepository for MemoryStorage {
+    fn create(&self, attachment: &Attachment) -> Result<()> {
+        self.attachments
+            .write()
+            .unwrap()
+            .insert(attachment.id.clone(), attachment.clone());
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Attachment>> {
+        Ok(self.attachments.read().unwrap().get(id).cloned())
+    }
+
+    fn get_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<Vec<Attachment>> {
+        let mut attachments = AttachmentRepository::get_all(self)?;
+        attachments.retain(|a| a.owner_type == owner_type && a.owner_id == owner_id);
+        Ok(attachments)
+    }
+
+    fn get_all(&self) -> Result<Vec<Attachment>> {
+        let mut attachments: Vec<Attachment> =
+            self.attachments.read().unwrap().values().cloned().collect();
+        attachments.sort_by_key(|a| a.created_at);
+        Ok(attachments)
+    }
+
+    fn count_by_hash(&self, hash: &str) -> Result<i64> {
+        Ok(self
+            .attachments
+            .read()
+            .unwrap()
+            .values()
+            .filter(|a| a.hash == hash)
+            .count() as i64)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.attachments.write().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+impl CommentRepository for MemoryStorage {
+    fn create(&self, comment: &MatterComment) -> Result<()> {
+        self.comments
+            .write()
+            .unwrap()
+            .insert(comment.id.clone(), comment.clone());
+        Ok(())
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<MatterComment>> {
+        Ok(self.comments.read().unwrap().get(id).cloned())
+    }
+
+    fn get_by_matter(&self, matter_id: &str) -> Result<Vec<MatterComment>> {
+        let mut comments: Vec<MatterComment> = self
+            .comments
+            .read()
+            .unwrap()
+            .values()
+            .filter(|c| c.matter_id == matter_id)
+            .cloned()
+            .collect();
+        comments.sort_by_key(|c| c.created_at);
+        Ok(comments)
+    }
+
+    fn get_summaries(&self) -> Result<HashMap<String, CommentSummary>> {
+        let mut summaries: HashMap<String, CommentSummary> = HashMap::new();
+        for comment in self.comments.read().unwrap().values() {
+            summaries
+                .entry(comment.matter_id.clone())
+                .and_modify(|summary| {
+                    summary.count += 1;
+                    if comment.created_at >= summary.last_comment.created_at {
+                        summary.last_comment = comment.clone();
+                    }
+                })
+                .or_insert_with(|| CommentSummary {
+                    count: 1,
+                    last_comment: comment.clone(),
+                });
+        }
+        Ok(summaries)
+    }
+
+    fn update(&self, comment: &MatterComment) -> Result<()> {
+        if let Some(existing) = self.comments.write().unwrap().get_mut(&comment.id) {
+            existing.content = comment.content.clone();
+            existing.updated_at = comment.updated_at;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        self.comments.write().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn delete_by_matter(&self, matter_id: &str) -> Result<()> {
+        self.comments
+            .write()
+            .unwrap()
+            .retain(|_, c| c.matter_id != matter_id);
+        Ok(())
+    }
+}
+
+// 同一组用例分别在内存和 SQLite 上运行，保证两种存储的行为一致
+#[cfg(test)]
+mod tests {
+    use crate::database::{
+        open_database, Matter, NotificationRecord, NotificationStatus, RepeatOccurrence, Todo,
+        TodoFilter,
+    };
+    use crate::repository::Repositories;
+    use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn each_storage(test: impl Fn(&Repositories)) {
+        test(&Repositories::in_memory());
+
+        let dir =
+            TempDir(std::env::temp_dir().join(format!("fates-test-{}", uuid::Uuid::new_v4())));
+        std::fs::create_dir_all(&dir.0).unwrap();
+        let conn = open_database(&dir.0.join("fates.db")).unwrap();
+        let storage = Repositories::sqlite(conn, dir.0.join("attachments"));
+        test(&storage);
+        storage.close().unwrap();
+    }
+
+    fn matter(id: &str, start: u32, end: u32) -> Matter {
+        serde_json::from_value(json!({
+            "id": id,
+            "title": format!("matter {}", id),
+            "tags": "work,home",
+            "start_time": at(start),
+            "end_time": at(end),
+            "created_at": at(0),
+            "updated_at": at(0),
+        }))
+        .unwrap()
+    }
+
+    fn todo(id: &str, status: &str, tags: &str) -> Todo {
+        serde_json::from_value(json!({
+            "id": id,
+            "title": format!("todo {}", id),
+            "status": status,
+            "tags": tags,
+            "created_at": at(0),
+            "updated_at": at(0),
+        }))
+        .unwrap()
+    }
+
+    fn notification(id: &str, type_: i32, created: u32) -> NotificationRecord {
+        serde_json::from_value(json!({
+            "id": id,
+            "title": "title",
+            "content": "content",
+            "type_": type_,
+            "status": NotificationStatus::Unread as i32,
+            "related_task_id": null,
+            "created_at": at(created),
+            "read_at": null,
+            "expire_at": null,
+            "action_url": null,
+            "reserved_1": null,
+            "reserved_2": null,
+            "reserved_3": null,
+            "reserved_4": null,
+            "reserved_5": null,
+        }))
+        .unwrap()
+    }
+
+    fn ids<T>(items: &[T], id: impl Fn(&T) -> &str) -> Vec<String> {
+        items.iter().map(|i| id(i).to_string()).collect()
+    }
+
+    #[test]
+    fn matters() {
+        each_storage(|s| {
+            s.matters.create(&matter("b", 10, 12)).unwrap();
+            s.matters.create(&matter("a", 8, 9)).unwrap();
+            s.matters.create(&matter("c", 14, 18)).unwrap();
+
+            let all = s.matters.get_all().unwrap();
+            assert_eq!(ids(&all, |m| &m.id), ["a", "b", "c"]);
+
+            // 与时间范围有交集的都返回
+            let range = s.matters.get_by_time_range(at(11), at(15)).unwrap();
+            assert_eq!(ids(&range, |m| &m.id), ["b", "c"]);
+            let inside = s.matters.get_by_time_range(at(15), at(16)).unwrap();
+            assert_eq!(ids(&inside, |m| &m.id), ["c"]);
+
+            let mut updated = matter("b", 10, 13);
+            updated.title = "renamed".into();
+            updated.created_at = at(5);
+            s.matters.update(&updated).unwrap();
+            let b = s.matters.get_by_id("b").unwrap().unwrap();
+            assert_eq!(b.title, "renamed");
+            assert_eq!(b.end_time, at(13));
+            assert_eq!(b.created_at, at(0));
+
+            let exact = s.matters.query_by_field("title", "renamed", true).unwrap();
+            assert_eq!(ids(&exact, |m| &m.id), ["b"]);
+            let partial = s.matters.query_by_field("title", "matter", false).unwrap();
+            assert_eq!(ids(&partial, |m| &m.id), ["a", "c"]);
+
+            s.matters.delete("b").unwrap();
+            assert!(s.matters.get_by_id("b").unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn todos() {
+        each_storage(|s| {
+            s.todos.create(&todo("a", "todo", "work,home")).unwrap();
+            s.todos.create(&todo("b", "completed", "homework")).unwrap();
+            s.todos.create(&todo("c", "todo", "home")).unwrap();
+
+            // 没有 sort_order 时追加到末尾
+            let all = s.todos.query(&TodoFilter::default()).unwrap();
+            assert_eq!(ids(&all, |t| &t.id), ["a", "b", "c"]);
+
+            let filter = TodoFilter {
+                status: Some("todo".into()),
+                ..Default::default()
+            };
+            assert_eq!(ids(&s.todos.query(&filter).unwrap(), |t| &t.id), ["a", "c"]);
+
+            // 只匹配完整的标签
+            let filter = TodoFilter {
+                tag: Some("home".into()),
+                ..Default::default()
+            };
+            assert_eq!(ids(&s.todos.query(&filter).unwrap(), |t| &t.id), ["a", "c"]);
+
+            s.todos
+                .reorder(&["c".into(), "a".into(), "b".into()])
+                .unwrap();
+            let all = s.todos.query(&TodoFilter::default()).unwrap();
+            assert_eq!(ids(&all, |t| &t.id), ["c", "a", "b"]);
+
+            // 更新时不带 sort_order 保留原来的顺序
+            let mut a = todo("a", "in_progress", "");
+            a.due_date = Some(at(12));
+            s.todos.update(&a).unwrap();
+            let a = s.todos.get_by_id("a").unwrap().unwrap();
+            assert_eq!(a.status, "in_progress");
+            assert_eq!(a.sort_order, Some(1));
+
+            let filter = TodoFilter {
+                due_before: Some(at(13)),
+                ..Default::default()
+            };
+            assert_eq!(ids(&s.todos.query(&filter).unwrap(), |t| &t.id), ["a"]);
+            let filter = TodoFilter {
+                due_after: Some(at(13)),
+                ..Default::default()
+            };
+            assert!(s.todos.query(&filter).unwrap().is_empty());
+
+            s.todos.delete("a").unwrap();
+            assert!(s.todos.get_by_id("a").unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn occurrences() {
+        each_storage(|s| {
+            let occurrence = |day: u32, matter_id: &str| RepeatOccurrence {
+                task_id: "task".into(),
+                occurrence_date: date(day),
+                matter_id: matter_id.into(),
+                created_at: at(0),
+            };
+            assert!(s.occurrences.create(&occurrence(1, "m1")).unwrap());
+            assert!(s.occurrences.create(&occurrence(2, "m2")).unwrap());
+            // 同一天只生成一次
+            assert!(!s.occurrences.create(&occurrence(1, "other")).unwrap());
+
+            assert!(s.occurrences.exists("task", date(1)).unwrap());
+            assert!(!s.occurrences.exists("task", date(3)).unwrap());
+            let by_task = s.occurrences.get_by_task("task").unwrap();
+            assert_eq!(ids(&by_task, |o| &o.matter_id), ["m2", "m1"]);
+            let by_matter = s.occurrences.get_by_matter("m1").unwrap().unwrap();
+            assert_eq!(by_matter.occurrence_date, date(1));
+
+            s.occurrences.delete_by_task("task").unwrap();
+            assert!(s.occurrences.get_by_task("task").unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn notifications() {
+        each_storage(|s| {
+            s.notifications.create(&notification("a", 1, 8)).unwrap();
+            s.notifications.create(&notification("b", 2, 9)).unwrap();
+            s.notifications.create(&notification("c", 2, 10)).unwrap();
+
+            let unread = s.notifications.get_unread().unwrap();
+            assert_eq!(ids(&unread, |n| &n.id), ["c", "b", "a"]);
+
+            s.notifications.mark_as_read_by_type(2).unwrap();
+            let unread = s.notifications.get_unread().unwrap();
+            assert_eq!(ids(&unread, |n| &n.id), ["a"]);
+
+            // update 不覆盖由 update_state 维护的字段
+            let b = s.notifications.get_by_id("b").unwrap().unwrap();
+            assert!(b.read_at.is_some());
+            let mut changed = notification("b", 2, 9);
+            changed.title = "changed".into();
+            s.notifications.update(&changed).unwrap();
+            let b = s.notifications.get_by_id("b").unwrap().unwrap();
+            assert_eq!(b.title, "changed");
+            assert!(b.read_at.is_some());
+
+            s.notifications.mark_all_as_read().unwrap();
+            assert!(s.notifications.get_unread().unwrap().is_empty());
+            let a = s.notifications.get_by_id("a").unwrap().unwrap();
+            assert_eq!(a.status, NotificationStatus::Read as i32);
+        });
+    }
+
+    #[test]
+    fn tags_and_kv() {
+        each_storage(|s| {
+            s.tags.create("work").unwrap();
+            s.tags.create("home").unwrap();
+            s.tags.create("work").unwrap();
+            let tags = s.tags.get_all().unwrap();
+            assert_eq!(ids(&tags, |t| &t.name), ["home", "work"]);
+            s.tags.delete("home").unwrap();
+            assert_eq!(s.tags.get_all().unwrap().len(), 1);
+
+            assert_eq!(s.kv.get("theme", "light").unwrap(), "light");
+            s.kv.set("theme", "dark").unwrap();
+            s.kv.set("theme", "system").unwrap();
+            assert_eq!(s.kv.get("theme", "light").unwrap(), "system");
+            s.kv.delete("theme").unwrap();
+            assert_eq!(s.kv.get("theme", "light").unwrap(), "light");
+        });
+    }
+}
@@ -0,0 +1,320 @@
+// 存储层抽象：HTTP 处理函数只依赖这里的 trait，具体实现可以是 SQLite 或内存
+
+mod blob;
+mod evented;
+mod memory;
+mod sqlite;
+
+pub use blob::{FsBlobStore, MemoryBlobStore};
+use evented::Evented;
+pub use memory::MemoryStorage;
+pub use sqlite::SqliteStorage;
+
+use crate::database::{
+    ApiToken, Attachment, CommentSummary, IncomingHook, Matter, MatterComment, MatterReminder,
+    NotificationChannelRecord, NotificationDelivery, NotificationRecord, ReminderJob,
+    ReminderStatus, RepeatOccurrence, RepeatTask, SafeConnection, Tag, Todo, TodoFilter, Webhook,
+    WebhookDelivery,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("SQLite 错误：{0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("文件读写错误：{0}")]
+    Io(#[from] std::io::Error),
+    #[error("存储错误：{0}")]
+    Backend(String),
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+pub trait MatterRepository: Send + Sync {
+    fn create(&self, matter: &Matter) -> Result<()>;
+    fn get_by_id(&self, id: &str) -> Result<Option<Matter>>;
+    fn get_all(&self) -> Result<Vec<Matter>>;
+    fn get_by_time_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Matter>>;
+    fn update(&self, matter: &Matter) -> Result<()>;
+    fn delete(&self, id: &str) -> Result<()>;
+    fn query_by_field(&self, field: &str, value: &str, exact_match: bool) -> Result<Vec<Matter>>;
+}
+
+pub trait RepeatTaskRepository: Send + Sync {
+    fn create(&self, task: &RepeatTask) -> Result<()>;
+    fn get_by_id(&self, id: &str) -> Result<Option<RepeatTask>>;
+    fn get_all(&self) -> Result<Vec<RepeatTask>>;
+    fn get_active_tasks(&self) -> Result<Vec<RepeatTask>>;
+    fn update(&self, task: &RepeatTask) -> Result<()>;
+    fn delete(&self, id: &str) -> Result<()>;
+    fn update_status(&self, id: &str, new_status: i32) -> Result<()>;
+}
+
+// 重复任务生成记录，保证同一天只生成一次
+pub trait OccurrenceRepository: Send + Sync {
+    fn create(&self, occurrence: &RepeatOccurrence) -> Result<bool>;
+    fn exists(&self, task_id: &str, date: NaiveDate) -> Result<bool>;
+    fn get_by_task(&self, task_id: &str) -> Result<Vec<RepeatOccurrence>>;
+    fn get_by_matter(&self, matter_id: &str) -> Result<Option<RepeatOccurrence>>;
+    fn delete_by_task(&self, task_id: &str) -> Result<()>;
+}
+
+pub trait TodoRepository: Send + Sync {
+    fn create(&self, todo: &Todo) -> Result<()>;
+    fn get_by_id(&self, id: &str) -> Result<Option<Todo>>;
+    fn query(&self, filter: &TodoFilter) -> Result<Vec<Todo>>;
+    fn update(&self, todo: &Todo) -> Result<()>;
+    fn reorder(&self, ids: &[String]) -> Result<()>;
+    fn delete(&self, id: &str) -> Result<()>;
+}
+
+pub trait TagRepository: Send + Sync {
+    fn create(&self, name: &str) -> Result<()>;
+    fn get_all(&self) -> Result<Vec<Tag>>;
+    fn update_last_used_at(&self, name: &str) -> Result<()>;
+    fn delete(&self, name: &str) -> Result<()>;
+}
+
+pub trait KVRepository: Send + Sync {
+    fn set(&self, key: &str, value: &str) -> Result<()>;
+    fn get(&self, key: &str, default: &str) -> Result<String>;
+    fn delete(&self, key: &str) -> Result<()>;
+}
+
+pub trait NotificationRepository: Send + Sync {
+    fn create(&self, notification: &NotificationRecord) -> Result<()>;
+    fn get_by_id(&self, id: &str) -> Result<Option<NotificationRecord>>;
+    fn get_unread(&self) -> Result<Vec<NotificationRecord>>;
+    fn update(&self, notification: &NotificationRecord) -> Result<()>;
+    fn delete(&self, id: &str) -> Result<()>;
+    fn mark_as_read(&self, id: &str) -> Result<()>;
+    fn mark_as_read_by_type(&self, type_: i32) -> Result<()>;
+    fn mark_all_as_read(&self) -> Result<()>;
+    fn get_renotify_due(&self, now: DateTime<Utc>) -> Result<Vec<NotificationRecord>>;
+    fn update_state(&self, notification: &NotificationRecord) -> Result<()>;
+    fn update_delivery_status(&self, id: &str, status: Option<i32>) -> Result<()>;
+}
+
+pub trait DeliveryRepository: Send + Sync {
+    fn upsert(&self, delivery: &NotificationDelivery) -> Result<()>;
+    fn get_due(&self, now: DateTime<Utc>) -> Result<Vec<NotificationDelivery>>;
+    fn get_by_notification(&self, notification_id: &str) -> Result<Vec<NotificationDelivery>>;
+    fn update(&self, delivery: &NotificationDelivery) -> Result<()>;
+    fn delete_by_notification(&self, notification_id: &str) -> Result<()>;
+}
+
+// 通知渠道配置，保存时整体替换
+pub trait ChannelRepository: Send + Sync {
+    fn get_all(&self) -> Result<Vec<NotificationChannelRecord>>;
+    fn replace_all(&self, channels: &[NotificationChannelRecord]) -> Result<()>;
+}
+
+pub trait ApiTokenRepository: Send + Sync {
+    fn create(&self, token: &ApiToken) -> Result<()>;
+    fn get_all(&self) -> Result<Vec<ApiToken>>;
+    fn get_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>>;
+    fn touch(&self, id: &str, used_at: DateTime<Utc>) -> Result<()>;
+    fn delete(&self, id: &str) -> Result<()>;
+}
+
+pub trait WebhookRepository: Send + Sync {
+    fn create(&self, webhook: &Webhook) -> Result<()>;
+    fn get_by_id(&self, id: &str) -> Result<Option<Webhook>>;
+    fn get_all(&self) -> Result<Vec<Webhook>>;
+    fn update(&self, webhook: &Webhook) -> Result<()>;
+    // 同时删除投递记录
+    fn delete(&self, id: &str) -> Result<()>;
+}
+
+pub trait WebhookDeliveryRepository: Send + Sync {
+    fn create(&self, delivery: &WebhookDelivery) -> Result<()>;
+    fn get_due(&self, now: DateTime<Utc>) -> Result<Vec<WebhookDelivery>>;
+    // 最近的投递记录，新的在前
+    fn get_by_webhook(&self, webhook_id: &str, limit: usize) -> Result<Vec<WebhookDelivery>>;
+    fn update(&self, delivery: &WebhookDelivery) -> Result<()>;
+    // 删除 before 之前创建且已经结束的记录，返回删除的条数
+    fn prune(&self, before: DateTime<Utc>) -> Result<usize>;
+}
+
+pub trait IncomingHookRepository: Send + Sync {
+    fn create(&self, hook: &IncomingHook) -> Result<()>;
+    fn get_by_id(&self, id: &str) -> Result<Option<IncomingHook>>;
+    fn get_by_hash(&self, token_hash: &str) -> Result<Option<IncomingHook>>;
+    fn get_all(&self) -> Result<Vec<IncomingHook>>;
+    // 不修改令牌
+    fn update(&self, hook: &IncomingHook) -> Result<()>;
+    fn touch(&self, id: &str, used_at: DateTime<Utc>) -> Result<()>;
+    fn delete(&self, id: &str) -> Result<()>;
+}
+
+pub trait ReminderRepository: Send + Sync {
+    fn upsert(&self, job: &ReminderJob) -> Result<()>;
+    fn get_due(&self, now: DateTime<Utc>) -> Result<Vec<ReminderJob>>;
+    fn get_by_matter(&self, matter_id: &str) -> Result<Vec<ReminderJob>>;
+    fn update_status(
+        &self,
+        id: &str,
+        status: ReminderStatus,
+        fired_at: Option<DateTime<Utc>>,
+    ) -> Result<()>;
+    fn delete_by_matter(&self, matter_id: &str) -> Result<()>;
+}
+
+pub trait MatterReminderRepository: Send + Sync {
+    fn create(&self, reminder: &MatterReminder) -> Result<()>;
+    fn get_by_id(&self, id: &str) -> Result<Option<MatterReminder>>;
+    fn get_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<Vec<MatterReminder>>;
+    fn get_absolute_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<MatterReminder>>;
+    fn max_offset(&self) -> Result<Option<i64>>;
+    fn update(&self, reminder: &MatterReminder) -> Result<()>;
+    fn delete(&self, id: &str) -> Result<()>;
+    fn delete_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<()>;
+}
+
+pub trait AttachmentRepository: Send + Sync {
+    fn create(&self, attachment: &Attachment) -> Result<()>;
+    fn get_by_id(&self, id: &str) -> Result<Option<Attachment>>;
+    fn get_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<Vec<Attachment>>;
+    fn get_all(&self) -> Result<Vec<Attachment>>;
+    fn count_by_hash(&self, hash: &str) -> Result<i64>;
+    fn delete(&self, id: &str) -> Result<()>;
+}
+
+pub trait CommentRepository: Send + Sync {
+    fn create(&self, comment: &MatterComment) -> Result<()>;
+    fn get_by_id(&self, id: &str) -> Result<Option<MatterComment>>;
+    fn get_by_matter(&self, matter_id: &str) -> Result<Vec<MatterComment>>;
+    // 以 matter_id 为键，没有评论的 matter 不会出现
+    fn get_summaries(&self) -> Result<HashMap<String, CommentSummary>>;
+    fn update(&self, comment: &MatterComment) -> Result<()>;
+    fn delete(&self, id: &str) -> Result<()>;
+    fn delete_by_matter(&self, matter_id: &str) -> Result<()>;
+}
+
+// 附件内容存储，以内容哈希为键
+pub trait BlobStore: Send + Sync {
+    fn put(&self, hash: &str, data: &[u8]) -> Result<()>;
+    fn get(&self, hash: &str) -> Result<Option<Vec<u8>>>;
+    fn remove(&self, hash: &str) -> Result<()>;
+    fn list(&self) -> Result<Vec<String>>;
+}
+
+// 供 HTTP 层使用的仓库集合
+#[derive(Clone)]
+pub struct Repositories {
+    pub matters: Arc<dyn MatterRepository>,
+    pub repeat_tasks: Arc<dyn RepeatTaskRepository>,
+    pub occurrences: Arc<dyn OccurrenceRepository>,
+    pub todos: Arc<dyn TodoRepository>,
+    pub tags: Arc<dyn TagRepository>,
+    pub kv: Arc<dyn KVRepository>,
+    pub notifications: Arc<dyn NotificationRepository>,
+    pub deliveries: Arc<dyn DeliveryRepository>,
+    pub channels: Arc<dyn ChannelRepository>,
+    pub api_tokens: Arc<dyn ApiTokenRepository>,
+    pub webhooks: Arc<dyn WebhookRepository>,
+    pub webhook_deliveries: Arc<dyn WebhookDeliveryRepository>,
+    pub incoming_hooks: Arc<dyn IncomingHookRepository>,
+    pub reminders: Arc<dyn ReminderRepository>,
+    pub matter_reminders: Arc<dyn MatterReminderRepository>,
+    pub attachments: Arc<dyn AttachmentRepository>,
+    pub comments: Arc<dyn CommentRepository>,
+    pub blobs: Arc<dyn BlobStore>,
+    // SQLite 存储的连接，退出时用来关闭数据库
+    conns: Vec<Arc<SafeConnection>>,
+}
+
+impl Repositories {
+    pub fn sqlite(conn: Arc<SafeConnection>, attachments_dir: PathBuf) -> Self {
+        Self {
+            conns: vec![conn.clone()],
+            ..Self::from_backend(
+                Arc::new(SqliteStorage::new(conn)),
+                Arc::new(FsBlobStore::new(attachments_dir)),
+            )
+        }
+    }
+
+    // 不落盘的存储，适合调试和测试，不会读写用户的 fates.db
+    pub fn in_memory() -> Self {
+        Self::from_backend(
+            Arc::new(MemoryStorage::default()),
+            Arc::new(MemoryBlobStore::default()),
+        )
+    }
+
+    pub fn from_backend<S>(backend: Arc<S>, blobs: Arc<dyn BlobStore>) -> Self
+    where
+        S: MatterRepository
+            + RepeatTaskRepository
+            + OccurrenceRepository
+            + TodoRepository
+            + TagRepository
+            + KVRepository
+            + NotificationRepository
+            + DeliveryRepository
+            + ChannelRepository
+            + ApiTokenRepository
+            + WebhookRepository
+            + WebhookDeliveryRepository
+            + IncomingHookRepository
+            + ReminderRepository
+            + MatterReminderRepository
+            + AttachmentRepository
+            + CommentRepository
+            + 'static,
+    {
+        // 用户可见的数据写入后发布变更事件
+        Self {
+            matters: Evented::<dyn MatterRepository>::wrap(backend.clone()),
+            repeat_tasks: Evented::<dyn RepeatTaskRepository>::wrap(backend.clone()),
+            occurrences: backend.clone(),
+            todos: Evented::<dyn TodoRepository>::wrap(backend.clone()),
+            tags: Evented::<dyn TagRepository>::wrap(backend.clone()),
+            kv: Evented::<dyn KVRepository>::wrap(backend.clone()),
+            notifications: Evented::<dyn NotificationRepository>::wrap(backend.clone()),
+            deliveries: backend.clone(),
+            channels: backend.clone(),
+            api_tokens: backend.clone(),
+            webhooks: backend.clone(),
+            webhook_deliveries: backend.clone(),
+            incoming_hooks: backend.clone(),
+            reminders: backend.clone(),
+            matter_reminders: Evented::<dyn MatterReminderRepository>::wrap(backend.clone()),
+            attachments: Evented::<dyn AttachmentRepository>::wrap(backend.clone()),
+            comments: Evented::<dyn CommentRepository>::wrap(backend),
+            blobs,
+            conns: Vec::new(),
+        }
+    }
+
+    // 访问令牌、webhook 和入站 hook 使用 shared 中的仓库，由所有 profile 共用
+    pub fn share_integrations(self, shared: &Repositories) -> Self {
+        let mut conns = self.conns;
+        conns.extend(shared.conns.iter().cloned());
+        Self {
+            api_tokens: shared.api_tokens.clone(),
+            webhooks: shared.webhooks.clone(),
+            webhook_deliveries: shared.webhook_deliveries.clone(),
+            incoming_hooks: shared.incoming_hooks.clone(),
+            conns,
+            ..self
+        }
+    }
+
+    // 关闭底层数据库，内存存储无需关闭
+    pub fn close(&self) -> Result<()> {
+        for conn in &self.conns {
+            conn.close()?;
+        }
+        Ok(())
+    }
+}
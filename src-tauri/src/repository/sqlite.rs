@@ -0,0 +1,487 @@
+use super::{
+    ApiTokenRepository, AttachmentRepository, ChannelRepository, CommentRepository,
+    DeliveryRepository, IncomingHookRepository, KVRepository, MatterReminderRepository,
+    MatterRepository, NotificationRepository, OccurrenceRepository, ReminderRepository,
+    RepeatTaskRepository, Result, TagRepository, TodoRepository, WebhookDeliveryRepository,
+    WebhookRepository,
+};
+use crate::database::{
+    ApiToken, Attachment, CommentSummary, IncomingHook, KVStore, Matter, MatterComment,
+    MatterReminder, NotificationChannelRecord, NotificationDelivery, NotificationRecord,
+    ReminderJob, ReminderStatus, RepeatOccurrence, RepeatTask, SafeConnection, Tag, Todo,
+    TodoFilter, Webhook, WebhookDelivery,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// 基于 SQLite 的实现，直接委托给 database 模块中的方法
+pub struct SqliteStorage {
+    conn: Arc<SafeConnection>,
+}
+
+impl SqliteStorage {
+    pub fn new(conn: Arc<SafeConnection>) -> Self {
+        Self { conn }
+    }
+}
+
+impl MatterRepository for SqliteStorage {
+    fn create(&self, matter: &Matter) -> Result<()> {
+        Ok(Matter::create(&self.conn, matter)?)
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Matter>> {
+        Ok(Matter::get_by_id(&self.conn, id)?)
+    }
+
+    fn get_all(&self) -> Result<Vec<Matter>> {
+        Ok(Matter::get_all(&self.conn)?)
+    }
+
+    fn get_by_time_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Matter>> {
+        Ok(Matter::get_by_time_range(&self.conn, start, end)?)
+    }
+
+    fn update(&self, matter: &Matter) -> Result<()> {
+        Ok(matter.update(&self.conn)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        Ok(Matter::delete(&self.conn, id)?)
+    }
+
+    fn query_by_field(&self, field: &str, value: &str, exact_match: bool) -> Result<Vec<Matter>> {
+        Ok(Matter::query_by_field(
+            &self.conn,
+            field,
+            value,
+            exact_match,
+        )?)
+    }
+}
+
+impl RepeatTaskRepository for SqliteStorage {
+    fn create(&self, task: &RepeatTask) -> Result<()> {
+        Ok(RepeatTask::create(&self.conn, task)?)
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<RepeatTask>> {
+        Ok(RepeatTask::get_by_id(&self.conn, id)?)
+    }
+
+    fn get_all(&self) -> Result<Vec<RepeatTask>> {
+        Ok(RepeatTask::get_all(&self.conn)?)
+    }
+
+    fn get_active_tasks(&self) -> Result<Vec<RepeatTask>> {
+        Ok(RepeatTask::get_active_tasks(&self.conn)?)
+    }
+
+    fn update(&self, task: &RepeatTask) -> Result<()> {
+        Ok(task.update(&self.conn)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        Ok(RepeatTask::delete(&self.conn, id)?)
+    }
+
+    fn update_status(&self, id: &str, new_status: i32) -> Result<()> {
+        Ok(RepeatTask::update_status(&self.conn, id, new_status)?)
+    }
+}
+
+impl OccurrenceRepository for SqliteStorage {
+    fn create(&self, occurrence: &RepeatOccurrence) -> Result<bool> {
+        Ok(RepeatOccurrence::create(&self.conn, occurrence)?)
+    }
+
+    fn exists(&self, task_id: &str, date: NaiveDate) -> Result<bool> {
+        Ok(RepeatOccurrence::exists(&self.conn, task_id, date)?)
+    }
+
+    fn get_by_task(&self, task_id: &str) -> Result<Vec<RepeatOccurrence>> {
+        Ok(RepeatOccurrence::get_by_task(&self.conn, task_id)?)
+    }
+
+    fn get_by_matter(&self, matter_id: &str) -> Result<Option<RepeatOccurrence>> {
+        Ok(RepeatOccurrence::get_by_matter(&self.conn, matter_id)?)
+    }
+
+    fn delete_by_task(&self, task_id: &str) -> Result<()> {
+        Ok(RepeatOccurrence::delete_by_task(&self.conn, task_id)?)
+    }
+}
+
+impl ReminderRepository for SqliteStorage {
+    fn upsert(&self, job: &ReminderJob) -> Result<()> {
+        Ok(ReminderJob::upsert(&self.conn, job)?)
+    }
+
+    fn get_due(&self, now: DateTime<Utc>) -> Result<Vec<ReminderJob>> {
+        Ok(ReminderJob::get_due(&self.conn, now)?)
+    }
+
+    fn get_by_matter(&self, matter_id: &str) -> Result<Vec<ReminderJob>> {
+        Ok(ReminderJob::get_by_matter(&self.conn, matter_id)?)
+    }
+
+    fn update_status(
+        &self,
+        id: &str,
+        status: ReminderStatus,
+        fired_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        Ok(ReminderJob::update_status(
+            &self.conn, id, status, fired_at,
+        )?)
+    }
+
+    fn delete_by_matter(&self, matter_id: &str) -> Result<()> {
+        Ok(ReminderJob::delete_by_matter(&self.conn, matter_id)?)
+    }
+}
+
+impl MatterReminderRepository for SqliteStorage {
+    fn create(&self, reminder: &MatterReminder) -> Result<()> {
+        Ok(MatterReminder::create(&self.conn, reminder)?)
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<MatterReminder>> {
+        Ok(MatterReminder::get_by_id(&self.conn, id)?)
+    }
+
+    fn get_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<Vec<MatterReminder>> {
+        Ok(MatterReminder::get_by_owner(
+            &self.conn, owner_type, owner_id,
+        )?)
+    }
+
+    fn get_absolute_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<MatterReminder>> {
+        Ok(MatterReminder::get_absolute_between(
+            &self.conn, start, end,
+        )?)
+    }
+
+    fn max_offset(&self) -> Result<Option<i64>> {
+        Ok(MatterReminder::max_offset(&self.conn)?)
+    }
+
+    fn update(&self, reminder: &MatterReminder) -> Result<()> {
+        Ok(MatterReminder::update(&self.conn, reminder)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        Ok(MatterReminder::delete(&self.conn, id)?)
+    }
+
+    fn delete_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<()> {
+        Ok(MatterReminder::delete_by_owner(
+            &self.conn, owner_type, owner_id,
+        )?)
+    }
+}
+
+impl TodoRepository for SqliteStorage {
+    fn create(&self, todo: &Todo) -> Result<()> {
+        Ok(Todo::create(&self.conn, todo)?)
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Todo>> {
+        Ok(Todo::get_by_id(&self.conn, id)?)
+    }
+
+    fn query(&self, filter: &TodoFilter) -> Result<Vec<Todo>> {
+        Ok(Todo::query(&self.conn, filter)?)
+    }
+
+    fn update(&self, todo: &Todo) -> Result<()> {
+        Ok(todo.update(&self.conn)?)
+    }
+
+    fn reorder(&self, ids: &[String]) -> Result<()> {
+        Ok(Todo::reorder(&self.conn, ids)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        Ok(Todo::delete(&self.conn, id)?)
+    }
+}
+
+impl TagRepository for SqliteStorage {
+    fn create(&self, name: &str) -> Result<()> {
+        Ok(Tag::create(&self.conn, name)?)
+    }
+
+    fn get_all(&self) -> Result<Vec<Tag>> {
+        Ok(Tag::get_all(&self.conn)?)
+    }
+
+    fn update_last_used_at(&self, name: &str) -> Result<()> {
+        Ok(Tag::update_last_used_at(&self.conn, name)?)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        Ok(Tag::delete(&self.conn, name)?)
+    }
+}
+
+impl KVRepository for SqliteStorage {
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        Ok(KVStore::set(&self.conn, key, value)?)
+    }
+
+    fn get(&self, key: &str, default: &str) -> Result<String> {
+        Ok(KVStore::get(&self.conn, key, default)?)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        Ok(KVStore::delete(&self.conn, key)?)
+    }
+}
+
+impl NotificationRepository for SqliteStorage {
+    fn create(&self, notification: &NotificationRecord) -> Result<()> {
+        Ok(NotificationRecord::create(&self.conn, notification)?)
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<NotificationRecord>> {
+        Ok(NotificationRecord::get_by_id(&self.conn, id)?)
+    }
+
+    fn get_unread(&self) -> Result<Vec<NotificationRecord>> {
+        Ok(NotificationRecord::get_unread(&self.conn)?)
+    }
+
+    fn update(&self, notification: &NotificationRecord) -> Result<()> {
+        Ok(notification.update(&self.conn)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        Ok(NotificationRecord::delete(&self.conn, id)?)
+    }
+
+    fn mark_as_read(&self, id: &str) -> Result<()> {
+        Ok(NotificationRecord::mark_as_read(&self.conn, id)?)
+    }
+
+    fn mark_as_read_by_type(&self, type_: i32) -> Result<()> {
+        Ok(NotificationRecord::mark_as_read_by_type(&self.conn, type_)?)
+    }
+
+    fn mark_all_as_read(&self) -> Result<()> {
+        Ok(NotificationRecord::mark_all_as_read(&self.conn)?)
+    }
+
+    fn get_renotify_due(&self, now: DateTime<Utc>) -> Result<Vec<NotificationRecord>> {
+        Ok(NotificationRecord::get_renotify_due(&self.conn, now)?)
+    }
+
+    fn update_state(&self, notification: &NotificationRecord) -> Result<()> {
+        Ok(notification.update_state(&self.conn)?)
+    }
+
+    fn update_delivery_status(&self, id: &str, status: Option<i32>) -> Result<()> {
+        Ok(NotificationRecord::update_delivery_status(
+            &self.conn, id, status,
+        )?)
+    }
+}
+
+impl ChannelRepository for SqliteStorage {
+    fn get_all(&self) -> Result<Vec<NotificationChannelRecord>> {
+        Ok(NotificationChannelRecord::get_all(&self.conn)?)
+    }
+
+    fn replace_all(&self, channels: &[NotificationChannelRecord]) -> Result<()> {
+        Ok(NotificationChannelRecord::replace_all(
+            &self.conn, channels,
+        )?)
+    }
+}
+
+impl DeliveryRepository for SqliteStorage {
+    fn upsert(&self, delivery: &NotificationDelivery) -> Result<()> {
+        Ok(NotificationDelivery::upsert(&self.conn, delivery)?)
+    }
+
+    fn get_due(&self, now: DateTime<Utc>) -> Result<Vec<NotificationDelivery>> {
+        Ok(NotificationDelivery::get_due(&self.conn, now)?)
+    }
+
+    fn get_by_notification(&self, notification_id: &str) -> Result<Vec<NotificationDelivery>> {
+        Ok(NotificationDelivery::get_by_notification(
+            &self.conn,
+            notification_id,
+        )?)
+    }
+
+    fn update(&self, delivery: &NotificationDelivery) -> Result<()> {
+        Ok(delivery.update(&self.conn)?)
+    }
+
+    fn delete_by_notification(&self, notification_id: &str) -> Result<()> {
+        Ok(NotificationDelivery::delete_by_notification(
+            &self.conn,
+            notification_id,
+        )?)
+    }
+}
+
+impl ApiTokenRepository for SqliteStorage {
+    fn create(&self, token: &ApiToken) -> Result<()> {
+        Ok(ApiToken::create(&self.conn, token)?)
+    }
+
+    fn get_all(&self) -> Result<Vec<ApiToken>> {
+        Ok(ApiToken::get_all(&self.conn)?)
+    }
+
+    fn get_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        Ok(ApiToken::get_by_hash(&self.conn, token_hash)?)
+    }
+
+    fn touch(&self, id: &str, used_at: DateTime<Utc>) -> Result<()> {
+        Ok(ApiToken::touch(&self.conn, id, used_at)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        Ok(ApiToken::delete(&self.conn, id)?)
+    }
+}
+
+impl WebhookRepository for SqliteStorage {
+    fn create(&self, webhook: &Webhook) -> Result<()> {
+        Ok(Webhook::create(&self.conn, webhook)?)
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Webhook>> {
+        Ok(Webhook::get_by_id(&self.conn, id)?)
+    }
+
+    fn get_all(&self) -> Result<Vec<Webhook>> {
+        Ok(Webhook::get_all(&self.conn)?)
+    }
+
+    fn update(&self, webhook: &Webhook) -> Result<()> {
+        Ok(webhook.update(&self.conn)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        Ok(Webhook::delete(&self.conn, id)?)
+    }
+}
+
+impl WebhookDeliveryRepository for SqliteStorage {
+    fn create(&self, delivery: &WebhookDelivery) -> Result<()> {
+        Ok(WebhookDelivery::create(&self.conn, delivery)?)
+    }
+
+    fn get_due(&self, now: DateTime<Utc>) -> Result<Vec<WebhookDelivery>> {
+        Ok(WebhookDelivery::get_due(&self.conn, now)?)
+    }
+
+    fn get_by_webhook(&self, webhook_id: &str, limit: usize) -> Result<Vec<WebhookDelivery>> {
+        Ok(WebhookDelivery::get_by_webhook(
+            &self.conn, webhook_id, limit,
+        )?)
+    }
+
+    fn update(&self, delivery: &WebhookDelivery) -> Result<()> {
+        Ok(delivery.update(&self.conn)?)
+    }
+
+    fn prune(&self, before: DateTime<Utc>) -> Result<usize> {
+        Ok(WebhookDelivery::prune(&self.conn, before)?)
+    }
+}
+
+impl IncomingHookRepository for SqliteStorage {
+    fn create(&self, hook: &IncomingHook) -> Result<()> {
+        Ok(IncomingHook::create(&self.conn, hook)?)
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<IncomingHook>> {
+        Ok(IncomingHook::get_by_id(&self.conn, id)?)
+    }
+
+    fn get_by_hash(&self, token_hash: &str) -> Result<Option<IncomingHook>> {
+        Ok(IncomingHook::get_by_hash(&self.conn, token_hash)?)
+    }
+
+    fn get_all(&self) -> Result<Vec<IncomingHook>> {
+        Ok(IncomingHook::get_all(&self.conn)?)
+    }
+
+    fn update(&self, hook: &IncomingHook) -> Result<()> {
+        Ok(hook.update(&self.conn)?)
+    }
+
+    fn touch(&self, id: &str, used_at: DateTime<Utc>) -> Result<()> {
+        Ok(IncomingHook::touch(&self.conn, id, used_at)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        Ok(IncomingHook::delete(&self.conn, id)?)
+    }
+}
+
+impl AttachmentRepository for SqliteStorage {
+    fn create(&self, attachment: &Attachment) -> Result<()> {
+        Ok(Attachment::create(&self.conn, attachment)?)
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<Attachment>> {
+        Ok(Attachment::get_by_id(&self.conn, id)?)
+    }
+
+    fn get_by_owner(&self, owner_type: &str, owner_id: &str) -> Result<Vec<Attachment>> {
+        Ok(Attachment::get_by_owner(&self.conn, owner_type, owner_id)?)
+    }
+
+    fn get_all(&self) -> Result<Vec<Attachment>> {
+        Ok(Attachment::get_all(&self.conn)?)
+    }
+
+    fn count_by_hash(&self, hash: &str) -> Result<i64> {
+        Ok(Attachment::count_by_hash(&self.conn, hash)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        Ok(Attachment::delete(&self.conn, id)?)
+    }
+}
+
+impl CommentRepository for SqliteStorage {
+    fn create(&self, comment: &MatterComment) -> Result<()> {
+        Ok(MatterComment::create(&self.conn, comment)?)
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<MatterComment>> {
+        Ok(MatterComment::get_by_id(&self.conn, id)?)
+    }
+
+    fn get_by_matter(&self, matter_id: &str) -> Result<Vec<MatterComment>> {
+        Ok(MatterComment::get_by_matter(&self.conn, matter_id)?)
+    }
+
+    fn get_summaries(&self) -> Result<HashMap<String, CommentSummary>> {
+        Ok(MatterComment::get_summaries(&self.conn)?)
+    }
+
+    fn update(&self, comment: &MatterComment) -> Result<()> {
+        Ok(comment.update(&self.conn)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        Ok(MatterComment::delete(&self.conn, id)?)
+    }
+
+    fn delete_by_matter(&self, matter_id: &str) -> Result<()> {
+        Ok(MatterComment::delete_by_matter(&self.conn, matter_id)?)
+    }
+}
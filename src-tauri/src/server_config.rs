@@ -0,0 +1,188 @@
+// HTTP 服务器的监听地址和端口，保存在 <app_data_dir>/server.json
+// 环境变量 FATES_HTTP_HOST / FATES_HTTP_PORT 优先于配置文件；
+// 端口被占用时依次尝试后面 fallback_ports 个端口，实际端口通过 get_http_server_info 告诉前端。
+// allowed_origins 是允许跨域访问的浏览器来源，环境变量 FATES_HTTP_ORIGINS（逗号分隔）可以覆盖
+
+use crate::cors;
+use crate::http_server::{self, ServerInfo};
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::{command, AppHandle, Emitter};
+
+const SERVER_CONFIG: &str = "server.json";
+const HOST_ENV: &str = "FATES_HTTP_HOST";
+const PORT_ENV: &str = "FATES_HTTP_PORT";
+const ORIGINS_ENV: &str = "FATES_HTTP_ORIGINS";
+const SERVER_CHANGED_EVENT: &str = "http-server-changed";
+
+const DEFAULT_HOST: &str = "localhost";
+const DEFAULT_PORT: u16 = 8523;
+const DEFAULT_FALLBACK_PORTS: u16 = 10;
+const MAX_FALLBACK_PORTS: u16 = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerConfig {
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    // 端口被占用时额外尝试的端口数，0 表示只用 port
+    #[serde(default = "default_fallback_ports")]
+    pub fallback_ports: u16,
+    // 允许跨域访问的来源，例如 http://localhost:3000；"*" 表示任意来源
+    #[serde(default = "default_allowed_origins")]
+    pub allowed_origins: Vec<String>,
+}
+
+fn default_host() -> String {
+    DEFAULT_HOST.to_string()
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+fn default_fallback_ports() -> u16 {
+    DEFAULT_FALLBACK_PORTS
+}
+
+fn default_allowed_origins() -> Vec<String> {
+    cors::DEFAULT_ORIGINS
+        .iter()
+        .map(|o| o.to_string())
+        .collect()
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: default_port(),
+            fallback_ports: default_fallback_ports(),
+            allowed_origins: default_allowed_origins(),
+        }
+    }
+}
+
+impl ServerConfig {
+    // 只读配置文件，不含环境变量
+    fn read(app_dir: &Path) -> Self {
+        fs::read_to_string(app_dir.join(SERVER_CONFIG))
+            .ok()
+            .and_then(
+                |content| match serde_json::from_str::<ServerConfig>(&content) {
+                    Ok(config) => Some(config),
+                    Err(e) => {
+                        log::error!("Invalid server config: {}", e);
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default()
+    }
+
+    // 实际使用的配置：配置文件加上环境变量覆盖
+    pub fn load(app_dir: &Path) -> Self {
+        let mut config = Self::read(app_dir);
+        if let Ok(host) = std::env::var(HOST_ENV) {
+            if !host.trim().is_empty() {
+                config.host = host.trim().to_string();
+            }
+        }
+        if let Ok(port) = std::env::var(PORT_ENV) {
+            match port.trim().parse() {
+                Ok(port) if port != 0 => config.port = port,
+                _ => log::warn!("Ignoring invalid {}: {}", PORT_ENV, port),
+            }
+        }
+        if let Ok(origins) = std::env::var(ORIGINS_ENV) {
+            config.allowed_origins = origins
+                .split(',')
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty())
+                .collect();
+        }
+        config
+    }
+
+    pub fn save(&self, app_dir: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(app_dir.join(SERVER_CONFIG), content).map_err(|e| e.to_string())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.host.trim().is_empty() {
+            return Err("监听地址不能为空".to_string());
+        }
+        if self.port == 0 {
+            return Err("端口不能为 0".to_string());
+        }
+        if self.fallback_ports > MAX_FALLBACK_PORTS {
+            return Err(format!("备用端口数不能超过 {}", MAX_FALLBACK_PORTS));
+        }
+        // 来源只包含协议、主机和端口
+        for origin in &self.allowed_origins {
+            let origin = origin.trim();
+            if origin != "*" && (!origin.contains("://") || origin.ends_with("://")) {
+                return Err(format!("无效的来源：{}", origin));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn load(app: &AppHandle) -> Result<ServerConfig, String> {
+    let app_dir = utils::get_app_data_dir(app.clone())?;
+    Ok(ServerConfig::load(&app_dir))
+}
+
+fn notify_changed(app: &AppHandle, info: &Option<ServerInfo>) {
+    if let Err(e) = app.emit(SERVER_CHANGED_EVENT, info.clone()) {
+        log::error!("Failed to emit server changed event: {}", e);
+    }
+}
+
+// 服务器当前的地址和实际端口，未运行时为空
+#[command]
+pub async fn get_http_server_info() -> Option<ServerInfo> {
+    http_server::server_info()
+}
+
+#[command]
+pub async fn get_http_server_config(app: AppHandle) -> Result<ServerConfig, String> {
+    let app_dir = utils::get_app_data_dir(app)?;
+    Ok(ServerConfig::read(&app_dir))
+}
+
+// 保存配置后立即按新配置重启服务器，环境变量仍然优先
+#[command]
+pub async fn set_http_server_config(
+    app: AppHandle,
+    config: ServerConfig,
+) -> Result<ServerInfo, String> {
+    config.validate()?;
+    let app_dir = utils::get_app_data_dir(app.clone())?;
+    config.save(&app_dir)?;
+    restart_http_server(app).await
+}
+
+#[command]
+pub async fn restart_http_server(app: AppHandle) -> Result<ServerInfo, String> {
+    let config = load(&app)?;
+    let info = http_server::restart_http_server(&config)
+        .await
+        .map_err(|e| e.to_string())?;
+    notify_changed(&app, &Some(info.clone()));
+    Ok(info)
+}
+
+#[command]
+pub async fn stop_http_server(app: AppHandle) -> Result<(), String> {
+    http_server::stop_http_server()
+        .await
+        .map_err(|e| e.to_string())?;
+    notify_changed(&app, &None);
+    Ok(())
+}
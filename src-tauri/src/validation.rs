@@ -0,0 +1,229 @@
+// 写入前的数据校验：matter、重复任务、todo 和通知的创建和更新接口统一在这里检查并规范化。
+// 所有字段检查完后一次性返回全部错误；创建时 id 为空由服务端生成 UUID，priority 超出范围时截断。
+// 错误原因与语言无关，提示文字由 problem::field_message 按语言生成，v1 固定使用中文
+
+use crate::database::{Matter, NotificationRecord, NotificationStatus, RepeatTask, Todo};
+use crate::problem::{self, Lang};
+use crate::recurrence::{self, RecurrenceError};
+use schemars::JsonSchema;
+use serde::Serialize;
+use thiserror::Error;
+
+// 与前端的 Priority 一致：-1 低，0 中，1 高
+pub const PRIORITY_MIN: i32 = -1;
+pub const PRIORITY_MAX: i32 = 1;
+pub const MAX_TITLE_LEN: usize = 500;
+
+const TODO_STATUSES: &[&str] = &["todo", "in_progress", "completed"];
+// 0 普通，1 重复任务生成，2 todo，3 日历导入
+const MATTER_TYPES: std::ops::RangeInclusive<i32> = 0..=3;
+// 1 启用，0 停止，-1 归档
+const REPEAT_TASK_STATUSES: std::ops::RangeInclusive<i32> = -1..=1;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    Required,
+    TooLong(usize),
+    // 不能早于另一个字段
+    Before(&'static str),
+    InvalidType(i32),
+    InvalidStatus(i32),
+    OneOf(&'static [&'static str]),
+    // 以下附带解析失败的具体原因
+    InvalidRecurrence(String),
+    InvalidLegacy(String),
+    InvalidTimeZone(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldIssue {
+    pub field: &'static str,
+    pub issue: Issue,
+}
+
+#[derive(Error, Debug, Clone)]
+#[error("{}", self.summary(Lang::Zh))]
+pub struct ValidationError(pub Vec<FieldIssue>);
+
+impl ValidationError {
+    pub fn field_errors(&self, lang: Lang) -> Vec<FieldError> {
+        self.0
+            .iter()
+            .map(|e| FieldError {
+                field: e.field.to_string(),
+                message: problem::field_message(&e.issue, lang),
+            })
+            .collect()
+    }
+
+    pub fn summary(&self, lang: Lang) -> String {
+        let (colon, separator) = match lang {
+            Lang::Zh => ("：", "；"),
+            Lang::En => (": ", "; "),
+        };
+        self.field_errors(lang)
+            .iter()
+            .map(|e| format!("{}{}{}", e.field, colon, e.message))
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+}
+
+// 创建时生成缺失的 id；更新时 id 来自路径
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Create,
+    Update,
+}
+
+// 只对 v2 生效的规则：v1 和 incoming hook 保持原有行为，允许前端创建的跨天事项（end_time 早于 start_time）
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Api {
+    V1,
+    V2,
+}
+
+#[derive(Default)]
+struct Errors(Vec<FieldIssue>);
+
+impl Errors {
+    fn add(&mut self, field: &'static str, issue: Issue) {
+        self.0.push(FieldIssue { field, issue });
+    }
+
+    fn finish(self) -> Result<(), ValidationError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError(self.0))
+        }
+    }
+}
+
+fn ensure_id(id: &mut String, mode: Mode, errors: &mut Errors) {
+    *id = id.trim().to_string();
+    if id.is_empty() {
+        match mode {
+            Mode::Create => *id = uuid::Uuid::new_v4().to_string(),
+            Mode::Update => errors.add("id", Issue::Required),
+        }
+    }
+}
+
+fn check_title(title: &mut String, errors: &mut Errors) {
+    *title = title.trim().to_string();
+    if title.is_empty() {
+        errors.add("title", Issue::Required);
+    } else if title.chars().count() > MAX_TITLE_LEN {
+        errors.add("title", Issue::TooLong(MAX_TITLE_LEN));
+    }
+}
+
+fn clamp_priority(priority: &mut i32) {
+    *priority = (*priority).clamp(PRIORITY_MIN, PRIORITY_MAX);
+}
+
+// 空字符串视为未设置
+fn check_tz(tz: &mut Option<String>, errors: &mut Errors) {
+    match tz.as_deref().map(str::trim) {
+        Some("") | None => *tz = None,
+        Some(name) => match recurrence::parse_tz(name) {
+            Ok(parsed) => *tz = Some(parsed.name().to_string()),
+            Err(_) => errors.add("tz", Issue::InvalidTimeZone(name.to_string())),
+        },
+    }
+}
+
+pub fn matter(matter: &mut Matter, mode: Mode, api: Api) -> Result<(), ValidationError> {
+    let mut errors = Errors::default();
+    ensure_id(&mut matter.id, mode, &mut errors);
+    check_title(&mut matter.title, &mut errors);
+    if api == Api::V2 && matter.end_time < matter.start_time {
+        errors.add("end_time", Issue::Before("start_time"));
+    }
+    if !MATTER_TYPES.contains(&matter.type_) {
+        errors.add("type_", Issue::InvalidType(matter.type_));
+    }
+    clamp_priority(&mut matter.priority);
+    check_tz(&mut matter.tz, &mut errors);
+    errors.finish()
+}
+
+// 同时让 recurrence 与 repeat_time 保持一致，previous 为更新前的任务
+pub fn repeat_task(
+    task: &mut RepeatTask,
+    previous: Option<&RepeatTask>,
+    mode: Mode,
+) -> Result<(), ValidationError> {
+    let mut errors = Errors::default();
+    ensure_id(&mut task.id, mode, &mut errors);
+    check_title(&mut task.title, &mut errors);
+    if !REPEAT_TASK_STATUSES.contains(&task.status) {
+        errors.add("status", Issue::InvalidStatus(task.status));
+    }
+    clamp_priority(&mut task.priority);
+    if let Err(e) = recurrence::sync_repeat_task(task, previous) {
+        match e {
+            RecurrenceError::Invalid(detail) => {
+                errors.add("recurrence", Issue::InvalidRecurrence(detail))
+            }
+            RecurrenceError::InvalidLegacy(detail) => {
+                errors.add("repeat_time", Issue::InvalidLegacy(detail))
+            }
+            RecurrenceError::InvalidTimeZone(detail) => {
+                errors.add("tz", Issue::InvalidTimeZone(detail))
+            }
+        }
+    }
+    errors.finish()
+}
+
+pub fn repeat_task_status(status: i32) -> Result<(), ValidationError> {
+    let mut errors = Errors::default();
+    if !REPEAT_TASK_STATUSES.contains(&status) {
+        errors.add("status", Issue::InvalidStatus(status));
+    }
+    errors.finish()
+}
+
+pub fn todo(todo: &mut Todo, mode: Mode) -> Result<(), ValidationError> {
+    let mut errors = Errors::default();
+    ensure_id(&mut todo.id, mode, &mut errors);
+    check_title(&mut todo.title, &mut errors);
+    todo.status = todo.status.trim().to_string();
+    if !TODO_STATUSES.contains(&todo.status.as_str()) {
+        errors.add("status", Issue::OneOf(TODO_STATUSES));
+    }
+    if let (Some(start), Some(due)) = (todo.start_time, todo.due_date) {
+        if due < start {
+            errors.add("due_date", Issue::Before("start_time"));
+        }
+    }
+    clamp_priority(&mut todo.priority);
+    errors.finish()
+}
+
+pub fn notification(
+    notification: &mut NotificationRecord,
+    mode: Mode,
+) -> Result<(), ValidationError> {
+    let mut errors = Errors::default();
+    ensure_id(&mut notification.id, mode, &mut errors);
+    check_title(&mut notification.title, &mut errors);
+    let statuses = NotificationStatus::Unread as i32..=NotificationStatus::Snoozed as i32;
+    if !statuses.contains(&notification.status) {
+        errors.add("status", Issue::InvalidStatus(notification.status));
+    }
+    if let Some(expire_at) = notification.expire_at {
+        if expire_at < notification.created_at {
+            errors.add("expire_at", Issue::Before("created_at"));
+        }
+    }
+    errors.finish()
+}
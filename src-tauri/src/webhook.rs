@@ -0,0 +1,441 @@
+// 出站 webhook：订阅数据变更事件，把订阅的事件（例如 matter.created、todo.completed）POST 到外部地址。
+// 请求头 X-Fates-Signature 为 sha256=<hex>，是用订阅的 secret 对请求体做的 HMAC-SHA256；
+// 每次投递写入 webhook_delivery，失败后按与通知渠道相同的指数退避重试
+
+use crate::database::{DeliveryStatus, Webhook, WebhookDelivery};
+use crate::events::{self, ChangeEvent};
+use crate::http_server;
+use crate::notification_channel::{backoff, MAX_ATTEMPTS};
+use crate::reminder;
+use crate::repository::{Repositories, StorageError};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use tauri::command;
+use tauri_plugin_http::reqwest;
+use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Notify;
+
+pub const SIGNATURE_HEADER: &str = "X-Fates-Signature";
+const EVENT_HEADER: &str = "X-Fates-Event";
+const DELIVERY_HEADER: &str = "X-Fates-Delivery";
+const TEST_EVENT: &str = "webhook.test";
+const SECRET_PREFIX: &str = "whsec_";
+
+const REQUEST_TIMEOUT_SECS: u64 = 15;
+// 没有新事件时检查待重试投递的间隔
+const RETRY_INTERVAL_SECS: u64 = 30;
+// 投递记录保留天数
+const RETENTION_DAYS: i64 = 30;
+pub const DELIVERY_LOG_LIMIT: usize = 100;
+
+// 有新投递时唤醒发送任务
+static PENDING: Lazy<Notify> = Lazy::new(Notify::new);
+
+#[derive(Error, Debug)]
+pub enum WebhookError {
+    #[error("配置错误：{0}")]
+    Config(String),
+    #[error("webhook 不存在：{0}")]
+    NotFound(String),
+    #[error("请求失败：{0}")]
+    Http(String),
+    #[error("服务器返回 {0}")]
+    Status(u16),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+pub type Result<T> = std::result::Result<T, WebhookError>;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CreatedWebhook {
+    // 签名密钥，只在创建时返回
+    pub secret: String,
+    #[serde(flatten)]
+    pub webhook: Webhook,
+}
+
+// 请求体：事件名加上变更事件本身
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: &'a str,
+    #[serde(flatten)]
+    change: &'a ChangeEvent,
+}
+
+pub fn event_name(change: &ChangeEvent) -> String {
+    format!("{}.{}", change.entity, change.type_.as_str())
+}
+
+// 支持 *、matter.* 这样的通配，events 为空表示全部
+fn subscribes(webhook: &Webhook, event: &str) -> bool {
+    let entity = event.split('.').next().unwrap_or_default();
+    webhook.events.is_empty()
+        || webhook.events.iter().any(|pattern| {
+            pattern == "*" || pattern == event || pattern.strip_suffix(".*") == Some(entity)
+        })
+}
+
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+fn generate_secret() -> String {
+    format!("{}{}", SECRET_PREFIX, uuid::Uuid::new_v4().simple())
+}
+
+fn validate(webhook: &Webhook) -> Result<()> {
+    let url = webhook.url.trim();
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(WebhookError::Config(format!("无效的地址：{}", url)));
+    }
+    if webhook.events.iter().any(|e| e.trim().is_empty()) {
+        return Err(WebhookError::Config("事件名称不能为空".to_string()));
+    }
+    Ok(())
+}
+
+fn normalize(webhook: &mut Webhook) {
+    webhook.url = webhook.url.trim().to_string();
+    for event in webhook.events.iter_mut() {
+        *event = event.trim().to_string();
+    }
+}
+
+// secret 为空时自动生成
+pub fn create(storage: &Repositories, mut webhook: Webhook) -> Result<CreatedWebhook> {
+    normalize(&mut webhook);
+    validate(&webhook)?;
+    let now = Utc::now();
+    webhook.id = uuid::Uuid::new_v4().to_string();
+    if webhook.secret.trim().is_empty() {
+        webhook.secret = generate_secret();
+    }
+    webhook.created_at = now;
+    webhook.updated_at = now;
+    storage.webhooks.create(&webhook)?;
+    Ok(CreatedWebhook {
+        secret: webhook.secret.clone(),
+        webhook,
+    })
+}
+
+// secret 为空时保留原来的密钥
+pub fn update(storage: &Repositories, id: &str, mut webhook: Webhook) -> Result<Webhook> {
+    let existing = storage
+        .webhooks
+        .get_by_id(id)?
+        .ok_or_else(|| WebhookError::NotFound(id.to_string()))?;
+    normalize(&mut webhook);
+    validate(&webhook)?;
+    webhook.id = existing.id;
+    webhook.created_at = existing.created_at;
+    webhook.updated_at = Utc::now();
+    if webhook.secret.trim().is_empty() {
+        webhook.secret = existing.secret;
+    }
+    storage.webhooks.update(&webhook)?;
+    Ok(webhook)
+}
+
+fn new_delivery(
+    webhook: &Webhook,
+    event: &str,
+    change: &ChangeEvent,
+    now: DateTime<Utc>,
+) -> Result<WebhookDelivery> {
+    let payload = serde_json::to_string(&Payload { event, change })
+        .map_err(|e| WebhookError::Config(e.to_string()))?;
+    Ok(WebhookDelivery {
+        id: uuid::Uuid::new_v4().to_string(),
+        webhook_id: webhook.id.clone(),
+        event_id: change.id as i64,
+        event: event.to_string(),
+        payload,
+        status: DeliveryStatus::Pending as i32,
+        attempts: 0,
+        next_attempt_at: now,
+        response_status: None,
+        last_error: None,
+        delivered_at: None,
+        created_at: now,
+    })
+}
+
+// 为订阅了该事件的 webhook 创建投递记录，返回创建的条数
+pub fn enqueue(storage: &Repositories, change: &ChangeEvent, now: DateTime<Utc>) -> Result<usize> {
+    let event = event_name(change);
+    let mut count = 0;
+    for webhook in storage.webhooks.get_all()? {
+        if !webhook.enabled || !subscribes(&webhook, &event) {
+            continue;
+        }
+        storage
+            .webhook_deliveries
+            .create(&new_delivery(&webhook, &event, change, now)?)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| WebhookError::Http(e.to_string()))
+}
+
+// 返回服务器的状态码，非 2xx 时为 Status 错误
+async fn send(
+    client: &reqwest::Client,
+    webhook: &Webhook,
+    delivery: &WebhookDelivery,
+) -> Result<u16> {
+    let body = delivery.payload.as_bytes().to_vec();
+    let response = client
+        .post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .header(EVENT_HEADER, delivery.event.as_str())
+        .header(DELIVERY_HEADER, delivery.id.as_str())
+        .header(SIGNATURE_HEADER, sign(&webhook.secret, &body))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| WebhookError::Http(e.to_string()))?;
+    let status = response.status().as_u16();
+    if !response.status().is_success() {
+        return Err(WebhookError::Status(status));
+    }
+    Ok(status)
+}
+
+// 除 408 和 429 外的 4xx 说明请求本身有问题，重试也不会成功
+fn retryable(error: &WebhookError) -> bool {
+    match error {
+        WebhookError::Http(_) => true,
+        WebhookError::Status(status) => !(400..500).contains(status) || matches!(status, 408 | 429),
+        _ => false,
+    }
+}
+
+fn record_result(
+    delivery: &mut WebhookDelivery,
+    result: Result<u16>,
+    now: DateTime<Utc>,
+    retry: bool,
+) {
+    delivery.attempts += 1;
+    match result {
+        Ok(status) => {
+            delivery.status = DeliveryStatus::Delivered as i32;
+            delivery.response_status = Some(status as i32);
+            delivery.delivered_at = Some(now);
+            delivery.last_error = None;
+        }
+        Err(e) => {
+            log::warn!(
+                "Failed to deliver {} to webhook {}: {}",
+                delivery.event,
+                delivery.webhook_id,
+                e
+            );
+            delivery.response_status = match e {
+                WebhookError::Status(status) => Some(status as i32),
+                _ => None,
+            };
+            if retry && retryable(&e) && delivery.attempts < MAX_ATTEMPTS {
+                delivery.next_attempt_at = now + backoff(delivery.attempts);
+            } else {
+                delivery.status = DeliveryStatus::Failed as i32;
+            }
+            delivery.last_error = Some(e.to_string());
+        }
+    }
+}
+
+// 发送所有到期的投递，失败的按退避时间重新排队
+pub async fn dispatch(storage: &Repositories, now: DateTime<Utc>) -> Result<()> {
+    let due = storage.webhook_deliveries.get_due(now)?;
+    if !due.is_empty() {
+        let webhooks: HashMap<String, Webhook> = storage
+            .webhooks
+            .get_all()?
+            .into_iter()
+            .map(|w| (w.id.clone(), w))
+            .collect();
+        let client = http_client()?;
+        for mut delivery in due {
+            let result = match webhooks.get(&delivery.webhook_id) {
+                Some(webhook) if webhook.enabled => send(&client, webhook, &delivery).await,
+                _ => Err(WebhookError::Config(format!(
+                    "webhook {} 不存在或已停用",
+                    delivery.webhook_id
+                ))),
+            };
+            record_result(&mut delivery, result, Utc::now(), true);
+            storage.webhook_deliveries.update(&delivery)?;
+        }
+    }
+
+    let pruned = storage
+        .webhook_deliveries
+        .prune(now - Duration::days(RETENTION_DAYS))?;
+    if pruned > 0 {
+        log::info!("Pruned {} old webhook deliveries", pruned);
+    }
+    Ok(())
+}
+
+// 立即发送一条测试事件，结果写入投递记录，不重试
+pub async fn test(storage: &Repositories, id: &str) -> Result<WebhookDelivery> {
+    let webhook = storage
+        .webhooks
+        .get_by_id(id)?
+        .ok_or_else(|| WebhookError::NotFound(id.to_string()))?;
+    let now = Utc::now();
+    let change = ChangeEvent {
+        id: 0,
+        entity: "webhook",
+        type_: events::ChangeType::Updated,
+        entity_id: Some(webhook.id.clone()),
+        data: Some(serde_json::json!({ "message": "Fates webhook test" })),
+        at: now,
+    };
+    let mut delivery = new_delivery(&webhook, TEST_EVENT, &change, now)?;
+    storage.webhook_deliveries.create(&delivery)?;
+    let result = send(&http_client()?, &webhook, &delivery).await;
+    record_result(&mut delivery, result, Utc::now(), false);
+    storage.webhook_deliveries.update(&delivery)?;
+    Ok(delivery)
+}
+
+async fn run_once() {
+    let Some(storage) = http_server::current_storage().await else {
+        return;
+    };
+    if let Err(e) = dispatch(&storage, Utc::now()).await {
+        log::error!("Failed to dispatch webhooks: {}", e);
+    }
+}
+
+// 一个任务把变更事件写成投递记录，另一个任务负责发送和重试
+pub fn start() {
+    tauri::async_runtime::spawn(async {
+        let mut receiver = events::listen();
+        loop {
+            let change = match receiver.recv().await {
+                Ok(change) => change,
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Webhook listener lagged, dropped {} events", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let Some(storage) = http_server::current_storage().await else {
+                continue;
+            };
+            match enqueue(&storage, &change, Utc::now()) {
+                Ok(0) => {}
+                Ok(_) => PENDING.notify_one(),
+                Err(e) => log::error!("Failed to queue webhook deliveries: {}", e),
+            }
+        }
+    });
+
+    tauri::async_runtime::spawn(async {
+        let interval = std::time::Duration::from_secs(RETRY_INTERVAL_SECS);
+        loop {
+            let _ = tokio::time::timeout(interval, PENDING.notified()).await;
+            run_once().await;
+        }
+    });
+}
+
+#[command]
+pub async fn list_webhooks() -> std::result::Result<Vec<Webhook>, String> {
+    let storage = reminder::command_storage().await?;
+    storage.webhooks.get_all().map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn create_webhook(webhook: Webhook) -> std::result::Result<CreatedWebhook, String> {
+    let storage = reminder::command_storage().await?;
+    create(&storage, webhook).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn update_webhook(id: String, webhook: Webhook) -> std::result::Result<Webhook, String> {
+    let storage = reminder::command_storage().await?;
+    update(&storage, &id, webhook).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn delete_webhook(id: String) -> std::result::Result<(), String> {
+    let storage = reminder::command_storage().await?;
+    storage.webhooks.delete(&id).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn get_webhook_deliveries(
+    id: String,
+) -> std::result::Result<Vec<WebhookDelivery>, String> {
+    let storage = reminder::command_storage().await?;
+    storage
+        .webhook_deliveries
+        .get_by_webhook(&id, DELIVERY_LOG_LIMIT)
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn test_webhook(id: String) -> std::result::Result<WebhookDelivery, String> {
+    let storage = reminder::command_storage().await?;
+    test(&storage, &id).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn webhook(events: &[&str]) -> Webhook {
+        serde_json::from_value(serde_json::json!({
+            "url": "http://127.0.0.1/hook",
+            "events": events,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn sign_matches_known_vector() {
+        // RFC 4231 测试用例 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn subscribes_by_pattern() {
+        assert!(subscribes(&webhook(&[]), "matter.created"));
+        assert!(subscribes(&webhook(&["*"]), "todo.completed"));
+
+        let matters = webhook(&["matter.*"]);
+        assert!(subscribes(&matters, "matter.created"));
+        assert!(subscribes(&matters, "matter.deleted"));
+        assert!(!subscribes(&matters, "todo.created"));
+        assert!(!subscribes(&matters, "matter_reminder.created"));
+
+        let exact = webhook(&["todo.completed", "tag.deleted"]);
+        assert!(subscribes(&exact, "todo.completed"));
+        assert!(subscribes(&exact, "tag.deleted"));
+        assert!(!subscribes(&exact, "todo.updated"));
+    }
+}
@@ -3,7 +3,7 @@ import { appConfig } from "$src/app-config";
 import { isHolidayDate } from "$src/i18n/holiday-cn";
 import { generateDescription, parseRepeatTimeString } from "$src/lib/utils/repeatTime";
 import { OpenAIClient } from "$src/openai";
-import platform from "$src/platform";
+import platform, { isTauri } from "$src/platform";
 import type { Matter, RepeatTask, Todo } from "$src/types";
 import dayjs from "dayjs";
 import { _ } from "svelte-i18n";
@@ -227,21 +227,24 @@ export class NotificationManager {
         );
 
         // Check repeat tasks
-        console.log(`[NotificationManager] Checking repeat tasks at ${currentDateTime.toLocaleString()}`);
-        const newlyCreatedMatters = await this.createDueRepeatTasks(currentDateTime, todayMatters);
-        if (newlyCreatedMatters.length > 0) {
-            console.log(
-                `[NotificationManager] Creating notification for new repeat tasks at ${currentDateTime.toLocaleString()}, ${
-                    newlyCreatedMatters.length
-                } new repeat tasks`
-            );
+        // In the desktop app the Rust repeat_scheduler generates repeat task matters
+        if (!isTauri) {
+            console.log(`[NotificationManager] Checking repeat tasks at ${currentDateTime.toLocaleString()}`);
+            const newlyCreatedMatters = await this.createDueRepeatTasks(currentDateTime, todayMatters);
+            if (newlyCreatedMatters.length > 0) {
+                console.log(
+                    `[NotificationManager] Creating notification for new repeat tasks at ${currentDateTime.toLocaleString()}, ${
+                        newlyCreatedMatters.length
+                    } new repeat tasks`
+                );
 
-            let notificationTitle = get(_)("app.messages.newRepeatTasks");
-            let notificationMessage = get(_)("app.messages.newRepeatTasksDescription", {
-                values: { count: newlyCreatedMatters.length },
-            });
-            this.onReceiveNotification(notificationTitle, notificationMessage, NotificationType.NewTask);
-            return true;
+                let notificationTitle = get(_)("app.messages.newRepeatTasks");
+                let notificationMessage = get(_)("app.messages.newRepeatTasksDescription", {
+                    values: { count: newlyCreatedMatters.length },
+                });
+                this.onReceiveNotification(notificationTitle, notificationMessage, NotificationType.NewTask);
+                return true;
+            }
         }
 
         let isAiEnabled = appConfig.getAIConfig().enabled;
@@ -400,7 +403,8 @@ export class NotificationManager {
         // Check upcoming tasks
         let notifications = appConfig.getNotifications();
         let shouldCheckUpcoming = false;
-        if (forceCheck) {
+        // In the desktop app the Rust reminder engine fires start/end notifications natively
+        if (forceCheck && !isTauri) {
             shouldCheckUpcoming = await this.isUpcomingCheckDue();
         }
         if (shouldCheckUpcoming) {
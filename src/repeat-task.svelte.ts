@@ -1,5 +1,5 @@
 import { Priority } from "$lib/types";
-import platform from "$src/platform";
+import platform, { isTauri } from "$src/platform";
 
 import type { RepeatTask, Matter } from "$src/types";
 import { v4 as uuidv4 } from "uuid";
@@ -38,6 +38,17 @@ class RepeatTaskAPI {
         }
     }
     public async createMatter(repeatTask: RepeatTask) {
+        // In the desktop app the matter is generated by Rust from the task's recurrence rule
+        if (isTauri) {
+            try {
+                const { createRepeatTaskMatter } = await import("$src/tauri/tauri-store");
+                await createRepeatTaskMatter(repeatTask.id);
+                console.log("[repeat-task] Matter created successfully");
+            } catch (error) {
+                console.error("[repeat-task] Failed to create matter:", error);
+            }
+            return;
+        }
         let components = repeatTask.repeat_time.split("|");
         if (components.length !== 3) {
             console.error("repeat_time format error", repeatTask.repeat_time);
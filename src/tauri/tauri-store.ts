@@ -1,3 +1,5 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { fetch } from "@tauri-apps/plugin-http";
 import type { Matter, NotificationRecord, RepeatTask, Todo } from "$src/types";
 
@@ -5,11 +7,49 @@ interface RequestOptions {
     debug?: boolean;
 }
 
-function getApiBaseUrl() {
-    // TODO: Custom api port
-    return "http://localhost:8523";
+interface ServerInfo {
+    host: string;
+    port: number;
+    base_url: string;
 }
 
+// 服务器实际监听的地址，配置的端口被占用时是备用端口；重启或修改配置后通过 http-server-changed 更新
+let apiBaseUrl: Promise<string> | null = null;
+
+listen<ServerInfo | null>("http-server-changed", (event) => {
+    apiBaseUrl = event.payload ? Promise.resolve(event.payload.base_url) : null;
+});
+
+const getApiBaseUrl = async () => {
+    apiBaseUrl ??= invoke<ServerInfo | null>("get_http_server_info").then((info) => {
+        if (!info) {
+            throw new Error("HTTP server is not running");
+        }
+        return info.base_url;
+    });
+    try {
+        return await apiBaseUrl;
+    } catch (error) {
+        // 服务器还没启动时不缓存失败结果，下次请求重新获取
+        apiBaseUrl = null;
+        throw error;
+    }
+};
+
+// 本次启动生成的应用令牌，只能通过 Tauri 命令取得
+let appToken: Promise<string> | null = null;
+
+const authHeaders = async (headers: Record<string, string> = {}) => {
+    appToken ??= invoke<string>("get_app_api_token");
+    try {
+        return { ...headers, Authorization: `Bearer ${await appToken}` };
+    } catch (error) {
+        // 服务器还没准备好时不缓存失败结果
+        appToken = null;
+        throw error;
+    }
+};
+
 
 // Debug utility
 const debug = (message: string, options?: RequestOptions) => {
@@ -24,9 +64,9 @@ const post = async (url: string, body: any, options?: RequestOptions) => {
     const response = await fetch(url, {
         method: "POST",
         body: JSON.stringify(body),
-        headers: {
+        headers: await authHeaders({
             "Content-Type": "application/json",
-        },
+        }),
     });
     const responseText = await response.text();
     debug(`ℹ️ POST ${url} , response: ${responseText}`, options);
@@ -35,7 +75,9 @@ const post = async (url: string, body: any, options?: RequestOptions) => {
 
 const get = async (url: string, options?: RequestOptions) => {
     debug(`⏳ GET ${url}`, options);
-    const response = await fetch(url);
+    const response = await fetch(url, {
+        headers: await authHeaders(),
+    });
     const responseText = await response.text();
     debug(`ℹ️ GET ${url} , response: ${responseText}`, options);
     return JSON.parse(responseText);
@@ -46,9 +88,9 @@ const put = async (url: string, body: any, options?: RequestOptions) => {
     const response = await fetch(url, {
         method: "PUT",
         body: JSON.stringify(body),
-        headers: {
+        headers: await authHeaders({
             "Content-Type": "application/json",
-        },
+        }),
     });
     const responseText = await response.text();
     debug(`ℹ️ PUT ${url} , response: ${responseText}`, options);
@@ -59,6 +101,7 @@ const delete_ = async (url: string, options?: RequestOptions) => {
     debug(`⏳ DELETE ${url}`, options);
     const response = await fetch(url, {
         method: "DELETE",
+        headers: await authHeaders(),
     });
     const responseText = await response.text();
     debug(`ℹ️ DELETE ${url} , response: ${responseText}`, options);
@@ -77,71 +120,72 @@ const processResponse = async (url: string, response: { code: number; msg: strin
 
 // Matter API
 export const createMatter = async (matter: Matter, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/matter`;
+    const url = `${await getApiBaseUrl()}/matter`;
     const response = await post(url, matter, options);
     return processResponse(url, response);
 };
 
 export const getMatterById = async (id: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/matter/${id}`;
+    const url = `${await getApiBaseUrl()}/matter/${id}`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const getAllMatters = async (options?: RequestOptions): Promise<Matter[]> => {
-    const url = `${getApiBaseUrl()}/matter`;
+    const url = `${await getApiBaseUrl()}/matter`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const queryMattersByField = async (field: string, value: string, exact_match: boolean, options?: RequestOptions): Promise<Matter[]> => {
-    const url = `${getApiBaseUrl()}/matter/query?field=${field}&value=${value}&exact_match=${exact_match}`;
+    const url = `${await getApiBaseUrl()}/matter/query?field=${field}&value=${value}&exact_match=${exact_match}`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const updateMatter = async (id: string, matter: Matter, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/matter/${id}`;
+    const url = `${await getApiBaseUrl()}/matter/${id}`;
     const response = await put(url, matter, options);
     return processResponse(url, response);
 };
 
 export const deleteMatter = async (id: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/matter/${id}`;
+    const url = `${await getApiBaseUrl()}/matter/${id}`;
     const response = await delete_(url, options);
     return processResponse(url, response);
 };
 
 export const getMattersByRange = async (start: string, end: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/matter/range?start=${start}&end=${end}`;
+    const url = `${await getApiBaseUrl()}/matter/range?start=${start}&end=${end}`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 // KVStore API
 export const setKV = async (key: string, value: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/kv/${key}`;
+    const url = `${await getApiBaseUrl()}/kv/${key}`;
     await fetch(url, {
         method: "PUT",
         body: value,
+        headers: await authHeaders(),
     });
 };
 
 export const getKV = async (key: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/kv/${key}`;
+    const url = `${await getApiBaseUrl()}/kv/${key}`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const deleteKV = async (key: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/kv/${key}`;
+    const url = `${await getApiBaseUrl()}/kv/${key}`;
     const response = await delete_(url, options);
     return processResponse(url, response);
 };
 
 // Tag API
 export const createTag = async (names: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/tags`;
+    const url = `${await getApiBaseUrl()}/tags`;
     const response = await post(url, {
         names: names
     }, options);
@@ -149,142 +193,149 @@ export const createTag = async (names: string, options?: RequestOptions) => {
 };
 
 export const getAllTags = async (options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/tags`;
+    const url = `${await getApiBaseUrl()}/tags`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const deleteTag = async (names: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/tags/${names}`;
+    const url = `${await getApiBaseUrl()}/tags/${names}`;
     const response = await delete_(url, options);
     return processResponse(url, response);
 };
 
 export const updateTagLastUsedAt = async (names: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/tags/update/${names}`;
+    const url = `${await getApiBaseUrl()}/tags/update/${names}`;
     const response = await put(url, {}, options);
     return processResponse(url, response);
 };
 
 // RepeatTask API
 export const createRepeatTask = async (task: RepeatTask, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/repeat-task`;
+    const url = `${await getApiBaseUrl()}/repeat-task`;
     const response = await post(url, task, options);
     return processResponse(url, response);
 };
 
 export const getRepeatTaskById = async (id: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/repeat-task/${id}`;
+    const url = `${await getApiBaseUrl()}/repeat-task/${id}`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const getAllRepeatTasks = async (options?: RequestOptions): Promise<RepeatTask[]> => {
-    const url = `${getApiBaseUrl()}/repeat-task`;
+    const url = `${await getApiBaseUrl()}/repeat-task`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const getActiveRepeatTasks = async (options?: RequestOptions): Promise<RepeatTask[]> => {
-    const url = `${getApiBaseUrl()}/repeat-task/active`;
+    const url = `${await getApiBaseUrl()}/repeat-task/active`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const updateRepeatTask = async (id: string, task: RepeatTask, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/repeat-task/${id}`;
+    const url = `${await getApiBaseUrl()}/repeat-task/${id}`;
     const response = await put(url, task, options);
     return processResponse(url, response);
 };
 
 export const deleteRepeatTask = async (id: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/repeat-task/${id}`;
+    const url = `${await getApiBaseUrl()}/repeat-task/${id}`;
     const response = await delete_(url, options);
     return processResponse(url, response);
 };
 
 export const updateRepeatTaskStatus = async (id: string, status: number, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/repeat-task/${id}/status/${status}`;
+    const url = `${await getApiBaseUrl()}/repeat-task/${id}/status/${status}`;
     const response = await put(url, {}, options);
     return processResponse(url, response);
 };
 
+// 立即为重复任务生成今天的 matter
+export const createRepeatTaskMatter = async (id: string, options?: RequestOptions): Promise<Matter> => {
+    const url = `${await getApiBaseUrl()}/repeat-task/${id}/matter`;
+    const response = await post(url, {}, options);
+    return processResponse(url, response);
+};
+
 // Todo API
 export const createTodo = async (todo: Todo, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/todo`;
+    const url = `${await getApiBaseUrl()}/todo`;
     const response = await post(url, todo, options);
     return processResponse(url, response);
 };
 
 export const getTodoById = async (id: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/todo/${id}`;
+    const url = `${await getApiBaseUrl()}/todo/${id}`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const getAllTodos = async (options?: RequestOptions): Promise<Todo[]> => {
-    const url = `${getApiBaseUrl()}/todo`;
+    const url = `${await getApiBaseUrl()}/todo`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const updateTodo = async (id: string, todo: Todo, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/todo/${id}`;
+    const url = `${await getApiBaseUrl()}/todo/${id}`;
     const response = await put(url, todo, options);
     return processResponse(url, response);
 };
 
 export const deleteTodo = async (id: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/todo/${id}`;
+    const url = `${await getApiBaseUrl()}/todo/${id}`;
     const response = await delete_(url, options);
     return processResponse(url, response);
 };
 
 // Notification API
 export const createNotification = async (notification: NotificationRecord, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/notification`;
+    const url = `${await getApiBaseUrl()}/notification`;
     const response = await post(url, notification, options);
     return processResponse(url, response);
 };
 
 export const getNotificationById = async (id: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/notification/${id}`;
+    const url = `${await getApiBaseUrl()}/notification/${id}`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const getUnreadNotifications = async (options?: RequestOptions): Promise<NotificationRecord[]> => {
-    const url = `${getApiBaseUrl()}/notification/unread`;
+    const url = `${await getApiBaseUrl()}/notification/unread`;
     const response = await get(url, options);
     return processResponse(url, response);
 };
 
 export const updateNotification = async (id: string, notification: NotificationRecord, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/notification/${id}`;
+    const url = `${await getApiBaseUrl()}/notification/${id}`;
     const response = await put(url, notification, options);
     return processResponse(url, response);
 };
 
 export const deleteNotification = async (id: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/notification/${id}`;
+    const url = `${await getApiBaseUrl()}/notification/${id}`;
     const response = await delete_(url, options);
     return processResponse(url, response);
 };
 
 export const markNotificationAsRead = async (id: string, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/notification/${id}/read`;
+    const url = `${await getApiBaseUrl()}/notification/${id}/read`;
     const response = await put(url, {}, options);
     return processResponse(url, response);
 };
 
 export const markNotificationAsReadByType = async (type_: number, options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/notification/read/${type_}`;
+    const url = `${await getApiBaseUrl()}/notification/read/${type_}`;
     const response = await put(url, {}, options);
     return processResponse(url, response);
 };
 
 export const markAllNotificationsAsRead = async (options?: RequestOptions) => {
-    const url = `${getApiBaseUrl()}/notification/read-all`;
+    const url = `${await getApiBaseUrl()}/notification/read-all`;
     const response = await put(url, {}, options);
     return processResponse(url, response);
 };
@@ -15,6 +15,7 @@ export interface Matter {
     reserved_3?: string;
     reserved_4?: string;
     reserved_5?: string;
+    tz?: string;
 }
 
 export interface RepeatTask {
@@ -27,6 +28,10 @@ export interface RepeatTask {
     updated_at: string;
     priority: number;
     description?: string;
+    recurrence?: string;
+    skip_holidays?: boolean;
+    makeup_workdays?: boolean;
+    tz?: string;
 }
 
 export interface Todo {
@@ -35,7 +40,12 @@ export interface Todo {
     status: string; // "todo", "in_progress", "completed"
     created_at: string;
     updated_at: string;
+    due_date?: string;
     start_time?: string;
+    priority?: number;
+    notes?: string;
+    tags?: string;
+    sort_order?: number;
 }
 
 export interface NotificationRecord {
@@ -54,6 +64,12 @@ export interface NotificationRecord {
     reserved_3?: string;
     reserved_4?: string;
     reserved_5?: string;
+    acknowledged_at?: string;
+    next_notify_at?: string;
+    escalate?: boolean;
+    escalation_count?: number;
+    // 0 pending, 1 delivered, 2 failed, 3 partial
+    delivery_status?: number;
 }
 
 export interface Tag {